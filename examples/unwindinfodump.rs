@@ -1,8 +1,7 @@
-use std::{fmt::Display, fs::File, io::Read};
+use std::{fs::File, io::Read};
 
-use macho_unwind_info::opcodes::{OpcodeArm64, OpcodeX86, OpcodeX86_64};
-use macho_unwind_info::UnwindInfo;
-use object::{Architecture, ObjectSection};
+use macho_unwind_info::{Arch, Symbol, UnwindInfo};
+use object::{Architecture, ObjectSection, ObjectSegment, ObjectSymbol, SymbolKind};
 
 fn main() {
     let mut args = std::env::args_os().skip(1);
@@ -23,7 +22,32 @@ fn main() {
         .section_by_name_bytes(b"__unwind_info")
         .expect("Could not find __unwind_info section");
     let data = unwind_info_data_section.data().unwrap();
-    let arch = file.architecture();
+    let arch = match file.architecture() {
+        Architecture::I386 => Arch::X86,
+        Architecture::X86_64 => Arch::X86_64,
+        Architecture::Aarch64 => Arch::Arm64,
+        arch => {
+            eprintln!("Unsupported architecture {:?}", arch);
+            std::process::exit(1);
+        }
+    };
+
+    // Function addresses in __unwind_info are relative to the __TEXT segment.
+    let base_address = file
+        .segments()
+        .find(|segment| segment.name() == Ok(Some("__TEXT")))
+        .expect("Could not find __TEXT segment")
+        .address();
+    let symbols: Vec<Symbol> = file
+        .symbols()
+        .filter(|symbol| symbol.kind() == SymbolKind::Text && symbol.is_definition())
+        .filter_map(|symbol| {
+            Some(Symbol {
+                address: u32::try_from(symbol.address().checked_sub(base_address)?).ok()?,
+                name: symbol.name().ok()?,
+            })
+        })
+        .collect();
 
     let info = UnwindInfo::parse(data).unwrap();
     let address_range = info.address_range();
@@ -32,21 +56,5 @@ fn main() {
         address_range.start, address_range.end
     );
     println!();
-    let mut function_iter = info.functions();
-    while let Some(function) = function_iter.next().unwrap() {
-        print_entry(function.start_address, function.opcode, arch);
-    }
-}
-
-fn print_entry(address: u32, opcode: u32, arch: Architecture) {
-    match arch {
-        Architecture::I386 => print_entry_impl(address, OpcodeX86::parse(opcode)),
-        Architecture::X86_64 => print_entry_impl(address, OpcodeX86_64::parse(opcode)),
-        Architecture::Aarch64 => print_entry_impl(address, OpcodeArm64::parse(opcode)),
-        _ => {}
-    }
-}
-
-fn print_entry_impl(address: u32, opcode: impl Display) {
-    println!("0x{:08x}: {}", address, opcode);
+    print!("{}", info.symbolized_report(arch, &symbols).unwrap());
 }
@@ -1,7 +1,7 @@
-use std::{fmt::Display, fs::File, io::Read};
+use std::{fs::File, io::Read};
 
-use macho_unwind_info::opcodes::{OpcodeArm64, OpcodeX86, OpcodeX86_64};
-use macho_unwind_info::UnwindInfo;
+use macho_unwind_info::opcodes::{Arm64, CompactArch, CompactOpcode, X86, X86_64};
+use macho_unwind_info::{Function, UnwindInfo};
 use object::{Architecture, ObjectSection};
 
 fn main() {
@@ -32,8 +32,8 @@ fn main() {
     let arch = file.architecture();
 
     let unwind_info = UnwindInfo::parse(data).unwrap();
-    let function = match unwind_info.lookup(pc) {
-        Ok(Some(f)) => f,
+    let details = match unwind_info.lookup_details(pc) {
+        Ok(Some(details)) => details,
         Ok(None) => {
             println!("No entry was found for address 0x{:x}", pc);
             std::process::exit(1);
@@ -46,21 +46,19 @@ fn main() {
             std::process::exit(1);
         }
     };
-    print_entry(function.start_address, function.opcode, arch);
-}
-
-fn print_entry(address: u32, opcode: u32, arch: Architecture) {
     match arch {
-        Architecture::I386 => print_entry_impl(address, OpcodeX86::parse(opcode)),
-        Architecture::X86_64 => print_entry_impl(address, OpcodeX86_64::parse(opcode)),
-        Architecture::Aarch64 => print_entry_impl(address, OpcodeArm64::parse(opcode)),
+        Architecture::I386 => print_entry::<X86>(&details.function),
+        Architecture::X86_64 => print_entry::<X86_64>(&details.function),
+        Architecture::Aarch64 => print_entry::<Arm64>(&details.function),
         _ => {}
     }
+    println!("{}", details);
 }
 
-fn print_entry_impl(address: u32, opcode: impl Display) {
+fn print_entry<A: CompactArch>(function: &Function) {
     println!(
         "Found entry with function address 0x{:08x} and opcode {}",
-        address, opcode
+        function.start_address,
+        A::Opcode::parse(function.opcode)
     );
 }
@@ -0,0 +1,45 @@
+use crate::opcodes::{OpcodeArm64, OpcodeX86, OpcodeX86_64, RegisterNames};
+
+/// The CPU architectures which use the compact unwinding format.
+///
+/// The `__unwind_info` section itself doesn't record which architecture it
+/// belongs to; this information comes from the containing mach-O binary.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Arch {
+    X86,
+    X86_64,
+    Arm64,
+}
+
+impl Arch {
+    /// Returns an object which formats `opcode` with the opcode parser for
+    /// this architecture. Registers are printed as DWARF register numbers.
+    pub fn display_opcode(self, opcode: u32) -> impl core::fmt::Display {
+        self.display_opcode_with(opcode, RegisterNames::Dwarf)
+    }
+
+    /// Like [`Arch::display_opcode`], but with the chosen register names.
+    pub fn display_opcode_with(self, opcode: u32, names: RegisterNames) -> impl core::fmt::Display {
+        OpcodeDisplay {
+            arch: self,
+            opcode,
+            names,
+        }
+    }
+}
+
+struct OpcodeDisplay {
+    arch: Arch,
+    opcode: u32,
+    names: RegisterNames,
+}
+
+impl core::fmt::Display for OpcodeDisplay {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.arch {
+            Arch::X86 => OpcodeX86::parse(self.opcode).display(self.names).fmt(f),
+            Arch::X86_64 => OpcodeX86_64::parse(self.opcode).display(self.names).fmt(f),
+            Arch::Arm64 => OpcodeArm64::parse(self.opcode).display(self.names).fmt(f),
+        }
+    }
+}
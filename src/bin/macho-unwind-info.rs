@@ -0,0 +1,690 @@
+//! Command-line tool for inspecting the `__unwind_info` section of mach-O binaries.
+
+use std::io::{BufRead, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use macho_unwind_info::opcodes::RegisterNames;
+use macho_unwind_info::{
+    Arch, EndAddressSource, Function, LookupDetails, OpcodeSource, PageKind, Symbol, UnwindInfo,
+};
+use object::read::macho::{FatArch, MachOFatFile32, MachOFatFile64};
+use object::{
+    Architecture, FileKind, Object, ObjectSection, ObjectSegment, ObjectSymbol, SymbolKind,
+};
+use serde_json::json;
+
+/// Exit code when a looked-up address or part of the text section is not
+/// covered by the unwind info.
+const EXIT_NOT_COVERED: u8 = 1;
+/// Exit code for invalid arguments. This matches the exit code used by clap.
+const EXIT_USAGE: u8 = 2;
+/// Exit code when the unwind info is malformed.
+const EXIT_MALFORMED: u8 = 3;
+/// Exit code when the input file can't be read or doesn't contain unwind info,
+/// or when the output can't be written.
+const EXIT_INPUT: u8 = 4;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Inspect the __unwind_info section of mach-O binaries",
+    after_help = "Exit codes:\n  \
+        0  success\n  \
+        1  an address or part of the text section is not covered by the unwind info\n  \
+        2  invalid arguments\n  \
+        3  the unwind info is malformed\n  \
+        4  the input can't be read or has no unwind info, or the output can't be written"
+)]
+struct Cli {
+    /// The architecture slice to use. Required for raw `__unwind_info`
+    /// files and for fat binaries with more than one slice.
+    #[arg(long, global = true)]
+    arch: Option<CliArch>,
+
+    /// The output format.
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// How registers are named in opcode descriptions.
+    #[arg(long, global = true, value_enum, default_value_t = CliRegisters::Dwarf)]
+    registers: CliRegisters,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print every function entry, with symbol names if available.
+    Dump { path: PathBuf },
+
+    /// Look up addresses, relative to the start of the __TEXT segment.
+    /// Addresses are read from stdin, one per line, if none are given.
+    Lookup {
+        path: PathBuf,
+        addresses: Vec<String>,
+
+        /// Explain how each function was found: the page and entry, where the
+        /// opcode and the end address came from, the personality and the LSDA.
+        #[arg(long)]
+        explain: bool,
+    },
+
+    /// Check that the whole section can be parsed.
+    Validate { path: PathBuf },
+
+    /// List the parts of the __text section without usable unwind info,
+    /// grouped by symbol, and the symbols which the linker merged into a
+    /// neighbor's entry. Only uncovered code makes the check fail. Needs a
+    /// mach-O file.
+    Coverage { path: PathBuf },
+
+    /// Print statistics about the opcodes and the section layout.
+    Stats { path: PathBuf },
+
+    /// Print the section in the layout of `llvm-objdump --unwind-info`, for
+    /// diffing the two outputs.
+    Objdump { path: PathBuf },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CliArch {
+    #[value(alias = "i386")]
+    X86,
+    #[value(name = "x86_64")]
+    X86_64,
+    #[value(alias = "aarch64")]
+    Arm64,
+}
+
+impl From<CliArch> for Arch {
+    fn from(arch: CliArch) -> Self {
+        match arch {
+            CliArch::X86 => Arch::X86,
+            CliArch::X86_64 => Arch::X86_64,
+            CliArch::Arm64 => Arch::Arm64,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CliRegisters {
+    /// DWARF register numbers, such as `reg6`.
+    Dwarf,
+    /// Assembly names, such as `rbp`.
+    Assembly,
+}
+
+impl From<CliRegisters> for RegisterNames {
+    fn from(registers: CliRegisters) -> Self {
+        match registers {
+            CliRegisters::Dwarf => RegisterNames::Dwarf,
+            CliRegisters::Assembly => RegisterNames::Assembly,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+enum CliError {
+    Usage(String),
+    Input(String),
+    Malformed(macho_unwind_info::Error),
+    Output(std::io::Error),
+}
+
+/// Like `print!`, but returns write errors as [`CliError::Output`] instead of
+/// panicking, so that a closed pipe ends the output quietly.
+macro_rules! out {
+    ($($arg:tt)*) => {
+        write!(std::io::stdout(), $($arg)*).map_err(CliError::Output)?
+    };
+}
+
+/// Like `println!`, but returns write errors as [`CliError::Output`].
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        writeln!(std::io::stdout(), $($arg)*).map_err(CliError::Output)?
+    };
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::Input(_) => EXIT_INPUT,
+            CliError::Malformed(_) => EXIT_MALFORMED,
+            CliError::Output(_) => EXIT_INPUT,
+        }
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage(message) | CliError::Input(message) => f.write_str(message),
+            CliError::Malformed(err) => write!(f, "Malformed unwind info: {}", err),
+            CliError::Output(err) => write!(f, "Could not write the output: {}", err),
+        }
+    }
+}
+
+impl From<macho_unwind_info::Error> for CliError {
+    fn from(err: macho_unwind_info::Error) -> Self {
+        CliError::Malformed(err)
+    }
+}
+
+/// The `__unwind_info` section of the selected slice, and the `__text`
+/// section range and function symbols if the input was a mach-O binary.
+struct Input<'data> {
+    arch: Arch,
+    unwind_info: &'data [u8],
+    text_range: Option<Range<u32>>,
+    symbols: Vec<Symbol<'data>>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli) {
+        Ok(code) => ExitCode::from(code),
+        // The reader of the output went away, e.g. `head` got enough lines.
+        Err(CliError::Output(err)) if err.kind() == std::io::ErrorKind::BrokenPipe => {
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            match cli.format {
+                Format::Text => eprintln!("error: {}", err),
+                Format::Json => {
+                    let _ = writeln!(std::io::stdout(), "{}", json!({ "error": err.to_string() }));
+                }
+            }
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<u8, CliError> {
+    let path = match &cli.command {
+        Command::Dump { path }
+        | Command::Lookup { path, .. }
+        | Command::Validate { path }
+        | Command::Coverage { path }
+        | Command::Stats { path }
+        | Command::Objdump { path } => path,
+    };
+    let data = std::fs::read(path)
+        .map_err(|err| CliError::Input(format!("Could not read {}: {}", path.display(), err)))?;
+    let input = load_input(path, &data, cli.arch.map(Arch::from))?;
+    let info = UnwindInfo::parse(input.unwind_info)?;
+    match &cli.command {
+        Command::Dump { .. } => dump(&info, &input, cli.registers.into(), cli.format),
+        Command::Lookup {
+            addresses, explain, ..
+        } => {
+            let addresses = if addresses.is_empty() {
+                std::io::stdin()
+                    .lock()
+                    .lines()
+                    .map(|line| line.map_err(|err| CliError::Input(err.to_string())))
+                    .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+                    .collect::<Result<Vec<_>, _>>()?
+            } else {
+                addresses.clone()
+            };
+            lookup(
+                &info,
+                &input,
+                &addresses,
+                *explain,
+                cli.registers.into(),
+                cli.format,
+            )
+        }
+        Command::Validate { .. } => validate(&info, cli.format),
+        Command::Coverage { .. } => coverage(&info, &input, cli.format),
+        Command::Stats { .. } => stats(&info, input.arch, cli.format),
+        Command::Objdump { .. } => objdump(&info, cli.format),
+    }
+}
+
+fn load_input<'data>(
+    path: &Path,
+    data: &'data [u8],
+    arch: Option<Arch>,
+) -> Result<Input<'data>, CliError> {
+    match FileKind::parse(data) {
+        Ok(FileKind::MachOFat32) => {
+            let fat = MachOFatFile32::parse(data)
+                .map_err(|err| CliError::Input(format!("Could not parse fat header: {}", err)))?;
+            load_fat_slice(data, fat.arches(), arch)
+        }
+        Ok(FileKind::MachOFat64) => {
+            let fat = MachOFatFile64::parse(data)
+                .map_err(|err| CliError::Input(format!("Could not parse fat header: {}", err)))?;
+            load_fat_slice(data, fat.arches(), arch)
+        }
+        Ok(FileKind::MachO32 | FileKind::MachO64) => load_macho(data, arch),
+        _ => match arch {
+            // Not a mach-O file: treat the whole file as the section contents.
+            Some(arch) => Ok(Input {
+                arch,
+                unwind_info: data,
+                text_range: None,
+                symbols: Vec::new(),
+            }),
+            None => Err(CliError::Usage(format!(
+                "{} is not a mach-O file; pass --arch to read it as a raw __unwind_info section",
+                path.display()
+            ))),
+        },
+    }
+}
+
+fn load_fat_slice<'data>(
+    data: &'data [u8],
+    fat_arches: &[impl FatArch],
+    arch: Option<Arch>,
+) -> Result<Input<'data>, CliError> {
+    let slices: Vec<(Option<Arch>, &[u8])> = fat_arches
+        .iter()
+        .map(|fat_arch| {
+            let slice = fat_arch.data(data).map_err(|err| {
+                CliError::Input(format!("Could not read fat binary slice: {}", err))
+            })?;
+            Ok((convert_arch(fat_arch.architecture()), slice))
+        })
+        .collect::<Result<_, CliError>>()?;
+    let available = || {
+        slices
+            .iter()
+            .filter_map(|(arch, _)| *arch)
+            .map(arch_name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let slice = match arch {
+        Some(arch) => slices
+            .iter()
+            .find(|(slice_arch, _)| *slice_arch == Some(arch))
+            .ok_or_else(|| {
+                CliError::Input(format!(
+                    "The fat binary has no {} slice (available: {})",
+                    arch_name(arch),
+                    available()
+                ))
+            })?,
+        None if slices.len() == 1 => &slices[0],
+        None => {
+            return Err(CliError::Usage(format!(
+                "The fat binary has several slices; pick one with --arch (available: {})",
+                available()
+            )))
+        }
+    };
+    load_macho(slice.1, arch)
+}
+
+fn load_macho(data: &[u8], arch: Option<Arch>) -> Result<Input<'_>, CliError> {
+    let file = object::File::parse(data)
+        .map_err(|err| CliError::Input(format!("Could not parse mach-O file: {}", err)))?;
+    let file_arch = convert_arch(file.architecture()).ok_or_else(|| {
+        CliError::Input(format!(
+            "Unsupported architecture {:?}",
+            file.architecture()
+        ))
+    })?;
+    if let Some(arch) = arch {
+        if arch != file_arch {
+            return Err(CliError::Input(format!(
+                "The file is {}, not {}",
+                arch_name(file_arch),
+                arch_name(arch)
+            )));
+        }
+    }
+    let unwind_info = file
+        .section_by_name_bytes(b"__unwind_info")
+        .ok_or_else(|| CliError::Input("Could not find __unwind_info section".to_string()))?
+        .data()
+        .map_err(|err| CliError::Input(format!("Could not read __unwind_info: {}", err)))?;
+
+    // Function addresses in __unwind_info are relative to the __TEXT segment.
+    let base_address = file
+        .segments()
+        .find(|segment| segment.name() == Ok(Some("__TEXT")))
+        .ok_or_else(|| CliError::Input("Could not find __TEXT segment".to_string()))?
+        .address();
+    let symbols = file
+        .symbols()
+        .filter(|symbol| symbol.kind() == SymbolKind::Text && symbol.is_definition())
+        .filter_map(|symbol| {
+            Some(Symbol {
+                address: u32::try_from(symbol.address().checked_sub(base_address)?).ok()?,
+                name: symbol.name().ok()?,
+            })
+        })
+        .collect();
+    let text_range = file.section_by_name_bytes(b"__text").and_then(|section| {
+        let start = u32::try_from(section.address().checked_sub(base_address)?).ok()?;
+        let end = start.checked_add(u32::try_from(section.size()).ok()?)?;
+        Some(start..end)
+    });
+    Ok(Input {
+        arch: file_arch,
+        unwind_info,
+        text_range,
+        symbols,
+    })
+}
+
+fn convert_arch(arch: Architecture) -> Option<Arch> {
+    match arch {
+        Architecture::I386 => Some(Arch::X86),
+        Architecture::X86_64 => Some(Arch::X86_64),
+        Architecture::Aarch64 => Some(Arch::Arm64),
+        _ => None,
+    }
+}
+
+fn arch_name(arch: Arch) -> &'static str {
+    match arch {
+        Arch::X86 => "x86",
+        Arch::X86_64 => "x86_64",
+        Arch::Arm64 => "arm64",
+    }
+}
+
+fn function_json(arch: Arch, names: RegisterNames, function: &Function) -> serde_json::Value {
+    json!({
+        "start_address": function.start_address,
+        "end_address": function.end_address,
+        "opcode": function.opcode,
+        "description": arch.display_opcode_with(function.opcode, names).to_string(),
+    })
+}
+
+fn dump(
+    info: &UnwindInfo,
+    input: &Input,
+    names: RegisterNames,
+    format: Format,
+) -> Result<u8, CliError> {
+    let mut report = info.symbolized_report(input.arch, &input.symbols)?;
+    report.register_names = names;
+    match format {
+        Format::Text => {
+            let range = info.address_range();
+            outln!(
+                "Unwind info for address range 0x{:08x}-0x{:08x}",
+                range.start,
+                range.end
+            );
+            outln!();
+            out!("{}", report);
+        }
+        Format::Json => {
+            let entries: Vec<_> = report
+                .entries
+                .iter()
+                .map(|entry| {
+                    let mut value = function_json(input.arch, names, &entry.function);
+                    value["symbol"] = json!(entry.symbol.map(|symbol| symbol.name));
+                    value["offset_from_symbol"] = json!(entry.offset_from_symbol);
+                    value["warnings"] = entry
+                        .warnings
+                        .iter()
+                        .map(|warning| warning.to_string())
+                        .collect();
+                    value
+                })
+                .collect();
+            outln!("{}", json!({ "functions": entries }));
+        }
+    }
+    Ok(0)
+}
+
+fn parse_address(address: &str) -> Result<u32, CliError> {
+    let address = address.trim();
+    let parsed = match address.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => address.parse(),
+    };
+    parsed.map_err(|_| CliError::Usage(format!("Invalid address {:?}", address)))
+}
+
+fn lookup(
+    info: &UnwindInfo,
+    input: &Input,
+    addresses: &[String],
+    explain: bool,
+    names: RegisterNames,
+    format: Format,
+) -> Result<u8, CliError> {
+    let addresses = addresses
+        .iter()
+        .map(|address| parse_address(address))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut exit_code = 0;
+    let mut results = Vec::new();
+    for address in addresses {
+        let (function, details) = if explain {
+            let details = info.lookup_details(address)?;
+            (
+                details.as_ref().map(|details| details.function.clone()),
+                details,
+            )
+        } else {
+            (info.lookup(address)?, None)
+        };
+        if function.is_none() {
+            exit_code = EXIT_NOT_COVERED;
+        }
+        match format {
+            Format::Text => {
+                match function {
+                    Some(function) => outln!(
+                        "0x{:x}: 0x{:08x}..0x{:08x} {}",
+                        address,
+                        function.start_address,
+                        function.end_address,
+                        input.arch.display_opcode_with(function.opcode, names)
+                    ),
+                    None => outln!("0x{:x}: not covered", address),
+                }
+                if let Some(details) = details {
+                    for line in details.to_string().lines() {
+                        outln!("    {}", line);
+                    }
+                }
+            }
+            Format::Json => {
+                let mut result = json!({
+                    "address": address,
+                    "function": function.map(|function| function_json(input.arch, names, &function)),
+                });
+                if explain {
+                    result["details"] = json!(details.map(|details| details_json(&details)));
+                }
+                results.push(result);
+            }
+        }
+    }
+    if format == Format::Json {
+        outln!("{}", json!({ "lookups": results }));
+    }
+    Ok(exit_code)
+}
+
+fn details_json(details: &LookupDetails) -> serde_json::Value {
+    let (opcode_palette, opcode_index) = match details.opcode_source {
+        OpcodeSource::GlobalPalette { index } => ("global", Some(index)),
+        OpcodeSource::LocalPalette { index } => ("local", Some(index)),
+        OpcodeSource::Inline => ("inline", None),
+    };
+    json!({
+        "page_index": details.page_index,
+        "page_kind": match details.page_kind {
+            PageKind::Regular => "regular",
+            PageKind::Compressed => "compressed",
+        },
+        "entry_index": details.entry_index,
+        "compressed_entry": details.compressed_entry.map(|entry| entry.0),
+        "opcode_palette": opcode_palette,
+        "opcode_index": opcode_index,
+        "end_address_from": match details.end_address_source {
+            EndAddressSource::NextEntry => "next_entry",
+            EndAddressSource::NextPage => "next_page",
+        },
+        "personality_index": details.personality_index,
+        "personality": details.personality,
+        "lsda": details.lsda,
+    })
+}
+
+fn validate(info: &UnwindInfo, format: Format) -> Result<u8, CliError> {
+    let mut function_count = 0;
+    let mut iter = info.functions();
+    while iter.next()?.is_some() {
+        function_count += 1;
+    }
+    // The stats walk every structure in the section, including the palettes.
+    info.stats()?;
+    match format {
+        Format::Text => outln!("ok: {} functions", function_count),
+        Format::Json => outln!("{}", json!({ "valid": true, "functions": function_count })),
+    }
+    Ok(0)
+}
+
+fn coverage(info: &UnwindInfo, input: &Input, format: Format) -> Result<u8, CliError> {
+    let text_range = input.text_range.clone().ok_or_else(|| {
+        CliError::Usage("Coverage analysis needs a mach-O file with a __text section".to_string())
+    })?;
+    let report = info.coverage(text_range, &input.symbols)?;
+    match format {
+        Format::Text => out!("{}", report),
+        Format::Json => {
+            let range_json = |range: &Range<u32>| json!({ "start": range.start, "end": range.end });
+            let symbols: Vec<_> = report
+                .symbols
+                .iter()
+                .map(|symbol| {
+                    json!({
+                        "symbol": symbol.symbol.name,
+                        "address": symbol.symbol.address,
+                        "issues": symbol.issues.iter().map(|issue| issue.to_string()).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            let uncovered: Vec<_> = report.uncovered_ranges.iter().map(range_json).collect();
+            outln!(
+                "{}",
+                json!({ "uncovered_ranges": uncovered, "symbols": symbols })
+            );
+        }
+    }
+    // Merged symbols are informational, so they don't fail the check.
+    Ok(if report.has_uncovered() {
+        EXIT_NOT_COVERED
+    } else {
+        0
+    })
+}
+
+fn stats(info: &UnwindInfo, arch: Arch, format: Format) -> Result<u8, CliError> {
+    let stats = info.stats()?;
+    let kinds = stats.opcode_kinds(arch);
+    let sizes = &stats.sizes;
+    match format {
+        Format::Text => {
+            outln!("functions: {}", stats.function_count());
+            outln!("  null: {}", kinds.null);
+            outln!("  frame-based: {}", kinds.frame_based);
+            outln!("  frameless immediate: {}", kinds.frameless_immediate);
+            outln!("  frameless indirect: {}", kinds.frameless_indirect);
+            outln!("  dwarf: {}", kinds.dwarf);
+            outln!("  unrecognized: {}", kinds.unrecognized);
+            outln!(
+                "eh_frame fraction: {:.1}%",
+                stats.eh_frame_fraction(arch) * 100.0
+            );
+            outln!(
+                "pages: {} regular, {} compressed, {:.1} functions per page",
+                stats.regular_page_count,
+                stats.compressed_page_count,
+                stats.average_functions_per_page()
+            );
+            outln!(
+                "entries: {} regular, {} global palette, {} local palette",
+                stats.regular_entry_count,
+                stats.global_palette_entry_count,
+                stats.local_palette_entry_count
+            );
+            outln!(
+                "opcodes: {} global, {} local",
+                stats.global_opcode_count,
+                stats.local_opcode_count
+            );
+            outln!("sizes (bytes):");
+            outln!("  header: {}", sizes.header);
+            outln!("  global opcodes: {}", sizes.global_opcodes);
+            outln!("  personalities: {}", sizes.personalities);
+            outln!("  page entries: {}", sizes.page_entries);
+            outln!("  lsdas: {}", sizes.lsdas);
+            outln!("  regular pages: {}", sizes.regular_pages);
+            outln!("  compressed pages: {}", sizes.compressed_pages);
+            outln!("  total: {}", sizes.total);
+        }
+        Format::Json => {
+            let value = json!({
+                "functions": stats.function_count(),
+                "opcode_kinds": {
+                    "null": kinds.null,
+                    "frame_based": kinds.frame_based,
+                    "frameless_immediate": kinds.frameless_immediate,
+                    "frameless_indirect": kinds.frameless_indirect,
+                    "dwarf": kinds.dwarf,
+                    "unrecognized": kinds.unrecognized,
+                },
+                "eh_frame_fraction": stats.eh_frame_fraction(arch),
+                "regular_page_count": stats.regular_page_count,
+                "compressed_page_count": stats.compressed_page_count,
+                "regular_entry_count": stats.regular_entry_count,
+                "global_palette_entry_count": stats.global_palette_entry_count,
+                "local_palette_entry_count": stats.local_palette_entry_count,
+                "global_opcode_count": stats.global_opcode_count,
+                "local_opcode_count": stats.local_opcode_count,
+                "sizes": {
+                    "header": sizes.header,
+                    "global_opcodes": sizes.global_opcodes,
+                    "personalities": sizes.personalities,
+                    "page_entries": sizes.page_entries,
+                    "lsdas": sizes.lsdas,
+                    "regular_pages": sizes.regular_pages,
+                    "compressed_pages": sizes.compressed_pages,
+                    "total": sizes.total,
+                },
+            });
+            outln!("{}", value);
+        }
+    }
+    Ok(0)
+}
+
+fn objdump(info: &UnwindInfo, format: Format) -> Result<u8, CliError> {
+    let listing = info.objdump_listing()?;
+    match format {
+        Format::Text => out!("{}", listing),
+        Format::Json => outln!("{}", json!({ "listing": listing.to_string() })),
+    }
+    Ok(0)
+}
@@ -0,0 +1,343 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::opcodes::permutation::encode_permutation_6;
+use crate::opcodes::{OpcodeArm64, OpcodeX86, OpcodeX86_64, RegisterNameX86, RegisterNameX86_64};
+use crate::raw::consts::*;
+use crate::{Arch, CfaRule, CfiRow, EhFrame, RegisterRule};
+
+/// A reason why unwind rules can't be expressed as a compact unwind opcode,
+/// as returned by [`Arch::compactify`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NotCompactable {
+    /// There is no FDE at the offset, or its CFI could not be evaluated.
+    InvalidCfi,
+
+    /// The CFA is computed by a DWARF expression.
+    CfaExpression,
+
+    /// The CFA is based on a register other than the frame pointer or the
+    /// stack pointer.
+    UnsupportedCfaRegister(u16),
+
+    /// The CFA offset is not a multiple of the stack alignment: the word size
+    /// on x86 and x86_64, 16 bytes on arm64.
+    UnalignedCfaOffset(i64),
+
+    /// The CFA is based on the frame pointer, but not at two words above it.
+    UnexpectedCfaOffset(i64),
+
+    /// The stack size is too large for the opcode. x86 and x86_64 opcodes can
+    /// still describe such functions with the frameless indirect kind, but
+    /// that needs the function's code.
+    StackSizeTooLarge(i64),
+
+    /// The return address is not where the opcode kind expects it: at the
+    /// top of the frame, or in `lr` for arm64 frameless functions.
+    UnexpectedReturnAddress(RegisterRule),
+
+    /// The register is restored with a rule other than a CFA offset.
+    UnsupportedRule(u16),
+
+    /// A register is saved which the opcodes can't describe.
+    UnsupportedRegister(u16),
+
+    /// More registers are saved than the opcode kind can describe.
+    TooManySavedRegisters(usize),
+
+    /// The saved registers are not stored in the order and at the offsets
+    /// which the opcode kind assumes.
+    NonStandardSaveOrder,
+
+    /// arm64: registers are saved without a frame record.
+    FramelessWithSavedRegisters,
+}
+
+impl Arch {
+    /// Returns the compact unwind opcode with the same unwind rules as `row`,
+    /// using the same rules as the linker (ld64) when it decides whether an
+    /// FDE can be replaced with a compact encoding.
+    ///
+    /// The returned opcode satisfies `self.opcode_cfi(opcode) == Some(row)`,
+    /// apart from the stack size of frameless indirect opcodes, which are
+    /// never returned.
+    pub fn compactify(self, row: &CfiRow) -> Result<u32, NotCompactable> {
+        let opcode = match self {
+            Arch::X86 => compactify_x86(
+                row,
+                &X86Registers {
+                    word_size: 4,
+                    frame_pointer: 4,
+                    stack_pointer: 5,
+                    compact_register: |register| {
+                        (1..=6).find(|n| {
+                            RegisterNameX86::parse(*n).map(|reg| reg.dwarf_number())
+                                == Some(register)
+                        })
+                    },
+                },
+            )?,
+            Arch::X86_64 => compactify_x86(
+                row,
+                &X86Registers {
+                    word_size: 8,
+                    frame_pointer: 6,
+                    stack_pointer: 7,
+                    compact_register: |register| {
+                        (1..=6).find(|n| {
+                            RegisterNameX86_64::parse(*n).map(|reg| reg.dwarf_number())
+                                == Some(register)
+                        })
+                    },
+                },
+            )?,
+            Arch::Arm64 => compactify_arm64(row)?,
+        };
+        if self.opcode_cfi(opcode).as_ref() != Some(row) {
+            return Err(NotCompactable::NonStandardSaveOrder);
+        }
+        Ok(opcode)
+    }
+}
+
+impl OpcodeX86_64 {
+    /// Returns the opcode with the same unwind rules as `row`, see
+    /// [`Arch::compactify`].
+    pub fn from_cfi(row: &CfiRow) -> Result<Self, NotCompactable> {
+        Arch::X86_64.compactify(row).map(Self::parse)
+    }
+}
+
+impl OpcodeX86 {
+    /// Returns the opcode with the same unwind rules as `row`, see
+    /// [`Arch::compactify`].
+    pub fn from_cfi(row: &CfiRow) -> Result<Self, NotCompactable> {
+        Arch::X86.compactify(row).map(Self::parse)
+    }
+}
+
+impl OpcodeArm64 {
+    /// Returns the opcode with the same unwind rules as `row`, see
+    /// [`Arch::compactify`].
+    pub fn from_cfi(row: &CfiRow) -> Result<Self, NotCompactable> {
+        Arch::Arm64.compactify(row).map(Self::parse)
+    }
+}
+
+impl EhFrame<'_> {
+    /// Evaluates the CFI of the FDE at `offset` and returns the equivalent
+    /// compact unwind opcode, see [`Arch::compactify`].
+    ///
+    /// The rules of the function body are used, see [`EhFrame::body_row`].
+    /// Like with any compact opcode, the rules don't apply inside the
+    /// prologue and the epilogues.
+    pub fn compactify(&self, arch: Arch, offset: u32) -> Result<u32, NotCompactable> {
+        let row = self
+            .fde_address_range(offset)
+            .and_then(|address_range| self.body_row(offset, address_range))
+            .ok_or(NotCompactable::InvalidCfi)?;
+        arch.compactify(&row)
+    }
+}
+
+struct X86Registers<F: Fn(u16) -> Option<u8>> {
+    word_size: i64,
+    frame_pointer: u16,
+    stack_pointer: u16,
+    /// Maps a DWARF register number to the register number of the opcodes.
+    compact_register: F,
+}
+
+fn compactify_x86<F: Fn(u16) -> Option<u8>>(
+    row: &CfiRow,
+    registers: &X86Registers<F>,
+) -> Result<u32, NotCompactable> {
+    let word_size = registers.word_size;
+    let CfaRule::RegisterOffset {
+        register: cfa_register,
+        offset: cfa_offset,
+    } = row.cfa
+    else {
+        return Err(NotCompactable::CfaExpression);
+    };
+    if row.return_address != RegisterRule::AtCfaOffset(-word_size) {
+        return Err(NotCompactable::UnexpectedReturnAddress(row.return_address));
+    }
+    if cfa_offset % word_size != 0 {
+        return Err(NotCompactable::UnalignedCfaOffset(cfa_offset));
+    }
+    let mut saved = Vec::new();
+    for &(register, rule) in &row.registers {
+        let RegisterRule::AtCfaOffset(offset) = rule else {
+            return Err(NotCompactable::UnsupportedRule(register));
+        };
+        if cfa_register == registers.frame_pointer && register == registers.frame_pointer {
+            if offset != -2 * word_size {
+                return Err(NotCompactable::NonStandardSaveOrder);
+            }
+            continue;
+        }
+        let compact_register = (registers.compact_register)(register)
+            .ok_or(NotCompactable::UnsupportedRegister(register))?;
+        if offset % word_size != 0 {
+            return Err(NotCompactable::NonStandardSaveOrder);
+        }
+        saved.push((offset, compact_register));
+    }
+    // Sort by address, i.e. from the last pushed register to the first.
+    saved.sort_unstable();
+
+    if cfa_register == registers.frame_pointer {
+        if cfa_offset != 2 * word_size {
+            return Err(NotCompactable::UnexpectedCfaOffset(cfa_offset));
+        }
+        // The registers are stored in five slots, starting at the lowest
+        // saved register and going up towards the frame pointer. The lowest
+        // slot is in the lowest bits.
+        let mut slots = 0;
+        let mut stack_offset_in_words = 0;
+        if let Some(&(lowest_offset, _)) = saved.first() {
+            stack_offset_in_words = (-lowest_offset - 2 * word_size) / word_size;
+            if stack_offset_in_words > 0xff {
+                return Err(NotCompactable::StackSizeTooLarge(-lowest_offset));
+            }
+            for &(offset, compact_register) in &saved {
+                let slot = (offset - lowest_offset) / word_size;
+                if slot >= 5 || offset >= -2 * word_size {
+                    return Err(NotCompactable::NonStandardSaveOrder);
+                }
+                slots |= u32::from(compact_register) << (slot * 3);
+            }
+        }
+        Ok(u32::from(OPCODE_KIND_X86_FRAMEBASED) << 24
+            | (stack_offset_in_words as u32) << 16
+            | slots)
+    } else if cfa_register == registers.stack_pointer {
+        if saved.len() > 6 {
+            return Err(NotCompactable::TooManySavedRegisters(saved.len()));
+        }
+        // The registers must be pushed right below the return address.
+        let lowest_offset = -word_size * (saved.len() as i64 + 1);
+        let contiguous = saved
+            .iter()
+            .enumerate()
+            .all(|(i, (offset, _))| *offset == lowest_offset + i as i64 * word_size);
+        if !contiguous || cfa_offset < -lowest_offset {
+            return Err(NotCompactable::NonStandardSaveOrder);
+        }
+        let stack_size_in_words = cfa_offset / word_size;
+        if stack_size_in_words > 0xff {
+            return Err(NotCompactable::StackSizeTooLarge(cfa_offset));
+        }
+        let compact_registers: Vec<u8> = saved.iter().map(|(_, register)| *register).collect();
+        Ok(u32::from(OPCODE_KIND_X86_FRAMELESS_IMMEDIATE) << 24
+            | (stack_size_in_words as u32) << 16
+            | (saved.len() as u32) << 10
+            | encode_permutation_6(&compact_registers))
+    } else {
+        Err(NotCompactable::UnsupportedCfaRegister(cfa_register))
+    }
+}
+
+fn compactify_arm64(row: &CfiRow) -> Result<u32, NotCompactable> {
+    let CfaRule::RegisterOffset {
+        register: cfa_register,
+        offset: cfa_offset,
+    } = row.cfa
+    else {
+        return Err(NotCompactable::CfaExpression);
+    };
+    if let Some(&(register, _)) = row
+        .registers
+        .iter()
+        .find(|(_, rule)| !matches!(rule, RegisterRule::AtCfaOffset(_)))
+    {
+        return Err(NotCompactable::UnsupportedRule(register));
+    }
+    match cfa_register {
+        29 => {
+            if cfa_offset != 16 {
+                return Err(NotCompactable::UnexpectedCfaOffset(cfa_offset));
+            }
+            if row.return_address != RegisterRule::AtCfaOffset(-8) {
+                return Err(NotCompactable::UnexpectedReturnAddress(row.return_address));
+            }
+            // x19 to x28 are bits 0 to 4, d8 to d15 are bits 5 to 8. Whether
+            // the pairs are stored at the right offsets is checked by the
+            // caller.
+            let mut bits = 0;
+            for &(register, _) in &row.registers {
+                let bit = match register {
+                    29 => continue,
+                    19..=28 => (register - 19) / 2,
+                    72..=79 => (register - 72) / 2 + 5,
+                    _ => return Err(NotCompactable::UnsupportedRegister(register)),
+                };
+                bits |= 1 << bit;
+            }
+            Ok(u32::from(OPCODE_KIND_ARM64_FRAMEBASED) << 24 | bits)
+        }
+        31 => {
+            if !row.registers.is_empty() {
+                return Err(NotCompactable::FramelessWithSavedRegisters);
+            }
+            if row.return_address != RegisterRule::Unchanged {
+                return Err(NotCompactable::UnexpectedReturnAddress(row.return_address));
+            }
+            if cfa_offset % 16 != 0 {
+                return Err(NotCompactable::UnalignedCfaOffset(cfa_offset));
+            }
+            let stack_size_in_units = cfa_offset / 16;
+            if !(0..=0xfff).contains(&stack_size_in_units) {
+                return Err(NotCompactable::StackSizeTooLarge(cfa_offset));
+            }
+            Ok(u32::from(OPCODE_KIND_ARM64_FRAMELESS) << 24 | (stack_size_in_units as u32) << 12)
+        }
+        _ => Err(NotCompactable::UnsupportedCfaRegister(cfa_register)),
+    }
+}
+
+impl fmt::Display for NotCompactable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotCompactable::InvalidCfi => write!(f, "the FDE's CFI could not be evaluated"),
+            NotCompactable::CfaExpression => write!(f, "the CFA is a DWARF expression"),
+            NotCompactable::UnsupportedCfaRegister(register) => {
+                write!(
+                    f,
+                    "the CFA is based on unsupported register reg{}",
+                    register
+                )
+            }
+            NotCompactable::UnalignedCfaOffset(offset) => {
+                write!(f, "the CFA offset {} is not aligned", offset)
+            }
+            NotCompactable::UnexpectedCfaOffset(offset) => write!(
+                f,
+                "the CFA offset {} from the frame pointer is not two words",
+                offset
+            ),
+            NotCompactable::StackSizeTooLarge(size) => {
+                write!(f, "the stack size {} is too large", size)
+            }
+            NotCompactable::UnexpectedReturnAddress(rule) => {
+                write!(f, "the return address is {}", rule)
+            }
+            NotCompactable::UnsupportedRule(register) => {
+                write!(f, "reg{} is not saved at a CFA offset", register)
+            }
+            NotCompactable::UnsupportedRegister(register) => {
+                write!(f, "unsupported register reg{} is saved", register)
+            }
+            NotCompactable::TooManySavedRegisters(count) => {
+                write!(f, "{} saved registers are too many", count)
+            }
+            NotCompactable::NonStandardSaveOrder => {
+                write!(f, "the registers are not saved in the standard order")
+            }
+            NotCompactable::FramelessWithSavedRegisters => {
+                write!(f, "registers are saved without a frame record")
+            }
+        }
+    }
+}
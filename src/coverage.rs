@@ -0,0 +1,220 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use crate::opcodes::OpcodeBitfield;
+use crate::raw::consts::OPCODE_KIND_NULL;
+use crate::reader::Reader;
+use crate::symbol::sorted_symbols;
+use crate::{Error, Function, Symbol, UnwindInfo};
+
+/// The parts of the `__text` section without usable unwind info, as returned
+/// by [`UnwindInfo::coverage`].
+///
+/// The [`Display`](fmt::Display) implementation prints one line per issue,
+/// prefixed with the symbol name, so that the output of two builds can be
+/// diffed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoverageReport<'s> {
+    /// The parts of the text range which are outside of the address range
+    /// covered by the pages.
+    pub uncovered_ranges: Vec<Range<u32>>,
+
+    /// The symbols which have at least one issue, in address order.
+    pub symbols: Vec<SymbolCoverage<'s>>,
+}
+
+/// The coverage issues of one symbol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolCoverage<'s> {
+    pub symbol: Symbol<'s>,
+
+    /// The symbol's extent: from its address to the next symbol, or to the
+    /// end of the text range.
+    pub range: Range<u32>,
+
+    pub issues: Vec<CoverageIssue>,
+}
+
+/// A reason why (part of) a symbol has no usable unwind info.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CoverageIssue {
+    /// This part of the symbol is not covered by any page.
+    Uncovered(Range<u32>),
+
+    /// This part of the symbol is covered by entries with a `Null` opcode.
+    NullOpcode(Range<u32>),
+
+    /// The symbol doesn't start an entry. The linker merged it into the entry
+    /// which starts at `entry_start_address`, which usually belongs to the
+    /// preceding symbol.
+    ///
+    /// This is informational: ld64 merges adjacent functions with identical
+    /// opcodes, so healthy binaries have many merged symbols, and the merged
+    /// entry's opcode is valid for them.
+    MergedIntoNeighbor { entry_start_address: u32 },
+}
+
+impl CoverageReport<'_> {
+    /// Returns true if no issues were found, including informational ones.
+    pub fn is_empty(&self) -> bool {
+        self.uncovered_ranges.is_empty() && self.symbols.is_empty()
+    }
+
+    /// Returns true if some code has no usable unwind info, i.e. if there
+    /// are [`CoverageIssue::Uncovered`] or [`CoverageIssue::NullOpcode`]
+    /// issues. Merged symbols are ignored.
+    pub fn has_uncovered(&self) -> bool {
+        !self.uncovered_ranges.is_empty()
+            || self
+                .symbols
+                .iter()
+                .flat_map(|symbol| &symbol.issues)
+                .any(CoverageIssue::is_uncovered)
+    }
+}
+
+impl CoverageIssue {
+    /// Returns true for [`CoverageIssue::Uncovered`] and
+    /// [`CoverageIssue::NullOpcode`], and false for the informational
+    /// [`CoverageIssue::MergedIntoNeighbor`].
+    pub fn is_uncovered(&self) -> bool {
+        matches!(
+            self,
+            CoverageIssue::Uncovered(_) | CoverageIssue::NullOpcode(_)
+        )
+    }
+}
+
+impl<R: Reader + ?Sized> UnwindInfo<'_, R> {
+    /// Checks which parts of the text section have no usable unwind info.
+    ///
+    /// `text_range` is the address range of the `__text` section, and
+    /// `symbols` are the function symbols, both relative to the image's
+    /// mach-O header. Symbols outside of `text_range` are ignored, and
+    /// `symbols` doesn't need to be sorted.
+    pub fn coverage<'s>(
+        &self,
+        text_range: Range<u32>,
+        symbols: &[Symbol<'s>],
+    ) -> Result<CoverageReport<'s>, Error> {
+        let mut functions = Vec::new();
+        let mut iter = self.functions();
+        while let Some(function) = iter.next()? {
+            functions.push(function);
+        }
+        let covered = self.address_range();
+
+        let uncovered_ranges = subtract(text_range.clone(), &covered).collect();
+
+        let symbols: Vec<Symbol<'s>> = sorted_symbols(symbols)
+            .into_iter()
+            .filter(|symbol| text_range.contains(&symbol.address))
+            .collect();
+        let mut symbol_coverage = Vec::new();
+        for (i, symbol) in symbols.iter().enumerate() {
+            let end = symbols
+                .get(i + 1)
+                .map_or(text_range.end, |next| next.address);
+            let range = symbol.address..end;
+            let mut issues = Vec::new();
+            for uncovered in subtract(range.clone(), &covered) {
+                issues.push(CoverageIssue::Uncovered(uncovered));
+            }
+            let first = functions.partition_point(|f| f.end_address <= range.start);
+            let mut null_range: Option<Range<u32>> = None;
+            for function in functions[first..]
+                .iter()
+                .take_while(|f| f.start_address < range.end)
+            {
+                if !is_null(function) {
+                    continue;
+                }
+                let start = function.start_address.max(range.start);
+                let end = function.end_address.min(range.end);
+                match &mut null_range {
+                    Some(null_range) if null_range.end == start => null_range.end = end,
+                    _ => {
+                        if let Some(null_range) = null_range.take() {
+                            issues.push(CoverageIssue::NullOpcode(null_range));
+                        }
+                        null_range = Some(start..end);
+                    }
+                }
+            }
+            if let Some(null_range) = null_range {
+                issues.push(CoverageIssue::NullOpcode(null_range));
+            }
+            if let Some(function) = functions.get(first) {
+                if function.start_address < symbol.address && !is_null(function) {
+                    issues.push(CoverageIssue::MergedIntoNeighbor {
+                        entry_start_address: function.start_address,
+                    });
+                }
+            }
+            if !issues.is_empty() {
+                symbol_coverage.push(SymbolCoverage {
+                    symbol: *symbol,
+                    range,
+                    issues,
+                });
+            }
+        }
+
+        Ok(CoverageReport {
+            uncovered_ranges,
+            symbols: symbol_coverage,
+        })
+    }
+}
+
+fn is_null(function: &Function) -> bool {
+    OpcodeBitfield::new(function.opcode).kind() == OPCODE_KIND_NULL
+}
+
+/// Returns the parts of `range` which are outside of `covered`.
+fn subtract(range: Range<u32>, covered: &Range<u32>) -> impl Iterator<Item = Range<u32>> {
+    let before = range.start..range.end.min(covered.start);
+    let after = range.start.max(covered.end)..range.end;
+    [before, after]
+        .into_iter()
+        .filter(|range| !range.is_empty())
+}
+
+impl fmt::Display for CoverageReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for range in &self.uncovered_ranges {
+            writeln!(
+                f,
+                "0x{:08x}-0x{:08x}: not covered by any page",
+                range.start, range.end
+            )?;
+        }
+        for symbol in &self.symbols {
+            for issue in &symbol.issues {
+                writeln!(f, "{}: {}", symbol.symbol.name, issue)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CoverageIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoverageIssue::Uncovered(range) => write!(
+                f,
+                "0x{:08x}-0x{:08x} not covered by any page",
+                range.start, range.end
+            ),
+            CoverageIssue::NullOpcode(range) => write!(
+                f,
+                "0x{:08x}-0x{:08x} has a null opcode",
+                range.start, range.end
+            ),
+            CoverageIssue::MergedIntoNeighbor {
+                entry_start_address,
+            } => write!(f, "merged into the entry at 0x{:08x}", entry_start_address),
+        }
+    }
+}
@@ -0,0 +1,496 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use zerocopy_derive::*;
+
+use crate::macho::{name_eq, Segments};
+use crate::raw::{U32, U64};
+use crate::reader::SliceReader;
+use crate::{Arch, Error, UnwindInfo};
+
+/// The error type for dyld shared cache parsing.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DyldCacheError {
+    #[error("Could not read dyld shared cache header")]
+    Header,
+
+    #[error("Unrecognized dyld shared cache magic")]
+    UnrecognizedMagic,
+
+    #[error("Could not read dyld shared cache mappings")]
+    Mappings,
+
+    #[error("Could not read dyld shared cache images")]
+    Images,
+
+    #[error("Could not read dyld shared cache sub-cache entries")]
+    SubCaches,
+
+    /// The number of sub-cache files passed to [`DyldCache::parse`] didn't
+    /// match the number of sub-caches listed in the main cache file.
+    #[error("Expected {expected} sub-cache files, got {actual}")]
+    SubCacheCount { expected: usize, actual: usize },
+
+    /// The UUID of the sub-cache file at this index didn't match the UUID
+    /// listed in the main cache file.
+    #[error("UUID mismatch for sub-cache {0}")]
+    SubCacheUuidMismatch(usize),
+
+    #[error("Could not read image path")]
+    ImagePath,
+
+    /// The image's mach-O header was not found at its address, or it is not
+    /// a 64-bit mach-O header.
+    #[error("Could not read the mach-O header of the image at 0x{0:x}")]
+    ImageHeader(u64),
+
+    /// A section's address was not covered by any of the cache mappings.
+    #[error("The section at 0x{0:x} is not mapped by the cache")]
+    UnmappedSection(u64),
+
+    #[error("Unwind info error: {0}")]
+    UnwindInfo(#[from] Error),
+}
+
+/// The dyld shared cache header. Only the fields used by this module are
+/// named.
+#[derive(Unaligned, FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+struct DyldCacheHeader {
+    magic: [u8; 16],
+    mapping_offset: U32,
+    mapping_count: U32,
+    images_offset_old: U32,
+    images_count_old: U32,
+    dyld_base_address: U64,
+    code_signature_offset: U64,
+    code_signature_size: U64,
+    slide_info_offset_unused: U64,
+    slide_info_size_unused: U64,
+    local_symbols_offset: U64,
+    local_symbols_size: U64,
+    uuid: [u8; 16],
+    cache_type: U64,
+    branch_pools_offset: U32,
+    branch_pools_count: U32,
+    dyld_in_cache_mh: U64,
+    dyld_in_cache_entry: U64,
+    images_text_offset: U64,
+    images_text_count: U64,
+    unused: [u8; 240],
+    sub_cache_array_offset: U32,
+    sub_cache_array_count: U32,
+    symbol_file_uuid: [u8; 16],
+    rosetta: [U64; 4],
+    images_offset: U32,
+    images_count: U32,
+    cache_sub_type: U32,
+    padding: U32,
+}
+
+/// Headers at least this big have the `sub_cache_array_*`, `images_offset`
+/// and `images_count` fields, and use sub-cache entries without file suffix.
+const MIN_HEADER_SIZE_SUBCACHES_V1: u32 = 0x1c8;
+
+/// Headers at least this big use sub-cache entries with file suffix.
+const MIN_HEADER_SIZE_SUBCACHES_V2: u32 = 0x1d0;
+
+#[derive(Unaligned, FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+struct DyldCacheMappingInfo {
+    address: U64,
+    size: U64,
+    file_offset: U64,
+    max_prot: U32,
+    init_prot: U32,
+}
+
+#[derive(Unaligned, FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+struct DyldCacheImageInfo {
+    address: U64,
+    mod_time: U64,
+    inode: U64,
+    path_file_offset: U32,
+    pad: U32,
+}
+
+#[derive(Unaligned, FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+struct DyldCacheImageTextInfo {
+    uuid: [u8; 16],
+    load_address: U64,
+    text_segment_size: U32,
+    path_offset: U32,
+}
+
+#[derive(Unaligned, FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+struct DyldSubCacheEntryV1 {
+    uuid: [u8; 16],
+    cache_vm_offset: U64,
+}
+
+#[derive(Unaligned, FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+struct DyldSubCacheEntryV2 {
+    uuid: [u8; 16],
+    cache_vm_offset: U64,
+    file_suffix: [u8; 32],
+}
+
+/// One file of the cache: either the main cache file or a sub-cache.
+struct CacheFile<'data> {
+    data: &'data [u8],
+    mappings: &'data [DyldCacheMappingInfo],
+}
+
+impl<'data> CacheFile<'data> {
+    fn parse(data: &'data [u8]) -> Result<(&'data DyldCacheHeader, Self), DyldCacheError> {
+        let header = data
+            .read_ref_at::<DyldCacheHeader>(0)
+            .ok_or(DyldCacheError::Header)?;
+        if !header.magic.starts_with(b"dyld_v1 ") {
+            return Err(DyldCacheError::UnrecognizedMagic);
+        }
+        let mappings = data
+            .read_slice_at::<DyldCacheMappingInfo>(
+                u32::from(header.mapping_offset).into(),
+                u32::from(header.mapping_count) as usize,
+            )
+            .ok_or(DyldCacheError::Mappings)?;
+        Ok((header, Self { data, mappings }))
+    }
+
+    /// Returns the data at `address` if it is mapped by this file, up to the
+    /// end of the mapping.
+    fn data_at_address(&self, address: u64) -> Option<&'data [u8]> {
+        self.mappings.iter().find_map(|mapping| {
+            let mapping_address: u64 = mapping.address.into();
+            let offset_in_mapping = address.checked_sub(mapping_address)?;
+            if offset_in_mapping >= u64::from(mapping.size) {
+                return None;
+            }
+            let start = u64::from(mapping.file_offset).checked_add(offset_in_mapping)?;
+            let end = u64::from(mapping.file_offset).checked_add(mapping.size.into())?;
+            self.data
+                .get(usize::try_from(start).ok()?..usize::try_from(end).ok()?)
+        })
+    }
+}
+
+/// A parsed dyld shared cache, consisting of the main cache file and its
+/// sub-cache files (if any).
+///
+/// Most system libraries on macOS and iOS only exist inside the dyld shared
+/// cache. This type finds the `__unwind_info` and `__eh_frame` sections of
+/// the libraries in the cache, and maps cache addresses to libraries.
+///
+/// All addresses used by this type are unslid VM addresses as found in the
+/// cache file, not the addresses at which the cache is mapped in a process.
+pub struct DyldCache<'data> {
+    /// The main cache file, followed by the sub-caches.
+    files: Vec<CacheFile<'data>>,
+
+    arch: Option<Arch>,
+
+    images: &'data [DyldCacheImageInfo],
+
+    /// May be empty for old caches.
+    image_texts: &'data [DyldCacheImageTextInfo],
+}
+
+impl<'data> DyldCache<'data> {
+    /// Returns the file name suffixes of the sub-cache files which belong to
+    /// the main cache file `data`, in the order in which they need to be
+    /// passed to [`DyldCache::parse`]. For example, if the main cache is at
+    /// `dyld_shared_cache_arm64e`, the first sub-cache is usually at
+    /// `dyld_shared_cache_arm64e.01` (or `.1` for older caches).
+    pub fn subcache_suffixes(data: &[u8]) -> Result<Vec<String>, DyldCacheError> {
+        let (header, _) = CacheFile::parse(data)?;
+        let header_size: u32 = header.mapping_offset.into();
+        if header_size < MIN_HEADER_SIZE_SUBCACHES_V1 {
+            return Ok(Vec::new());
+        }
+        let offset: u64 = u32::from(header.sub_cache_array_offset).into();
+        let count = u32::from(header.sub_cache_array_count) as usize;
+        if header_size < MIN_HEADER_SIZE_SUBCACHES_V2 {
+            data.read_slice_at::<DyldSubCacheEntryV1>(offset, count)
+                .ok_or(DyldCacheError::SubCaches)?;
+            return Ok((1..=count).map(|i| alloc::format!(".{i}")).collect());
+        }
+        let entries = data
+            .read_slice_at::<DyldSubCacheEntryV2>(offset, count)
+            .ok_or(DyldCacheError::SubCaches)?;
+        Ok(entries
+            .iter()
+            .map(|entry| {
+                let len = entry.file_suffix.iter().position(|&b| b == 0);
+                let suffix = &entry.file_suffix[..len.unwrap_or(entry.file_suffix.len())];
+                String::from_utf8_lossy(suffix).into_owned()
+            })
+            .collect())
+    }
+
+    /// Parse the main cache file `data`, together with the data of its
+    /// sub-cache files, which need to be supplied in the order returned by
+    /// [`DyldCache::subcache_suffixes`].
+    pub fn parse(data: &'data [u8], subcaches: &[&'data [u8]]) -> Result<Self, DyldCacheError> {
+        let (header, main_file) = CacheFile::parse(data)?;
+        let header_size: u32 = header.mapping_offset.into();
+
+        let subcache_uuids: Vec<[u8; 16]> = if header_size >= MIN_HEADER_SIZE_SUBCACHES_V1 {
+            let offset: u64 = u32::from(header.sub_cache_array_offset).into();
+            let count = u32::from(header.sub_cache_array_count) as usize;
+            if header_size >= MIN_HEADER_SIZE_SUBCACHES_V2 {
+                data.read_slice_at::<DyldSubCacheEntryV2>(offset, count)
+                    .ok_or(DyldCacheError::SubCaches)?
+                    .iter()
+                    .map(|entry| entry.uuid)
+                    .collect()
+            } else {
+                data.read_slice_at::<DyldSubCacheEntryV1>(offset, count)
+                    .ok_or(DyldCacheError::SubCaches)?
+                    .iter()
+                    .map(|entry| entry.uuid)
+                    .collect()
+            }
+        } else {
+            Vec::new()
+        };
+        if subcache_uuids.len() != subcaches.len() {
+            return Err(DyldCacheError::SubCacheCount {
+                expected: subcache_uuids.len(),
+                actual: subcaches.len(),
+            });
+        }
+
+        let mut files = Vec::with_capacity(subcaches.len() + 1);
+        files.push(main_file);
+        for (i, (subcache_data, uuid)) in subcaches.iter().zip(&subcache_uuids).enumerate() {
+            let (subcache_header, subcache_file) = CacheFile::parse(subcache_data)?;
+            if &subcache_header.uuid != uuid {
+                return Err(DyldCacheError::SubCacheUuidMismatch(i));
+            }
+            files.push(subcache_file);
+        }
+
+        let (images_offset, images_count) = if header_size >= MIN_HEADER_SIZE_SUBCACHES_V1 {
+            (header.images_offset, header.images_count)
+        } else {
+            (header.images_offset_old, header.images_count_old)
+        };
+        let images = data
+            .read_slice_at::<DyldCacheImageInfo>(
+                u32::from(images_offset).into(),
+                u32::from(images_count) as usize,
+            )
+            .ok_or(DyldCacheError::Images)?;
+        let image_texts = data
+            .read_slice_at::<DyldCacheImageTextInfo>(
+                header.images_text_offset.into(),
+                u64::from(header.images_text_count) as usize,
+            )
+            .unwrap_or(&[]);
+
+        Ok(Self {
+            files,
+            arch: arch_from_magic(&header.magic),
+            images,
+            image_texts,
+        })
+    }
+
+    /// The architecture of the cache, derived from the magic string in the
+    /// header. Returns `None` for architectures which don't use the compact
+    /// unwinding format.
+    pub fn arch(&self) -> Option<Arch> {
+        self.arch
+    }
+
+    /// The number of images in the cache.
+    pub fn image_count(&self) -> usize {
+        self.images.len()
+    }
+
+    /// The image at `index`, if it exists.
+    pub fn image(&self, index: usize) -> Option<DyldCacheImage<'data, '_>> {
+        let info = self.images.get(index)?;
+        Some(DyldCacheImage {
+            cache: self,
+            index,
+            info,
+        })
+    }
+
+    /// Returns an iterator over all images in the cache.
+    pub fn images(&self) -> impl Iterator<Item = DyldCacheImage<'data, '_>> + '_ {
+        (0..self.images.len()).filter_map(|index| self.image(index))
+    }
+
+    /// Finds the image whose `__TEXT` segment contains `address`.
+    ///
+    /// To look up the unwind info for `address`, subtract the image's
+    /// [`DyldCacheImage::header_address`] and pass the result to
+    /// [`UnwindInfo::lookup`].
+    pub fn image_for_address(
+        &self,
+        address: u64,
+    ) -> Result<Option<DyldCacheImage<'data, '_>>, DyldCacheError> {
+        if !self.image_texts.is_empty() {
+            let text_info = self.image_texts.iter().find(|text_info| {
+                let start: u64 = text_info.load_address.into();
+                let size: u64 = u32::from(text_info.text_segment_size).into();
+                (start..start.saturating_add(size)).contains(&address)
+            });
+            let Some(text_info) = text_info else {
+                return Ok(None);
+            };
+            let load_address: u64 = text_info.load_address.into();
+            return Ok(self
+                .images()
+                .find(|image| image.header_address() == load_address));
+        }
+
+        // Old caches don't have the image text table, so we have to look at
+        // the mach-O header of each image.
+        for image in self.images() {
+            if image.sections()?.text_segment.contains(&address) {
+                return Ok(Some(image));
+            }
+        }
+        Ok(None)
+    }
+
+    fn data_at_address(&self, address: u64) -> Option<&'data [u8]> {
+        self.files
+            .iter()
+            .find_map(|file| file.data_at_address(address))
+    }
+
+    /// Returns `size` bytes at `address`, from whichever cache file maps it.
+    fn data_at_address_range(&self, address: u64, size: u64) -> Option<&'data [u8]> {
+        self.data_at_address(address)?
+            .get(..usize::try_from(size).ok()?)
+    }
+}
+
+fn arch_from_magic(magic: &[u8; 16]) -> Option<Arch> {
+    let arch_name = magic[b"dyld_v1".len()..]
+        .split(|&b| b == b' ' || b == 0)
+        .find(|s| !s.is_empty())?;
+    match arch_name {
+        b"i386" => Some(Arch::X86),
+        b"x86_64" | b"x86_64h" => Some(Arch::X86_64),
+        b"arm64" | b"arm64e" => Some(Arch::Arm64),
+        _ => None,
+    }
+}
+
+/// An image (a dylib or framework) in a [`DyldCache`].
+#[derive(Clone, Copy)]
+pub struct DyldCacheImage<'data, 'cache> {
+    cache: &'cache DyldCache<'data>,
+    index: usize,
+    info: &'data DyldCacheImageInfo,
+}
+
+/// The data and the address of a section of an image in the dyld shared cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DyldCacheSection<'data> {
+    /// The unslid VM address of the section.
+    pub address: u64,
+
+    /// The section data, which may come from a sub-cache file.
+    pub data: &'data [u8],
+}
+
+/// The locations of the unwinding-related parts of an image in the dyld
+/// shared cache.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DyldCacheImageSections<'data> {
+    /// The unslid VM address range of the image's `__TEXT` segment.
+    pub text_segment: Range<u64>,
+
+    /// The `__TEXT,__unwind_info` section.
+    pub unwind_info: Option<DyldCacheSection<'data>>,
+
+    /// The `__TEXT,__eh_frame` section.
+    pub eh_frame: Option<DyldCacheSection<'data>>,
+}
+
+impl<'data> DyldCacheImage<'data, '_> {
+    /// The index of this image in the cache's image list.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The unslid VM address of the image's mach-O header. Addresses in the
+    /// image's `__unwind_info` are relative to this address.
+    pub fn header_address(&self) -> u64 {
+        self.info.address.into()
+    }
+
+    /// The install path of the image, e.g. `/usr/lib/libSystem.B.dylib`.
+    pub fn path(&self) -> Result<&'data str, DyldCacheError> {
+        let data = self.cache.files[0].data;
+        let start = u32::from(self.info.path_file_offset) as usize;
+        let bytes = data.get(start..).ok_or(DyldCacheError::ImagePath)?;
+        let len = bytes
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(DyldCacheError::ImagePath)?;
+        core::str::from_utf8(&bytes[..len]).map_err(|_| DyldCacheError::ImagePath)
+    }
+
+    /// Parses the image's mach-O header and finds its `__TEXT` segment and
+    /// its unwinding sections.
+    pub fn sections(&self) -> Result<DyldCacheImageSections<'data>, DyldCacheError> {
+        let header_address = self.header_address();
+        let header_data = self
+            .cache
+            .data_at_address(header_address)
+            .ok_or(DyldCacheError::ImageHeader(header_address))?;
+        let segments =
+            Segments::parse(header_data, 0).ok_or(DyldCacheError::ImageHeader(header_address))?;
+
+        let mut result = DyldCacheImageSections {
+            text_segment: 0..0,
+            unwind_info: None,
+            eh_frame: None,
+        };
+        for (segment, sections) in segments {
+            if !name_eq(&segment.segname, b"__TEXT") {
+                continue;
+            }
+            let vmaddr: u64 = segment.vmaddr.into();
+            result.text_segment = vmaddr..vmaddr.saturating_add(segment.vmsize.into());
+            for section in sections {
+                let slot = if name_eq(&section.sectname, b"__unwind_info") {
+                    &mut result.unwind_info
+                } else if name_eq(&section.sectname, b"__eh_frame") {
+                    &mut result.eh_frame
+                } else {
+                    continue;
+                };
+                let address: u64 = section.addr.into();
+                let data = self
+                    .cache
+                    .data_at_address_range(address, section.size.into())
+                    .ok_or(DyldCacheError::UnmappedSection(address))?;
+                *slot = Some(DyldCacheSection { address, data });
+            }
+        }
+        Ok(result)
+    }
+
+    /// Returns the parsed `__unwind_info` of this image, or `None` if the image
+    /// has no `__unwind_info` section.
+    pub fn unwind_info(&self) -> Result<Option<UnwindInfo<'data>>, DyldCacheError> {
+        match self.sections()?.unwind_info {
+            Some(section) => Ok(Some(UnwindInfo::parse(section.data)?)),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,572 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use gimli::{BaseAddresses, CieOrFde, EndianSlice, LittleEndian, UnwindContext, UnwindSection};
+
+use crate::opcodes::{
+    CompactOpcode, OpcodeArm64, OpcodeBitfield, OpcodeX86, OpcodeX86_64, ReturnAddressLocation,
+};
+use crate::raw::consts::OPCODE_KIND_NULL;
+use crate::reader::Reader;
+use crate::{Arch, Error, Function, UnwindInfo};
+
+type Slice<'a> = EndianSlice<'a, LittleEndian>;
+
+/// The `__eh_frame` section of an image, with an index of its FDEs.
+///
+/// Only the FDEs are indexed; their CFI is evaluated on demand.
+pub struct EhFrame<'a> {
+    section: gimli::EhFrame<Slice<'a>>,
+    bases: BaseAddresses,
+    /// The FDEs, sorted by start address.
+    fdes: Vec<FdeEntry<'a>>,
+    /// The section offsets of the FDEs and their indexes in `fdes`, sorted
+    /// by offset.
+    fdes_by_offset: Vec<(u32, usize)>,
+    /// Whether the whole section could be parsed.
+    complete: bool,
+}
+
+struct FdeEntry<'a> {
+    offset: u32,
+    fde: gimli::FrameDescriptionEntry<Slice<'a>>,
+}
+
+/// How the canonical frame address, i.e. the value of the stack pointer
+/// before the call instruction, is computed.
+///
+/// Registers are identified by their DWARF register number in `__eh_frame`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CfaRule {
+    /// The CFA is the value of `register` plus `offset`.
+    RegisterOffset { register: u16, offset: i64 },
+
+    /// x86 and x86_64 frameless indirect: the CFA is the value of `register`
+    /// (the stack pointer) plus the `sub` immediate stored in the function's
+    /// code, plus `stack_adjust_in_bytes`.
+    Indirect {
+        register: u16,
+        immediate_offset_from_function_start: u8,
+        stack_adjust_in_bytes: u8,
+    },
+
+    /// The CFA is computed by a DWARF expression.
+    Expression,
+}
+
+/// Where the caller's value of a register is found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RegisterRule {
+    /// The register is not saved, so the caller's value is still in the
+    /// register, or the value is not recoverable.
+    Unchanged,
+
+    /// The caller's value is stored on the stack at the CFA plus the offset.
+    AtCfaOffset(i64),
+
+    /// Any other rule, such as a register move or a DWARF expression. These
+    /// rules can't be expressed by compact unwind opcodes.
+    Other,
+}
+
+/// The unwind rules for one address range of a function.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CfiRow {
+    pub cfa: CfaRule,
+
+    /// The rule for the return address.
+    pub return_address: RegisterRule,
+
+    /// The rules for the other registers which are not [`RegisterRule::Unchanged`],
+    /// sorted by register number.
+    pub registers: Vec<(u16, RegisterRule)>,
+}
+
+/// A disagreement between a compact unwind opcode and `__eh_frame`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CfiMismatch {
+    /// The `Dwarf` opcode's `eh_frame_fde` offset is not the offset of an FDE.
+    InvalidFdeOffset,
+
+    /// The `Dwarf` opcode's FDE doesn't cover the function's start address.
+    FdeDoesNotCoverFunction {
+        fde_address_range: Range<u64>,
+    },
+
+    /// The FDE's CFI program could not be evaluated.
+    InvalidCfi,
+
+    Cfa {
+        compact: CfaRule,
+        eh_frame: CfaRule,
+    },
+
+    ReturnAddress {
+        compact: RegisterRule,
+        eh_frame: RegisterRule,
+    },
+
+    SavedRegister {
+        register: u16,
+        compact: RegisterRule,
+        eh_frame: RegisterRule,
+    },
+}
+
+/// The mismatches for a function entry, as returned by
+/// [`UnwindInfo::check_eh_frame`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionCfiMismatch {
+    pub function: Function,
+
+    /// The offset of the FDE in `__eh_frame`: the `Dwarf` opcode's offset, or
+    /// the offset of the FDE covering the function for other opcodes.
+    pub eh_frame_fde: u32,
+
+    pub mismatches: Vec<CfiMismatch>,
+}
+
+impl<'a> EhFrame<'a> {
+    /// Indexes the FDEs of the `__eh_frame` section data.
+    ///
+    /// `address` is the address of the section, relative to the image's
+    /// mach-O header. The linker only uses PC-relative pointers in
+    /// `__eh_frame`, so the FDE addresses are then relative to the mach-O
+    /// header as well, like the addresses in [`Function`].
+    ///
+    /// Indexing stops at the first malformed entry; see [`EhFrame::is_complete`].
+    pub fn parse(data: &'a [u8], address: u64) -> Self {
+        let section = gimli::EhFrame::new(data, LittleEndian);
+        let bases = BaseAddresses::default().set_eh_frame(address);
+        let mut fdes = Vec::new();
+        let mut entries = section.entries(&bases);
+        let complete = loop {
+            match entries.next() {
+                Ok(Some(CieOrFde::Fde(partial))) => {
+                    let Ok(offset) = u32::try_from(partial.offset()) else {
+                        break false;
+                    };
+                    match partial.parse(gimli::EhFrame::cie_from_offset) {
+                        Ok(fde) => fdes.push(FdeEntry { offset, fde }),
+                        Err(_) => break false,
+                    }
+                }
+                Ok(Some(CieOrFde::Cie(_))) => {}
+                Ok(None) => break true,
+                Err(_) => break false,
+            }
+        };
+        fdes.sort_by_key(|entry| entry.fde.initial_address());
+        let mut fdes_by_offset: Vec<(u32, usize)> = fdes
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (entry.offset, index))
+            .collect();
+        fdes_by_offset.sort_unstable();
+        Self {
+            section,
+            bases,
+            fdes,
+            fdes_by_offset,
+            complete,
+        }
+    }
+
+    /// Whether all entries of the section could be parsed.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Returns the section offset of the FDE which covers `address`.
+    pub fn fde_for_address(&self, address: u64) -> Option<u32> {
+        self.entry_for_address(address).map(|entry| entry.offset)
+    }
+
+    /// Returns the address range covered by the FDE at `offset`, or `None`
+    /// if there is no FDE at this offset.
+    pub fn fde_address_range(&self, offset: u32) -> Option<Range<u64>> {
+        let entry = self.entry_at_offset(offset)?;
+        Some(entry.fde.initial_address()..entry.fde.end_address())
+    }
+
+    /// Evaluates the CFI of the FDE at `offset` and returns the row for the
+    /// body of the code in `address_range`.
+    ///
+    /// The body row is the one with the most saved registers and, among
+    /// those, the largest CFA offset, i.e. the state after the prologue.
+    /// Returns `None` if there is no FDE at this offset or if the CFI can't
+    /// be evaluated.
+    pub fn body_row(&self, offset: u32, address_range: Range<u64>) -> Option<CfiRow> {
+        let entry = self.entry_at_offset(offset)?;
+        let return_address_register = entry.fde.cie().return_address_register().0;
+        let mut ctx = UnwindContext::new();
+        let mut table = entry.fde.rows(&self.section, &self.bases, &mut ctx).ok()?;
+        let mut best: Option<((usize, i64), CfiRow)> = None;
+        while let Some(row) = table.next_row().ok()? {
+            if row.end_address() <= address_range.start || row.start_address() >= address_range.end
+            {
+                continue;
+            }
+            let cfa = match *row.cfa() {
+                gimli::CfaRule::RegisterAndOffset { register, offset } => CfaRule::RegisterOffset {
+                    register: register.0,
+                    offset,
+                },
+                gimli::CfaRule::Expression(_) => CfaRule::Expression,
+            };
+            let mut return_address = RegisterRule::Unchanged;
+            let mut registers = Vec::new();
+            for (register, rule) in row.registers() {
+                let rule = match *rule {
+                    gimli::RegisterRule::Undefined | gimli::RegisterRule::SameValue => {
+                        RegisterRule::Unchanged
+                    }
+                    gimli::RegisterRule::Offset(offset) => RegisterRule::AtCfaOffset(offset),
+                    _ => RegisterRule::Other,
+                };
+                if register.0 == return_address_register {
+                    return_address = rule;
+                } else if rule != RegisterRule::Unchanged {
+                    registers.push((register.0, rule));
+                }
+            }
+            registers.sort_by_key(|(register, _)| *register);
+            let cfa_offset = match cfa {
+                CfaRule::RegisterOffset { offset, .. } => offset,
+                _ => 0,
+            };
+            let score = (registers.len(), cfa_offset);
+            // On ties, the later row wins, so that a frame pointer based CFA
+            // is preferred over the stack pointer based CFA of the same depth.
+            if best
+                .as_ref()
+                .is_none_or(|(best_score, _)| score >= *best_score)
+            {
+                best = Some((
+                    score,
+                    CfiRow {
+                        cfa,
+                        return_address,
+                        registers,
+                    },
+                ));
+            }
+        }
+        best.map(|(_, row)| row)
+    }
+
+    fn entry_for_address(&self, address: u64) -> Option<&FdeEntry<'a>> {
+        let index = self
+            .fdes
+            .partition_point(|entry| entry.fde.initial_address() <= address)
+            .checked_sub(1)?;
+        let entry = &self.fdes[index];
+        entry.fde.contains(address).then_some(entry)
+    }
+
+    fn entry_at_offset(&self, offset: u32) -> Option<&FdeEntry<'a>> {
+        let index = self
+            .fdes_by_offset
+            .binary_search_by_key(&offset, |(offset, _)| *offset)
+            .ok()?;
+        Some(&self.fdes[self.fdes_by_offset[index].1])
+    }
+}
+
+impl Arch {
+    /// Returns the unwind rules implied by the compact unwind opcode, with
+    /// the DWARF register numbers used in `__eh_frame`.
+    ///
+    /// Returns `None` for opcodes which don't describe the frame: `Null`,
+    /// `Dwarf`, and invalid opcodes.
+    pub fn opcode_cfi(self, opcode: u32) -> Option<CfiRow> {
+        match self {
+            Arch::X86 => {
+                let opcode = OpcodeX86::parse(opcode);
+                compact_cfi(&opcode, x86_cfa(self, &opcode)?)
+            }
+            Arch::X86_64 => {
+                let opcode = OpcodeX86_64::parse(opcode);
+                compact_cfi(&opcode, x86_64_cfa(self, &opcode)?)
+            }
+            Arch::Arm64 => {
+                let opcode = OpcodeArm64::parse(opcode);
+                compact_cfi(&opcode, arm64_cfa(&opcode)?)
+            }
+        }
+    }
+}
+
+/// Builds the rules from the opcode's return address location and saved
+/// registers. Only the CFA rule differs between the architectures.
+fn compact_cfi(opcode: &impl CompactOpcode, cfa: CfaRule) -> Option<CfiRow> {
+    let return_address = match opcode.return_address_location()? {
+        ReturnAddressLocation::AtCfaOffset(offset) => RegisterRule::AtCfaOffset(offset),
+        // The return address stays in lr.
+        ReturnAddressLocation::InRegister(_) => RegisterRule::Unchanged,
+    };
+    let registers = opcode
+        .saved_registers()
+        .iter()
+        .map(|saved| {
+            (
+                saved.dwarf_number,
+                RegisterRule::AtCfaOffset(saved.cfa_offset),
+            )
+        })
+        .collect();
+    Some(CfiRow {
+        cfa,
+        return_address,
+        registers,
+    })
+}
+
+fn x86_64_cfa(arch: Arch, opcode: &OpcodeX86_64) -> Option<CfaRule> {
+    match *opcode {
+        OpcodeX86_64::FrameBased { .. } => Some(CfaRule::RegisterOffset {
+            register: arch.frame_pointer_register(),
+            offset: 16,
+        }),
+        OpcodeX86_64::FramelessImmediate {
+            stack_size_in_bytes,
+            ..
+        } => Some(CfaRule::RegisterOffset {
+            register: arch.stack_pointer_register(),
+            offset: stack_size_in_bytes.into(),
+        }),
+        OpcodeX86_64::FramelessIndirect {
+            immediate_offset_from_function_start,
+            stack_adjust_in_bytes,
+            ..
+        } => Some(CfaRule::Indirect {
+            register: arch.stack_pointer_register(),
+            immediate_offset_from_function_start,
+            stack_adjust_in_bytes,
+        }),
+        _ => None,
+    }
+}
+
+fn x86_cfa(arch: Arch, opcode: &OpcodeX86) -> Option<CfaRule> {
+    match *opcode {
+        OpcodeX86::FrameBased { .. } => Some(CfaRule::RegisterOffset {
+            register: arch.frame_pointer_register(),
+            offset: 8,
+        }),
+        OpcodeX86::FramelessImmediate {
+            stack_size_in_bytes,
+            ..
+        } => Some(CfaRule::RegisterOffset {
+            register: arch.stack_pointer_register(),
+            offset: stack_size_in_bytes.into(),
+        }),
+        OpcodeX86::FramelessIndirect {
+            immediate_offset_from_function_start,
+            stack_adjust_in_bytes,
+            ..
+        } => Some(CfaRule::Indirect {
+            register: arch.stack_pointer_register(),
+            immediate_offset_from_function_start,
+            stack_adjust_in_bytes,
+        }),
+        _ => None,
+    }
+}
+
+fn arm64_cfa(opcode: &OpcodeArm64) -> Option<CfaRule> {
+    match *opcode {
+        OpcodeArm64::FrameBased { .. } => Some(CfaRule::RegisterOffset {
+            register: Arch::Arm64.frame_pointer_register(),
+            offset: 16,
+        }),
+        OpcodeArm64::Frameless {
+            stack_size_in_bytes,
+        } => Some(CfaRule::RegisterOffset {
+            register: Arch::Arm64.stack_pointer_register(),
+            offset: stack_size_in_bytes.into(),
+        }),
+        _ => None,
+    }
+}
+
+impl<R: Reader + ?Sized> UnwindInfo<'_, R> {
+    /// Compares the compact unwind opcodes with the CFI in `__eh_frame`.
+    ///
+    /// For `Dwarf` opcodes, this checks that `eh_frame_fde` is the offset of
+    /// an FDE which covers the function. For the other opcodes, the CFI of the
+    /// FDE covering the function's start address, if any, is evaluated at the
+    /// function body (see [`EhFrame::body_row`]) and compared with the rules
+    /// implied by the opcode (see [`Arch::opcode_cfi`]). Only the entries with
+    /// at least one mismatch are returned.
+    pub fn check_eh_frame(
+        &self,
+        arch: Arch,
+        eh_frame: &EhFrame<'_>,
+    ) -> Result<Vec<FunctionCfiMismatch>, Error> {
+        let mut results = Vec::new();
+        let mut iter = self.functions();
+        while let Some(function) = iter.next()? {
+            let start = u64::from(function.start_address);
+            let end = u64::from(function.end_address);
+            let mut mismatches = Vec::new();
+            let eh_frame_fde = if OpcodeBitfield::new(function.opcode).kind() == OPCODE_KIND_NULL {
+                continue;
+            } else if let Some(eh_frame_fde) = dwarf_fde_offset(arch, function.opcode) {
+                match eh_frame.fde_address_range(eh_frame_fde) {
+                    None => mismatches.push(CfiMismatch::InvalidFdeOffset),
+                    Some(fde_address_range) if !fde_address_range.contains(&start) => {
+                        mismatches.push(CfiMismatch::FdeDoesNotCoverFunction { fde_address_range })
+                    }
+                    Some(_) => {}
+                }
+                eh_frame_fde
+            } else {
+                let (Some(compact), Some(eh_frame_fde)) = (
+                    arch.opcode_cfi(function.opcode),
+                    eh_frame.fde_for_address(start),
+                ) else {
+                    continue;
+                };
+                match eh_frame.body_row(eh_frame_fde, start..end) {
+                    Some(row) => compare_rows(&compact, &row, &mut mismatches),
+                    None => mismatches.push(CfiMismatch::InvalidCfi),
+                }
+                eh_frame_fde
+            };
+            if !mismatches.is_empty() {
+                results.push(FunctionCfiMismatch {
+                    function,
+                    eh_frame_fde,
+                    mismatches,
+                });
+            }
+        }
+        Ok(results)
+    }
+}
+
+fn dwarf_fde_offset(arch: Arch, opcode: u32) -> Option<u32> {
+    match arch {
+        Arch::X86 => OpcodeX86::parse(opcode).eh_frame_fde(),
+        Arch::X86_64 => OpcodeX86_64::parse(opcode).eh_frame_fde(),
+        Arch::Arm64 => OpcodeArm64::parse(opcode).eh_frame_fde(),
+    }
+}
+
+fn compare_rows(compact: &CfiRow, eh_frame: &CfiRow, mismatches: &mut Vec<CfiMismatch>) {
+    let cfa_matches = match (compact.cfa, eh_frame.cfa) {
+        // The stack size of indirect opcodes is stored in the code, so only
+        // the register can be compared.
+        (CfaRule::Indirect { register, .. }, CfaRule::RegisterOffset { register: r, .. }) => {
+            register == r
+        }
+        (compact, eh_frame) => compact == eh_frame,
+    };
+    if !cfa_matches {
+        mismatches.push(CfiMismatch::Cfa {
+            compact: compact.cfa,
+            eh_frame: eh_frame.cfa,
+        });
+    }
+    if compact.return_address != eh_frame.return_address {
+        mismatches.push(CfiMismatch::ReturnAddress {
+            compact: compact.return_address,
+            eh_frame: eh_frame.return_address,
+        });
+    }
+    let rule = |row: &CfiRow, register: u16| {
+        row.registers
+            .iter()
+            .find(|(r, _)| *r == register)
+            .map_or(RegisterRule::Unchanged, |(_, rule)| *rule)
+    };
+    let mut registers: Vec<u16> = compact
+        .registers
+        .iter()
+        .chain(&eh_frame.registers)
+        .map(|(register, _)| *register)
+        .collect();
+    registers.sort_unstable();
+    registers.dedup();
+    for register in registers {
+        let (compact, eh_frame) = (rule(compact, register), rule(eh_frame, register));
+        if compact != eh_frame {
+            mismatches.push(CfiMismatch::SavedRegister {
+                register,
+                compact,
+                eh_frame,
+            });
+        }
+    }
+}
+
+impl fmt::Display for CfaRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CfaRule::RegisterOffset { register, offset } => {
+                if *offset == 0 {
+                    write!(f, "reg{}", register)
+                } else {
+                    write!(f, "reg{}{:+}", register, offset)
+                }
+            }
+            CfaRule::Indirect {
+                register,
+                immediate_offset_from_function_start,
+                stack_adjust_in_bytes,
+            } => write!(
+                f,
+                "reg{}+[function_start+{}]+{}",
+                register, immediate_offset_from_function_start, stack_adjust_in_bytes
+            ),
+            CfaRule::Expression => write!(f, "(expression)"),
+        }
+    }
+}
+
+impl fmt::Display for RegisterRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterRule::Unchanged => write!(f, "unchanged"),
+            RegisterRule::AtCfaOffset(offset) => write!(f, "[CFA{:+}]", offset),
+            RegisterRule::Other => write!(f, "(other rule)"),
+        }
+    }
+}
+
+impl fmt::Display for CfiMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CfiMismatch::InvalidFdeOffset => write!(f, "eh_frame_fde is not the offset of an FDE"),
+            CfiMismatch::FdeDoesNotCoverFunction { fde_address_range } => write!(
+                f,
+                "the FDE covers 0x{:08x}-0x{:08x}, not the function",
+                fde_address_range.start, fde_address_range.end
+            ),
+            CfiMismatch::InvalidCfi => write!(f, "the FDE's CFI could not be evaluated"),
+            CfiMismatch::Cfa { compact, eh_frame } => {
+                write!(
+                    f,
+                    "CFA is {} in the opcode, {} in the FDE",
+                    compact, eh_frame
+                )
+            }
+            CfiMismatch::ReturnAddress { compact, eh_frame } => write!(
+                f,
+                "return address is {} in the opcode, {} in the FDE",
+                compact, eh_frame
+            ),
+            CfiMismatch::SavedRegister {
+                register,
+                compact,
+                eh_frame,
+            } => write!(
+                f,
+                "reg{} is {} in the opcode, {} in the FDE",
+                register, compact, eh_frame
+            ),
+        }
+    }
+}
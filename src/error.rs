@@ -1,6 +1,21 @@
+use core::fmt;
+
 /// The error type used in this crate.
+///
+/// The [`ErrorKind`] says what went wrong, and the [`ErrorContext`] says
+/// where: the section offset, page, entry and looked-up address, as far as
+/// they are known.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("{kind}{context}")]
+pub struct Error {
+    kind: ErrorKind,
+    context: ErrorContext,
+}
+
+/// The kinds of [`Error`].
 #[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Error {
+#[non_exhaustive]
+pub enum ErrorKind {
     /// The data slice was not big enough to read the struct, or we
     /// were trying to follow an invalid offset to somewhere outside
     /// of the data bounds.
@@ -25,11 +40,125 @@ pub enum Error {
     /// somewhere else, this error is thrown.
     #[error("Unexpected sentinel page")]
     UnexpectedSentinelPage,
+
+    /// The address of a compressed function entry, i.e. the page's
+    /// first_address plus the entry's relative address, doesn't fit into
+    /// 32 bits.
+    #[error("The function address overflowed")]
+    AddressOverflow,
+}
+
+/// Where an [`Error`] happened. Fields are `None` if they don't apply or
+/// aren't known.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ErrorContext {
+    /// The section offset of the data that couldn't be read or was invalid.
+    pub section_offset: Option<u64>,
+
+    /// The index of the second-level page.
+    pub page_index: Option<usize>,
+
+    /// The index of the function entry within the page.
+    pub entry_index: Option<usize>,
+
+    /// The address that was being looked up.
+    pub address: Option<u32>,
+}
+
+impl Error {
+    /// What went wrong.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Where it went wrong.
+    pub fn context(&self) -> &ErrorContext {
+        &self.context
+    }
+
+    /// A read error at `section_offset`.
+    pub(crate) fn read(error: ReadError, section_offset: u64) -> Self {
+        Error::from(error).with_section_offset(section_offset)
+    }
+
+    // The context is filled in from the innermost call outwards, so the
+    // `with_*` methods keep values that are already set.
+
+    pub(crate) fn with_section_offset(mut self, section_offset: u64) -> Self {
+        self.context.section_offset.get_or_insert(section_offset);
+        self
+    }
+
+    pub(crate) fn with_page_index(mut self, page_index: usize) -> Self {
+        self.context.page_index.get_or_insert(page_index);
+        self
+    }
+
+    pub(crate) fn with_entry_index(mut self, entry_index: usize) -> Self {
+        self.context.entry_index.get_or_insert(entry_index);
+        self
+    }
+
+    pub(crate) fn with_address(mut self, address: u32) -> Self {
+        self.context.address.get_or_insert(address);
+        self
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error {
+            kind,
+            context: ErrorContext::default(),
+        }
+    }
+}
+
+impl From<ReadError> for Error {
+    fn from(error: ReadError) -> Self {
+        ErrorKind::ReadError(error).into()
+    }
+}
+
+impl PartialEq<ErrorKind> for Error {
+    fn eq(&self, kind: &ErrorKind) -> bool {
+        self.kind == *kind
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    /// Formats the known fields as ` (at section offset 0x1c, page 2, ...)`,
+    /// or as nothing if no field is known.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = " (";
+        let mut field = |f: &mut fmt::Formatter<'_>, args: fmt::Arguments| {
+            let r = write!(f, "{}{}", separator, args);
+            separator = ", ";
+            r
+        };
+        if let Some(section_offset) = self.section_offset {
+            field(f, format_args!("at section offset 0x{:x}", section_offset))?;
+        }
+        if let Some(page_index) = self.page_index {
+            field(f, format_args!("page {}", page_index))?;
+        }
+        if let Some(entry_index) = self.entry_index {
+            field(f, format_args!("entry {}", entry_index))?;
+        }
+        if let Some(address) = self.address {
+            field(f, format_args!("looking up 0x{:x}", address))?;
+        }
+        if separator == ", " {
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
 }
 
 /// This error indicates that the data slice was not large enough to
 /// read the respective item.
 #[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ReadError {
     #[error("Could not read CompactUnwindInfoHeader")]
     Header,
@@ -40,6 +169,12 @@ pub enum ReadError {
     #[error("Could not read pages")]
     Pages,
 
+    #[error("Could not read personalities")]
+    Personalities,
+
+    #[error("Could not read LSDA entries")]
+    Lsdas,
+
     #[error("Could not read RegularPage")]
     RegularPage,
 
@@ -0,0 +1,140 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::reader::Reader;
+#[cfg(feature = "alloc")]
+use crate::{Error, Function, UnwindInfo};
+
+/// An iterator over the function start addresses in the data of an
+/// `LC_FUNCTION_STARTS` load command.
+///
+/// The data is a sequence of ULEB128-encoded deltas, terminated by a zero
+/// delta. The first delta is relative to the start of the `__TEXT` segment,
+/// so the yielded addresses are relative to the image's mach-O header, like
+/// the addresses in [`Function`](crate::Function).
+///
+/// Iteration stops at the terminator, at the end of the data, or at a
+/// malformed delta.
+#[derive(Clone, Debug)]
+pub struct FunctionStarts<'a> {
+    data: &'a [u8],
+    address: u32,
+}
+
+impl<'a> FunctionStarts<'a> {
+    /// Create an iterator over the `LC_FUNCTION_STARTS` data, i.e. the bytes
+    /// at `dataoff..dataoff + datasize` in the mach-O file.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, address: 0 }
+    }
+
+    fn read_uleb128(&mut self) -> Option<u32> {
+        let mut value: u32 = 0;
+        let mut shift = 0;
+        loop {
+            let (&byte, rest) = self.data.split_first()?;
+            self.data = rest;
+            if shift >= 32 {
+                return None;
+            }
+            value |= u32::from(byte & 0x7f).checked_shl(shift)?;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+            shift += 7;
+        }
+    }
+}
+
+impl Iterator for FunctionStarts<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let delta = self.read_uleb128().filter(|delta| *delta != 0);
+        let Some(address) = delta.and_then(|delta| self.address.checked_add(delta)) else {
+            self.data = &[];
+            return None;
+        };
+        self.address = address;
+        Some(address)
+    }
+}
+
+/// The part of the unwind entries between one function start and the next,
+/// as returned by [`UnwindInfo::function_ranges`].
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionRange {
+    /// The function start address.
+    pub start_address: u32,
+
+    /// The next function start address, or the end of the last entry.
+    pub end_address: u32,
+
+    /// The unwind ranges making up this range, in address order. There is
+    /// more than one piece if the function spans several entries, for example
+    /// because its opcode changes mid-function.
+    ///
+    /// Only contiguous code is included: the cold part of a function which
+    /// was split into hot and cold parts usually sits elsewhere in `__text`,
+    /// and gets a range of its own.
+    pub pieces: Vec<Function>,
+}
+
+#[cfg(feature = "alloc")]
+impl<R: Reader + ?Sized> UnwindInfo<'_, R> {
+    /// Splits the unwind entries at the `LC_FUNCTION_STARTS` boundaries,
+    /// which recovers the function boundaries that the linker lost when it
+    /// merged adjacent functions with identical opcodes into one entry.
+    ///
+    /// Each range starts at a function start and extends to the next one,
+    /// or to the end of the last entry. Each piece keeps its entry's opcode.
+    /// Entries which start between two function starts, such as entries for
+    /// a mid-function opcode change, are added to the preceding range. Code
+    /// before the first function start is not part of any range, so an empty
+    /// `function_starts` gives no ranges.
+    ///
+    /// `function_starts` must be sorted, for example as produced by
+    /// [`FunctionStarts`].
+    pub fn function_ranges(&self, function_starts: &[u32]) -> Result<Vec<FunctionRange>, Error> {
+        let mut ranges: Vec<FunctionRange> = Vec::new();
+        if function_starts.is_empty() {
+            return Ok(ranges);
+        }
+        let mut iter = self.functions();
+        while let Some(function) = iter.next()? {
+            let first = function_starts.partition_point(|start| *start <= function.start_address);
+            let inner_starts = function_starts[first..]
+                .iter()
+                .copied()
+                .take_while(|start| *start < function.end_address);
+            let mut piece_start = function.start_address;
+            let mut at_function_start =
+                first > 0 && function_starts[first - 1] == function.start_address;
+            for piece_end in inner_starts.chain(core::iter::once(function.end_address)) {
+                if piece_end == piece_start {
+                    continue;
+                }
+                let piece = Function {
+                    start_address: piece_start,
+                    end_address: piece_end,
+                    opcode: function.opcode,
+                };
+                if at_function_start {
+                    ranges.push(FunctionRange {
+                        start_address: piece.start_address,
+                        end_address: piece.end_address,
+                        pieces: alloc::vec![piece],
+                    });
+                } else if let Some(range) = ranges.last_mut() {
+                    range.end_address = piece.end_address;
+                    range.pieces.push(piece);
+                }
+                piece_start = piece_end;
+                at_function_start = true;
+            }
+        }
+        Ok(ranges)
+    }
+}
@@ -28,6 +28,26 @@
 //! duration of the function. And the unwind info lets you discern between these two
 //! types of functions ("frame-based" and "frameless").
 //!
+//! # Cargo features
+//!
+//! - `std` (enabled by default): Implements `std::error::Error` for the error
+//!   types. Implies `alloc`.
+//! - `alloc`: Enables the APIs which need to allocate.
+//! - `dyld-cache`: Enables the [`dyld_cache`] module, for finding the unwind
+//!   info of the libraries in the dyld shared cache. Implies `alloc`.
+//! - `eh-frame`: Enables [`EhFrame`] and [`UnwindInfo::check_eh_frame`], for
+//!   comparing the compact unwind info with the CFI in `__eh_frame`, and
+//!   [`Arch::compactify`], for converting CFI into compact opcodes. Implies
+//!   `alloc`.
+//! - `rayon`: Enables `UnwindInfo::par_functions`, which iterates over the
+//!   functions in parallel, one page per task. Implies `std`.
+//! - `cli`: Builds the `macho-unwind-info` command-line tool. Implies `std`.
+//!
+//! Without any features, the crate is `no_std` and doesn't allocate. The lookup,
+//! the function iteration and the opcode parsing are all available in this
+//! configuration, so they can be used in a signal handler or in a bare-metal
+//! environment.
+//!
 //! # Example
 //!
 //! ```rust
@@ -46,32 +66,98 @@
 //! # }
 //! ```
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod arch;
+#[cfg(feature = "eh-frame")]
+mod compactify;
+#[cfg(feature = "alloc")]
+mod coverage;
+/// Provides access to the unwind info of the images in the dyld shared cache.
+#[cfg(feature = "dyld-cache")]
+pub mod dyld_cache;
+#[cfg(feature = "eh-frame")]
+mod eh_frame;
 mod error;
+mod function_starts;
+#[cfg(feature = "alloc")]
+mod lint;
+mod lookup_details;
+#[cfg(feature = "dyld-cache")]
+mod macho;
+#[cfg(feature = "std")]
+mod module_map;
 mod num_display;
+#[cfg(feature = "alloc")]
+mod objdump;
+mod owned;
+mod page;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod range;
+#[cfg(feature = "alloc")]
+mod report;
+mod stats;
+mod symbol;
 
 /// Provides architecture-specific opcode parsing.
 pub mod opcodes;
 /// Lower-level structs for interpreting the format data. Can be used if the convenience APIs are too limiting.
 pub mod raw;
 
-mod reader;
+/// Provides the [`Reader`](reader::Reader) trait for reading the section data from
+/// sources other than a byte slice.
+pub mod reader;
 
+/// Walks the stack through multiple frames, using the compact unwind info.
+pub mod stack_walker;
+
+pub use arch::Arch;
+#[cfg(feature = "eh-frame")]
+pub use compactify::*;
+#[cfg(feature = "alloc")]
+pub use coverage::*;
+#[cfg(feature = "eh-frame")]
+pub use eh_frame::*;
 pub use error::*;
+pub use function_starts::*;
+#[cfg(feature = "alloc")]
+pub use lint::*;
+pub use lookup_details::*;
+#[cfg(feature = "std")]
+pub use module_map::*;
+#[cfg(feature = "alloc")]
+pub use objdump::ObjdumpListing;
+use opcodes::OpcodeBitfield;
+pub use owned::OwnedUnwindInfo;
+pub use page::*;
+pub use range::*;
 use raw::*;
+use reader::{ArrayRef, Reader};
+#[cfg(feature = "alloc")]
+pub use report::*;
+pub use stats::*;
+pub use symbol::Symbol;
 
 /// A parsed representation of the unwind info.
 ///
 /// The UnwindInfo contains a list of pages, each of which contain a list of
 /// function entries.
-pub struct UnwindInfo<'a> {
+///
+/// By default, the unwind info wraps a byte slice. Other sources of section data
+/// can be used via [`UnwindInfo::from_reader`].
+pub struct UnwindInfo<'a, R: Reader + ?Sized = [u8]> {
     /// The full __unwind_info section data.
-    data: &'a [u8],
+    data: &'a R,
 
     /// The list of global opcodes.
-    global_opcodes: &'a [Opcode],
+    global_opcodes: ArrayRef<Opcode>,
 
     /// The list of page entries in this UnwindInfo.
-    pages: &'a [PageEntry],
+    pages: ArrayRef<PageEntry>,
 }
 
 /// The information about a single function in the UnwindInfo.
@@ -92,14 +178,54 @@ pub struct Function {
     pub opcode: u32,
 }
 
+impl Function {
+    /// Whether this entry starts a function, according to the
+    /// `UNWIND_IS_NOT_FUNCTION_START` bit of the opcode.
+    ///
+    /// Note that the linker merges adjacent functions with identical opcodes
+    /// into one entry, so an entry which starts a function can still cover
+    /// several functions. Use [`UnwindInfo::function_ranges`] to split them.
+    pub fn is_function_start(&self) -> bool {
+        OpcodeBitfield::new(self.opcode).is_function_start()
+    }
+
+    /// Whether the `UNWIND_IS_NOT_FUNCTION_START` bit of the opcode is set,
+    /// i.e. whether this entry continues a function which started at an
+    /// earlier address.
+    pub fn is_not_function_start(&self) -> bool {
+        OpcodeBitfield::new(self.opcode).is_not_function_start()
+    }
+}
+
 impl<'a> UnwindInfo<'a> {
     /// Create an [UnwindInfo] instance which wraps the raw bytes of a mach-O binary's
     /// `__unwind_info` section. The data can have arbitrary alignment. The parsing done
     /// in this function is minimal; it's basically just three bounds checks.
     pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
-        let header = CompactUnwindInfoHeader::parse(data)?;
-        let global_opcodes = header.global_opcodes(data)?;
-        let pages = header.pages(data)?;
+        Self::from_reader(data)
+    }
+}
+
+impl<'a, R: Reader + ?Sized> UnwindInfo<'a, R> {
+    /// Create an [UnwindInfo] instance which reads the `__unwind_info` section
+    /// through the given [`Reader`]. This reads the header and checks that the
+    /// global opcodes and the page entries can be read.
+    pub fn from_reader(data: &'a R) -> Result<Self, Error> {
+        let header = *data
+            .read_at::<CompactUnwindInfoHeader>(0)
+            .ok_or(Error::read(ReadError::Header, 0))?;
+        let global_opcodes = ArrayRef::new(
+            data,
+            header.global_opcodes_offset().into(),
+            header.global_opcodes_len() as usize,
+            ReadError::GlobalOpcodes,
+        )?;
+        let pages = ArrayRef::new(
+            data,
+            header.pages_offset().into(),
+            header.pages_len() as usize,
+            ReadError::Pages,
+        )?;
         Ok(Self {
             data,
             global_opcodes,
@@ -108,23 +234,28 @@ impl<'a> UnwindInfo<'a> {
     }
 
     /// Returns an iterator over all the functions in this UnwindInfo.
-    pub fn functions(&self) -> FunctionIter<'a> {
+    pub fn functions(&self) -> FunctionIter<'a, R> {
         FunctionIter {
             data: self.data,
             global_opcodes: self.global_opcodes,
             pages: self.pages,
-            cur_page: None,
+            pages_start_index: 0,
+            front: None,
+            back: None,
         }
     }
 
     /// Returns the range of addresses covered by unwind information.
+    ///
+    /// Returns an empty range if the first or the last page entry could not be
+    /// read, which can only happen for non-slice readers.
     pub fn address_range(&self) -> core::ops::Range<u32> {
-        if self.pages.is_empty() {
-            return 0..0;
+        match (self.pages.first(self.data), self.pages.last(self.data)) {
+            (Ok(Some(first_page)), Ok(Some(last_page))) => {
+                first_page.first_address()..last_page.first_address()
+            }
+            _ => 0..0,
         }
-        let first_page = self.pages.first().unwrap();
-        let last_page = self.pages.last().unwrap();
-        first_page.first_address()..last_page.first_address()
     }
 
     /// Looks up the unwind information for the function that covers the given address.
@@ -142,244 +273,450 @@ impl<'a> UnwindInfo<'a> {
     /// It's just two binary searches: First to find the right page, end then to find
     /// the right function within a page. The search happens inside the wrapped data,
     /// with no extra copies.
+    ///
+    /// Errors have the looked-up address in their [`ErrorContext`].
     pub fn lookup(&self, pc: u32) -> Result<Option<Function>, Error> {
-        let Self {
-            pages,
-            data,
-            global_opcodes,
-        } = self;
-        let page_index = match pages.binary_search_by_key(&pc, PageEntry::first_address) {
-            Ok(i) => i,
-            Err(insertion_index) => {
-                if insertion_index == 0 {
-                    return Ok(None);
-                }
-                insertion_index - 1
-            }
+        self.lookup_impl(pc).map_err(|err| err.with_address(pc))
+    }
+
+    fn lookup_impl(&self, pc: u32) -> Result<Option<Function>, Error> {
+        let Some(page_index) = self.page_index_of(pc)? else {
+            return Ok(None);
         };
-        if page_index == pages.len() - 1 {
-            // We found the sentinel last page, which just marks the end of the range.
-            // So the looked up address is at or after the end address, i.e. outside the
-            // range of addresses covered by this UnwindInfo.
+        let Some(page) = self.page(page_index)? else {
             return Ok(None);
-        }
-        let page_entry = &pages[page_index];
-        let next_page_entry = &pages[page_index + 1];
-        let page_offset = page_entry.page_offset();
-        match page_entry.page_kind(data)? {
+        };
+        let Some(function_index) = page.index_of(pc)? else {
+            return Ok(None);
+        };
+        Ok(page
+            .function_at(function_index)?
+            .map(|page_function| page_function.function))
+    }
+}
+
+/// The function entries of a second-level page.
+#[derive(Clone, Copy)]
+enum PageFunctions {
+    Regular {
+        page: RegularPage,
+        functions: ArrayRef<RegularFunctionEntry>,
+    },
+    Compressed {
+        page: CompressedPage,
+        functions: ArrayRef<U32>,
+        local_opcodes: ArrayRef<Opcode>,
+    },
+}
+
+impl PageFunctions {
+    /// Parse the second-level page that `page_entry` points to. This must not be
+    /// called for the sentinel page entry at the end of the pages list.
+    fn parse<R: Reader + ?Sized>(data: &R, page_entry: &PageEntry) -> Result<Self, Error> {
+        let page_offset: u64 = page_entry.page_offset().into();
+        let kind: u32 = (*data
+            .read_at::<U32>(page_offset)
+            .ok_or(Error::read(ReadError::PageKind, page_offset))?)
+        .into();
+        match kind {
             consts::PAGE_KIND_REGULAR => {
-                let page = RegularPage::parse(data, page_offset.into())?;
-                let functions = page.functions(data, page_offset)?;
-                let function_index =
-                    match functions.binary_search_by_key(&pc, RegularFunctionEntry::address) {
-                        Ok(i) => i,
-                        Err(insertion_index) => {
-                            if insertion_index == 0 {
-                                return Err(Error::InvalidPageEntryFirstAddress);
-                            }
-                            insertion_index - 1
-                        }
-                    };
-                let entry = &functions[function_index];
-                let fun_address = entry.address();
-                let next_fun_address = if let Some(next_entry) = functions.get(function_index + 1) {
-                    next_entry.address()
-                } else {
-                    next_page_entry.first_address()
-                };
-                Ok(Some(Function {
-                    start_address: fun_address,
-                    end_address: next_fun_address,
-                    opcode: entry.opcode(),
-                }))
+                let page = *data
+                    .read_at::<RegularPage>(page_offset)
+                    .ok_or(Error::read(ReadError::RegularPage, page_offset))?;
+                let functions = ArrayRef::new(
+                    data,
+                    page_offset + u64::from(page.functions_offset()),
+                    page.functions_len().into(),
+                    ReadError::RegularPageFunctions,
+                )?;
+                Ok(PageFunctions::Regular { page, functions })
             }
             consts::PAGE_KIND_COMPRESSED => {
-                let page = CompressedPage::parse(data, page_offset.into())?;
-                let functions = page.functions(data, page_offset)?;
-                let page_address = page_entry.first_address();
-                let rel_pc = pc - page_address;
-                let function_index = match functions.binary_search_by_key(&rel_pc, |&entry| {
-                    CompressedFunctionEntry::new(entry.into()).relative_address()
-                }) {
-                    Ok(i) => i,
-                    Err(insertion_index) => {
-                        if insertion_index == 0 {
-                            return Err(Error::InvalidPageEntryFirstAddress);
-                        }
-                        insertion_index - 1
-                    }
-                };
-
-                let entry = CompressedFunctionEntry::new(functions[function_index].into());
-                let fun_address = page_address + entry.relative_address();
-                let next_fun_address = if let Some(next_entry) = functions.get(function_index + 1) {
-                    let next_entry = CompressedFunctionEntry::new((*next_entry).into());
-                    page_address + next_entry.relative_address()
-                } else {
-                    next_page_entry.first_address()
-                };
-
-                let opcode_index: usize = entry.opcode_index().into();
-                let opcode = if opcode_index < global_opcodes.len() {
-                    global_opcodes[opcode_index].opcode()
-                } else {
-                    let local_opcodes = page.local_opcodes(data, page_offset)?;
-                    let local_index = opcode_index - global_opcodes.len();
-                    local_opcodes[local_index].opcode()
-                };
-                Ok(Some(Function {
-                    start_address: fun_address,
-                    end_address: next_fun_address,
-                    opcode,
-                }))
+                let page = *data
+                    .read_at::<CompressedPage>(page_offset)
+                    .ok_or(Error::read(ReadError::CompressedPage, page_offset))?;
+                let functions = ArrayRef::new(
+                    data,
+                    page_offset + u64::from(page.functions_offset()),
+                    page.functions_len().into(),
+                    ReadError::CompressedPageFunctions,
+                )?;
+                let local_opcodes = ArrayRef::new(
+                    data,
+                    page_offset + u64::from(page.local_opcodes_offset()),
+                    page.local_opcodes_len().into(),
+                    ReadError::LocalOpcodes,
+                )?;
+                Ok(PageFunctions::Compressed {
+                    page,
+                    functions,
+                    local_opcodes,
+                })
             }
             consts::PAGE_KIND_SENTINEL => {
-                // Only the last page should be a sentinel page, and we've already checked earlier
-                // that we're not in the last page.
-                Err(Error::UnexpectedSentinelPage)
+                // Only the last page should be a sentinel page, and the callers
+                // never parse the last page.
+                Err(Error::from(ErrorKind::UnexpectedSentinelPage).with_section_offset(page_offset))
             }
-            _ => Err(Error::InvalidPageKind),
+            _ => Err(Error::from(ErrorKind::InvalidPageKind).with_section_offset(page_offset)),
         }
     }
 }
 
-/// An iterator over the functions in an UnwindInfo page.
-pub struct FunctionIter<'a> {
+/// Look up the opcode for a compressed function entry in the global or the
+/// local opcode palette.
+fn resolve_opcode<R: Reader + ?Sized>(
+    data: &R,
+    global_opcodes: &ArrayRef<Opcode>,
+    local_opcodes: &ArrayRef<Opcode>,
+    entry: CompressedFunctionEntry,
+) -> Result<u32, Error> {
+    resolve_opcode_and_source(data, global_opcodes, local_opcodes, entry).map(|(opcode, _)| opcode)
+}
+
+/// The address of a compressed function entry: the page's first address
+/// plus the entry's relative address.
+fn compressed_entry_address(
+    page_address: u32,
+    entry: CompressedFunctionEntry,
+) -> Result<u32, Error> {
+    page_address
+        .checked_add(entry.relative_address())
+        .ok_or_else(|| ErrorKind::AddressOverflow.into())
+}
+
+/// Like [`resolve_opcode`], but also returns which palette the opcode came from.
+fn resolve_opcode_and_source<R: Reader + ?Sized>(
+    data: &R,
+    global_opcodes: &ArrayRef<Opcode>,
+    local_opcodes: &ArrayRef<Opcode>,
+    entry: CompressedFunctionEntry,
+) -> Result<(u32, OpcodeSource), Error> {
+    let opcode_index: usize = entry.opcode_index().into();
+    let (opcode, source) = if opcode_index < global_opcodes.len() {
+        (
+            global_opcodes.get(data, opcode_index)?,
+            OpcodeSource::GlobalPalette {
+                index: opcode_index as u8,
+            },
+        )
+    } else {
+        let local_index = opcode_index - global_opcodes.len();
+        if local_index >= local_opcodes.len() {
+            return Err(ReadError::LocalOpcodes.into());
+        }
+        (
+            local_opcodes.get(data, local_index)?,
+            OpcodeSource::LocalPalette {
+                index: local_index as u8,
+            },
+        )
+    };
+    Ok((opcode.opcode(), source))
+}
+
+/// An iterator over the functions in an UnwindInfo.
+///
+/// Functions can be taken from both ends, with [`FunctionIter::next`] and
+/// [`FunctionIter::next_back`]. [`FunctionIter::nth`] and
+/// [`FunctionIter::skip`] skip whole pages by their function counts, without
+/// reading the skipped entries.
+pub struct FunctionIter<'a, R: Reader + ?Sized = [u8]> {
     /// The full __unwind_info section data.
-    data: &'a [u8],
+    data: &'a R,
 
     /// The list of global opcodes.
-    global_opcodes: &'a [Opcode],
+    global_opcodes: ArrayRef<Opcode>,
+
+    /// The page entries of the pages which haven't been started from either
+    /// end, followed by the page entry whose first address ends the last of
+    /// these pages.
+    pages: ArrayRef<PageEntry>,
 
-    /// The slice of the remaining to-be-iterated-over pages.
-    pages: &'a [PageEntry],
+    /// The index of the first page in `pages`. Used for error contexts.
+    pages_start_index: usize,
 
-    /// The page whose functions we're iterating over at the moment.
-    cur_page: Option<PageWithPartialFunctions<'a>>,
+    /// The page whose functions are taken by `next`.
+    front: Option<PartialPage>,
+
+    /// The page whose functions are taken by `next_back`.
+    back: Option<PartialPage>,
 }
 
-/// The current page of the function iterator.
-/// The functions field is the slice of the remaining to-be-iterated-over functions.
+/// The remaining to-be-iterated-over functions of a page.
 #[derive(Clone, Copy)]
-enum PageWithPartialFunctions<'a> {
-    Regular {
-        next_page_address: u32,
-        functions: &'a [RegularFunctionEntry],
-    },
+struct PartialPage {
+    page_index: usize,
+
+    /// The index of the first remaining function within the page.
+    first_entry_index: usize,
+
+    /// The end address of the last remaining function. This is the next
+    /// page's first address, until functions are taken from the back.
+    end_address: u32,
+
+    functions: PartialPageFunctions,
+}
+
+#[derive(Clone, Copy)]
+enum PartialPageFunctions {
+    Regular(ArrayRef<RegularFunctionEntry>),
     Compressed {
         page_address: u32,
-        next_page_address: u32,
-        local_opcodes: &'a [Opcode],
-        functions: &'a [U32],
+        local_opcodes: ArrayRef<Opcode>,
+        functions: ArrayRef<U32>,
     },
 }
 
-impl<'a> FunctionIter<'a> {
+impl PartialPage {
+    fn len(&self) -> usize {
+        match &self.functions {
+            PartialPageFunctions::Regular(functions) => functions.len(),
+            PartialPageFunctions::Compressed { functions, .. } => functions.len(),
+        }
+    }
+
+    /// Reads the remaining function at `index`, which must be in bounds.
+    fn function<R: Reader + ?Sized>(
+        &self,
+        data: &R,
+        global_opcodes: &ArrayRef<Opcode>,
+        index: usize,
+    ) -> Result<Function, Error> {
+        let is_last = index + 1 == self.len();
+        let function = match &self.functions {
+            PartialPageFunctions::Regular(functions) => {
+                let entry = functions.get(data, index)?;
+                let end_address = if is_last {
+                    self.end_address
+                } else {
+                    functions.get(data, index + 1)?.address()
+                };
+                Function {
+                    start_address: entry.address(),
+                    end_address,
+                    opcode: entry.opcode(),
+                }
+            }
+            PartialPageFunctions::Compressed {
+                page_address,
+                local_opcodes,
+                functions,
+            } => {
+                let entry = CompressedFunctionEntry::new(functions.get(data, index)?.into());
+                let end_address = if is_last {
+                    self.end_address
+                } else {
+                    let next_entry =
+                        CompressedFunctionEntry::new(functions.get(data, index + 1)?.into());
+                    compressed_entry_address(*page_address, next_entry)?
+                };
+                Function {
+                    start_address: compressed_entry_address(*page_address, entry)?,
+                    end_address,
+                    opcode: resolve_opcode(data, global_opcodes, local_opcodes, entry)?,
+                }
+            }
+        };
+        Ok(function)
+    }
+
+    /// Drops the first `count` remaining functions, which must not be more
+    /// than [`PartialPage::len`].
+    fn skip_front(&mut self, count: usize) {
+        match &mut self.functions {
+            PartialPageFunctions::Regular(functions) => *functions = functions.split_at(count).1,
+            PartialPageFunctions::Compressed { functions, .. } => {
+                *functions = functions.split_at(count).1
+            }
+        }
+        self.first_entry_index += count;
+    }
+
+    /// Drops the last remaining function, which starts at `start_address`.
+    fn skip_back(&mut self, start_address: u32) {
+        let len = self.len() - 1;
+        match &mut self.functions {
+            PartialPageFunctions::Regular(functions) => *functions = functions.split_at(len).0,
+            PartialPageFunctions::Compressed { functions, .. } => {
+                *functions = functions.split_at(len).0
+            }
+        }
+        self.end_address = start_address;
+    }
+
+    fn pop_front<R: Reader + ?Sized>(
+        &mut self,
+        data: &R,
+        global_opcodes: &ArrayRef<Opcode>,
+    ) -> Result<Option<Function>, Error> {
+        if self.len() == 0 {
+            return Ok(None);
+        }
+        let function = self
+            .function(data, global_opcodes, 0)
+            .map_err(|err| self.add_context(err, self.first_entry_index))?;
+        self.skip_front(1);
+        Ok(Some(function))
+    }
+
+    fn pop_back<R: Reader + ?Sized>(
+        &mut self,
+        data: &R,
+        global_opcodes: &ArrayRef<Opcode>,
+    ) -> Result<Option<Function>, Error> {
+        let Some(index) = self.len().checked_sub(1) else {
+            return Ok(None);
+        };
+        let function = self
+            .function(data, global_opcodes, index)
+            .map_err(|err| self.add_context(err, self.first_entry_index + index))?;
+        self.skip_back(function.start_address);
+        Ok(Some(function))
+    }
+
+    fn add_context(&self, err: Error, entry_index: usize) -> Error {
+        err.with_page_index(self.page_index)
+            .with_entry_index(entry_index)
+    }
+}
+
+impl<'a, R: Reader + ?Sized> FunctionIter<'a, R> {
+    /// Returns the next function. Errors have the index of the page and,
+    /// if the page could be read, of the entry in their [`ErrorContext`].
     #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Result<Option<Function>, Error> {
         loop {
-            let cur_page = if let Some(cur_page) = self.cur_page.as_mut() {
-                cur_page
-            } else {
-                let cur_page = match self.next_page()? {
-                    Some(page) => page,
+            if let Some(front) = &mut self.front {
+                if let Some(function) = front.pop_front(self.data, &self.global_opcodes)? {
+                    return Ok(Some(function));
+                }
+                self.front = None;
+            }
+            match self.start_front_page()? {
+                Some(page) => self.front = Some(page),
+                // Only the back page has functions left, if any.
+                None => match &mut self.back {
+                    Some(back) => return back.pop_front(self.data, &self.global_opcodes),
                     None => return Ok(None),
-                };
-                self.cur_page.insert(cur_page)
-            };
+                },
+            }
+        }
+    }
 
-            match cur_page {
-                PageWithPartialFunctions::Regular {
-                    next_page_address,
-                    functions,
-                } => {
-                    if let Some((entry, remainder)) = functions.split_first() {
-                        *functions = remainder;
-                        let start_address = entry.address();
-                        let end_address = remainder
-                            .first()
-                            .map(RegularFunctionEntry::address)
-                            .unwrap_or(*next_page_address);
-                        return Ok(Some(Function {
-                            start_address,
-                            end_address,
-                            opcode: entry.opcode(),
-                        }));
-                    }
-                }
-                PageWithPartialFunctions::Compressed {
-                    page_address,
-                    functions,
-                    next_page_address,
-                    local_opcodes,
-                } => {
-                    if let Some((entry, remainder)) = functions.split_first() {
-                        *functions = remainder;
-                        let entry = CompressedFunctionEntry::new((*entry).into());
-                        let start_address = *page_address + entry.relative_address();
-                        let end_address = match remainder.first() {
-                            Some(next_entry) => {
-                                let next_entry = CompressedFunctionEntry::new((*next_entry).into());
-                                *page_address + next_entry.relative_address()
-                            }
-                            None => *next_page_address,
-                        };
-                        let opcode_index: usize = entry.opcode_index().into();
-                        let opcode = if opcode_index < self.global_opcodes.len() {
-                            self.global_opcodes[opcode_index].opcode()
-                        } else {
-                            let local_index = opcode_index - self.global_opcodes.len();
-                            local_opcodes[local_index].opcode()
-                        };
-                        return Ok(Some(Function {
-                            start_address,
-                            end_address,
-                            opcode,
-                        }));
-                    }
+    /// Returns the last remaining function. The last function of each page
+    /// ends at the first address of the next page entry, like in
+    /// [`FunctionIter::next`].
+    pub fn next_back(&mut self) -> Result<Option<Function>, Error> {
+        loop {
+            if let Some(back) = &mut self.back {
+                if let Some(function) = back.pop_back(self.data, &self.global_opcodes)? {
+                    return Ok(Some(function));
                 }
+                self.back = None;
+            }
+            match self.start_back_page()? {
+                Some(page) => self.back = Some(page),
+                // Only the front page has functions left, if any.
+                None => match &mut self.front {
+                    Some(front) => return front.pop_back(self.data, &self.global_opcodes),
+                    None => return Ok(None),
+                },
             }
-            self.cur_page = None;
         }
     }
 
-    fn next_page(&mut self) -> Result<Option<PageWithPartialFunctions<'a>>, Error> {
-        let (page_entry, remainder) = match self.pages.split_first() {
-            Some(split) => split,
-            None => return Ok(None),
-        };
-
-        self.pages = remainder;
+    /// Returns the `n`th next function, like `Iterator::nth`.
+    ///
+    /// Pages which only contain skipped functions are skipped as a whole:
+    /// only their headers are read.
+    pub fn nth(&mut self, n: usize) -> Result<Option<Function>, Error> {
+        self.skip_functions(n)?;
+        self.next()
+    }
 
-        let next_page_entry = match remainder.first() {
-            Some(entry) => entry,
-            None => return Ok(None),
-        };
+    /// Skips the next `n` functions, like `Iterator::skip`. See
+    /// [`FunctionIter::nth`].
+    pub fn skip(mut self, n: usize) -> Result<Self, Error> {
+        self.skip_functions(n)?;
+        Ok(self)
+    }
 
-        let page_offset = page_entry.page_offset();
-        let page_address = page_entry.first_address();
-        let next_page_address = next_page_entry.first_address();
-        let data = self.data;
-        let cur_page = match page_entry.page_kind(data)? {
-            consts::PAGE_KIND_REGULAR => {
-                let page = RegularPage::parse(data, page_offset.into())?;
-                PageWithPartialFunctions::Regular {
-                    functions: page.functions(data, page_offset)?,
-                    next_page_address,
+    fn skip_functions(&mut self, mut n: usize) -> Result<(), Error> {
+        loop {
+            if let Some(front) = &mut self.front {
+                if n < front.len() {
+                    front.skip_front(n);
+                    return Ok(());
                 }
+                n -= front.len();
+                self.front = None;
             }
-            consts::PAGE_KIND_COMPRESSED => {
-                let page = CompressedPage::parse(data, page_offset.into())?;
-                PageWithPartialFunctions::Compressed {
-                    page_address,
-                    next_page_address,
-                    functions: page.functions(data, page_offset)?,
-                    local_opcodes: page.local_opcodes(data, page_offset)?,
+            match self.start_front_page()? {
+                Some(page) => self.front = Some(page),
+                None => {
+                    if let Some(back) = &mut self.back {
+                        back.skip_front(n.min(back.len()));
+                    }
+                    return Ok(());
                 }
             }
-            consts::PAGE_KIND_SENTINEL => return Err(Error::UnexpectedSentinelPage),
-            _ => return Err(Error::InvalidPageKind),
+        }
+    }
+
+    /// Takes the first page out of `pages`, or returns `None` if only the
+    /// page entry after the last page is left.
+    fn start_front_page(&mut self) -> Result<Option<PartialPage>, Error> {
+        if self.pages.len() < 2 {
+            return Ok(None);
+        }
+        let page_index = self.pages_start_index;
+        let page = self
+            .start_page(0)
+            .map_err(|err| err.with_page_index(page_index))?;
+        self.pages = self.pages.split_at(1).1;
+        self.pages_start_index += 1;
+        Ok(Some(page))
+    }
+
+    /// Takes the last page out of `pages`, or returns `None` if only the
+    /// page entry after the last page is left.
+    fn start_back_page(&mut self) -> Result<Option<PartialPage>, Error> {
+        let Some(index) = self.pages.len().checked_sub(2) else {
+            return Ok(None);
         };
-        Ok(Some(cur_page))
+        let page_index = self.pages_start_index + index;
+        let page = self
+            .start_page(index)
+            .map_err(|err| err.with_page_index(page_index))?;
+        // The page's entry stays in `pages`, as the end of the previous page.
+        self.pages = self.pages.split_at(index + 1).0;
+        Ok(Some(page))
+    }
+
+    /// Parses the page at `index` in `pages`. This only reads the page
+    /// entries and the page header.
+    fn start_page(&self, index: usize) -> Result<PartialPage, Error> {
+        let data = self.data;
+        let page_entry = self.pages.get(data, index)?;
+        let next_page_entry = self.pages.get(data, index + 1)?;
+        let functions = match PageFunctions::parse(data, &page_entry)? {
+            PageFunctions::Regular { functions, .. } => PartialPageFunctions::Regular(functions),
+            PageFunctions::Compressed {
+                functions,
+                local_opcodes,
+                ..
+            } => PartialPageFunctions::Compressed {
+                page_address: page_entry.first_address(),
+                local_opcodes,
+                functions,
+            },
+        };
+        Ok(PartialPage {
+            page_index: self.pages_start_index + index,
+            first_entry_index: 0,
+            end_address: next_page_entry.first_address(),
+            functions,
+        })
     }
 }
@@ -0,0 +1,231 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::opcodes::{OpcodeArm64, OpcodeBitfield, OpcodeX86, OpcodeX86_64};
+use crate::raw::consts::*;
+use crate::reader::Reader;
+use crate::{Arch, Error, Function, UnwindInfo};
+
+/// A reason why an opcode is suspicious, even though it could be parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OpcodeWarning {
+    /// The opcode kind is not defined for the architecture.
+    UnrecognizedKind(u8),
+
+    /// Bits which are not used by the opcode kind are set. The value has the
+    /// unused bits of the lower 24 bits of the opcode.
+    UnusedBitsSet(u32),
+
+    /// x86 and x86_64 frame-based: a register is stored in a slot which is at
+    /// or above the frame pointer, because the slot index is not below the
+    /// stack offset (in words). The slot index counts from the lowest bits of
+    /// the opcode, i.e. from the lowest address.
+    SavedRegisterBeyondStackOffset { slot: u8, stack_offset_in_words: u8 },
+
+    /// x86 and x86_64 frame-based: a register slot contains an undefined
+    /// register number.
+    InvalidRegisterNumber { slot: u8, register: u8 },
+
+    /// x86 and x86_64 frame-based: the same register is saved more than once.
+    DuplicateSavedRegister(u8),
+
+    /// x86 and x86_64 frameless: the saved register count is larger than 6.
+    /// `parse` returns `InvalidFrameless` for these opcodes.
+    InvalidRegisterCount(u8),
+
+    /// x86 and x86_64 frameless: the register permutation doesn't encode a
+    /// valid permutation for the register count. `parse` either returns
+    /// `InvalidFrameless` or ignores the extra bits for these opcodes.
+    InvalidRegisterPermutation { count: u8, permutation: u16 },
+
+    /// x86 and x86_64 frameless immediate: the stack size is too small to hold
+    /// the return address and the saved registers.
+    StackSizeTooSmall {
+        stack_size_in_bytes: u16,
+        register_count: u8,
+    },
+}
+
+impl fmt::Display for OpcodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpcodeWarning::UnrecognizedKind(kind) => write!(f, "unrecognized opcode kind {}", kind),
+            OpcodeWarning::UnusedBitsSet(bits) => write!(f, "unused bits 0x{:06x} are set", bits),
+            OpcodeWarning::SavedRegisterBeyondStackOffset {
+                slot,
+                stack_offset_in_words,
+            } => write!(
+                f,
+                "register slot {} is beyond the stack offset of {} words",
+                slot, stack_offset_in_words
+            ),
+            OpcodeWarning::InvalidRegisterNumber { slot, register } => {
+                write!(
+                    f,
+                    "register slot {} has invalid register number {}",
+                    slot, register
+                )
+            }
+            OpcodeWarning::DuplicateSavedRegister(register) => {
+                write!(f, "register number {} is saved more than once", register)
+            }
+            OpcodeWarning::InvalidRegisterCount(count) => {
+                write!(f, "invalid saved register count {}", count)
+            }
+            OpcodeWarning::InvalidRegisterPermutation { count, permutation } => write!(
+                f,
+                "invalid permutation {} for {} saved registers",
+                permutation, count
+            ),
+            OpcodeWarning::StackSizeTooSmall {
+                stack_size_in_bytes,
+                register_count,
+            } => write!(
+                f,
+                "stack size {} is too small for the return address and {} saved registers",
+                stack_size_in_bytes, register_count
+            ),
+        }
+    }
+}
+
+/// The warnings for a function entry, as returned by [`UnwindInfo::lint`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionLint {
+    pub function: Function,
+    pub warnings: Vec<OpcodeWarning>,
+}
+
+impl OpcodeX86_64 {
+    /// Checks the opcode for encodings which parse but can't be correct.
+    pub fn lint(opcode: u32) -> Vec<OpcodeWarning> {
+        lint_x86(opcode, 8)
+    }
+}
+
+impl OpcodeX86 {
+    /// Checks the opcode for encodings which parse but can't be correct.
+    pub fn lint(opcode: u32) -> Vec<OpcodeWarning> {
+        lint_x86(opcode, 4)
+    }
+}
+
+impl OpcodeArm64 {
+    /// Checks the opcode for encodings which parse but can't be correct.
+    pub fn lint(opcode: u32) -> Vec<OpcodeWarning> {
+        let bitfield = OpcodeBitfield::new(opcode);
+        let mut warnings = Vec::new();
+        let unused_bits = match bitfield.kind() {
+            OPCODE_KIND_NULL => bitfield.specific_bits(),
+            // libunwind also restores the register pairs of bits 0 to 8 for
+            // frameless functions.
+            OPCODE_KIND_ARM64_FRAMELESS => bitfield.specific_bits() & 0xe00,
+            OPCODE_KIND_ARM64_DWARF => 0,
+            OPCODE_KIND_ARM64_FRAMEBASED => bitfield.specific_bits() & !0x1ff,
+            kind => {
+                warnings.push(OpcodeWarning::UnrecognizedKind(kind));
+                0
+            }
+        };
+        if unused_bits != 0 {
+            warnings.push(OpcodeWarning::UnusedBitsSet(unused_bits));
+        }
+        warnings
+    }
+}
+
+impl Arch {
+    /// Checks the opcode for encodings which parse but can't be correct, using
+    /// the lint for this architecture.
+    pub fn lint_opcode(self, opcode: u32) -> Vec<OpcodeWarning> {
+        match self {
+            Arch::X86 => OpcodeX86::lint(opcode),
+            Arch::X86_64 => OpcodeX86_64::lint(opcode),
+            Arch::Arm64 => OpcodeArm64::lint(opcode),
+        }
+    }
+}
+
+fn lint_x86(opcode: u32, word_size: u16) -> Vec<OpcodeWarning> {
+    let bitfield = OpcodeBitfield::new(opcode);
+    let mut warnings = Vec::new();
+    let unused_bits = match bitfield.kind() {
+        OPCODE_KIND_NULL => bitfield.specific_bits(),
+        OPCODE_KIND_X86_FRAMEBASED => {
+            let stack_offset_in_words = ((opcode >> 16) & 0xff) as u8;
+            let mut seen = [false; 8];
+            for slot in 0..5 {
+                let register = ((opcode >> (slot * 3)) & 0b111) as u8;
+                if register == 0 {
+                    continue;
+                }
+                if slot >= stack_offset_in_words {
+                    warnings.push(OpcodeWarning::SavedRegisterBeyondStackOffset {
+                        slot,
+                        stack_offset_in_words,
+                    });
+                }
+                if register == 7 {
+                    warnings.push(OpcodeWarning::InvalidRegisterNumber { slot, register });
+                } else if seen[register as usize] {
+                    warnings.push(OpcodeWarning::DuplicateSavedRegister(register));
+                }
+                seen[register as usize] = true;
+            }
+            opcode & 0x8000
+        }
+        kind @ (OPCODE_KIND_X86_FRAMELESS_IMMEDIATE | OPCODE_KIND_X86_FRAMELESS_INDIRECT) => {
+            let count = ((opcode >> 10) & 0b111) as u8;
+            let permutation = (opcode & 0b11_1111_1111) as u16;
+            if count > 6 {
+                warnings.push(OpcodeWarning::InvalidRegisterCount(count));
+            } else if permutation >= max_permutation(count) {
+                warnings.push(OpcodeWarning::InvalidRegisterPermutation { count, permutation });
+            }
+            if kind == OPCODE_KIND_X86_FRAMELESS_IMMEDIATE {
+                let stack_size_in_bytes = ((opcode >> 16) & 0xff) as u16 * word_size;
+                if count <= 6 && stack_size_in_bytes < (u16::from(count) + 1) * word_size {
+                    warnings.push(OpcodeWarning::StackSizeTooSmall {
+                        stack_size_in_bytes,
+                        register_count: count,
+                    });
+                }
+                // The stack adjust bits are only used by the indirect kind.
+                opcode & 0xe000
+            } else {
+                0
+            }
+        }
+        OPCODE_KIND_X86_DWARF => 0,
+        kind => {
+            warnings.push(OpcodeWarning::UnrecognizedKind(kind));
+            0
+        }
+    };
+    if unused_bits != 0 {
+        warnings.push(OpcodeWarning::UnusedBitsSet(unused_bits));
+    }
+    warnings
+}
+
+/// The number of valid permutation encodings for `count` of 6 registers,
+/// i.e. 6! / (6 - count)!.
+fn max_permutation(count: u8) -> u16 {
+    (0..u16::from(count)).map(|i| 6 - i).product()
+}
+
+impl<R: Reader + ?Sized> UnwindInfo<'_, R> {
+    /// Runs the opcode lint for `arch` over every function entry. Only the
+    /// entries with at least one warning are returned.
+    pub fn lint(&self, arch: Arch) -> Result<Vec<FunctionLint>, Error> {
+        let mut lints = Vec::new();
+        let mut iter = self.functions();
+        while let Some(function) = iter.next()? {
+            let warnings = arch.lint_opcode(function.opcode);
+            if !warnings.is_empty() {
+                lints.push(FunctionLint { function, warnings });
+            }
+        }
+        Ok(lints)
+    }
+}
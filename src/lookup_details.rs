@@ -0,0 +1,221 @@
+use core::fmt;
+
+use crate::opcodes::OpcodeBitfield;
+use crate::raw::{CompactUnwindInfoHeader, CompressedFunctionEntry, LsdaEntry, U32};
+use crate::reader::{ArrayRef, Reader};
+use crate::{Error, Function, OpcodeSource, PageView, ReadError, UnwindInfo};
+
+/// The kind of a second-level page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PageKind {
+    Regular,
+    Compressed,
+}
+
+/// Where the end address of a looked-up function came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EndAddressSource {
+    /// The start address of the next entry in the same page.
+    NextEntry,
+
+    /// The first address of the next page entry, because the function is
+    /// the last entry of its page.
+    NextPage,
+}
+
+/// The result of [`UnwindInfo::lookup_details`]: the function, and the path
+/// the lookup took to find it.
+///
+/// The [`Display`](fmt::Display) implementation explains the path, one step
+/// per line.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LookupDetails {
+    pub function: Function,
+
+    /// The index of the second-level page which covers the address.
+    pub page_index: usize,
+
+    pub page_kind: PageKind,
+
+    /// The index of the function entry within the page.
+    pub entry_index: usize,
+
+    /// The raw function entry, for compressed pages.
+    pub compressed_entry: Option<CompressedFunctionEntry>,
+
+    /// Which palette the opcode came from, and its index there.
+    pub opcode_source: OpcodeSource,
+
+    pub end_address_source: EndAddressSource,
+
+    /// The opcode's personality slot, a one-based index into the personality
+    /// array, or `None` if the slot is zero.
+    pub personality_index: Option<u8>,
+
+    /// The personality array entry for the slot, which is the image offset of
+    /// a pointer to the personality function. `None` if there's no slot, or
+    /// if the slot is out of bounds.
+    pub personality: Option<u32>,
+
+    /// The image offset of the function's LSDA, if the opcode has the LSDA
+    /// bit set and the page's LSDA descriptors contain the function.
+    pub lsda: Option<u32>,
+}
+
+impl<R: Reader + ?Sized> UnwindInfo<'_, R> {
+    /// Like [`UnwindInfo::lookup`], but also returns how the function was
+    /// found, and the function's personality and LSDA.
+    pub fn lookup_details(&self, pc: u32) -> Result<Option<LookupDetails>, Error> {
+        self.lookup_details_impl(pc)
+            .map_err(|err| err.with_address(pc))
+    }
+
+    fn lookup_details_impl(&self, pc: u32) -> Result<Option<LookupDetails>, Error> {
+        let Some(page_index) = self.page_index_of(pc)? else {
+            return Ok(None);
+        };
+        let Some(page) = self.page(page_index)? else {
+            return Ok(None);
+        };
+        let Some(entry_index) = page.index_of(pc)? else {
+            return Ok(None);
+        };
+        let Some(page_function) = page.function_at(entry_index)? else {
+            return Ok(None);
+        };
+        let (page_kind, compressed_entry) = match &page {
+            PageView::Regular(_) => (PageKind::Regular, None),
+            PageView::Compressed(page) => (PageKind::Compressed, page.entry_at(entry_index)?),
+        };
+        let end_address_source = if entry_index + 1 < page.len() {
+            EndAddressSource::NextEntry
+        } else {
+            EndAddressSource::NextPage
+        };
+
+        let function = page_function.function;
+        let bitfield = OpcodeBitfield::new(function.opcode);
+        let personality_index = Some(bitfield.personality_index()).filter(|index| *index != 0);
+        let personality = match personality_index {
+            Some(index) => self.personality(index)?,
+            None => None,
+        };
+        let lsda = if bitfield.has_lsda() {
+            self.lsda(page_index, function.start_address)
+                .map_err(|err| err.with_page_index(page_index))?
+        } else {
+            None
+        };
+
+        Ok(Some(LookupDetails {
+            function,
+            page_index,
+            page_kind,
+            entry_index,
+            compressed_entry,
+            opcode_source: page_function.opcode_source,
+            end_address_source,
+            personality_index,
+            personality,
+            lsda,
+        }))
+    }
+
+    /// Reads the entry for the one-based personality slot `index`.
+    fn personality(&self, index: u8) -> Result<Option<u32>, Error> {
+        let header = *self
+            .data
+            .read_at::<CompactUnwindInfoHeader>(0)
+            .ok_or(Error::read(ReadError::Header, 0))?;
+        let index = u32::from(index) - 1;
+        if index >= header.personalities_len() {
+            return Ok(None);
+        }
+        let offset = u64::from(header.personalities_offset()) + u64::from(index) * 4;
+        let personality = *self
+            .data
+            .read_at::<U32>(offset)
+            .ok_or(Error::read(ReadError::Personalities, offset))?;
+        Ok(Some(personality.into()))
+    }
+
+    /// Looks up the LSDA of the function at `start_address` in the LSDA
+    /// descriptors of the page at `page_index`.
+    fn lsda(&self, page_index: usize, start_address: u32) -> Result<Option<u32>, Error> {
+        let lsdas_start = self.pages.get(self.data, page_index)?.lsda_index_offset();
+        let lsdas_end = self
+            .pages
+            .get(self.data, page_index + 1)?
+            .lsda_index_offset();
+        let lsdas = ArrayRef::<LsdaEntry>::new(
+            self.data,
+            lsdas_start.into(),
+            lsdas_end.saturating_sub(lsdas_start) as usize / core::mem::size_of::<LsdaEntry>(),
+            ReadError::Lsdas,
+        )?;
+        match lsdas.binary_search_by_key(self.data, &start_address, LsdaEntry::function_offset)? {
+            Ok(index) => Ok(Some(lsdas.get(self.data, index)?.lsda_offset())),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+impl fmt::Display for LookupDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.page_kind {
+            PageKind::Regular => "regular",
+            PageKind::Compressed => "compressed",
+        };
+        writeln!(
+            f,
+            "page {} ({}), entry {}",
+            self.page_index, kind, self.entry_index
+        )?;
+        if let Some(entry) = self.compressed_entry {
+            writeln!(
+                f,
+                "raw entry 0x{:08x}: opcode index {}, relative address 0x{:x}",
+                entry.0,
+                entry.opcode_index(),
+                entry.relative_address()
+            )?;
+        }
+        match self.opcode_source {
+            OpcodeSource::GlobalPalette { index } => writeln!(
+                f,
+                "opcode 0x{:08x} from the global palette at index {}",
+                self.function.opcode, index
+            )?,
+            OpcodeSource::LocalPalette { index } => writeln!(
+                f,
+                "opcode 0x{:08x} from the page's local palette at index {}",
+                self.function.opcode, index
+            )?,
+            OpcodeSource::Inline => {
+                writeln!(f, "opcode 0x{:08x} stored inline", self.function.opcode)?
+            }
+        }
+        let end_source = match self.end_address_source {
+            EndAddressSource::NextEntry => "the next entry",
+            EndAddressSource::NextPage => "the next page",
+        };
+        writeln!(
+            f,
+            "function 0x{:08x}..0x{:08x}, end address from {}",
+            self.function.start_address, self.function.end_address, end_source
+        )?;
+        match (self.personality_index, self.personality) {
+            (None, _) => writeln!(f, "no personality")?,
+            (Some(index), Some(personality)) => {
+                writeln!(f, "personality slot {}: 0x{:08x}", index, personality)?
+            }
+            (Some(index), None) => writeln!(f, "personality slot {}: out of bounds", index)?,
+        }
+        let has_lsda = OpcodeBitfield::new(self.function.opcode).has_lsda();
+        match (has_lsda, self.lsda) {
+            (false, _) => write!(f, "no LSDA"),
+            (true, Some(lsda)) => write!(f, "LSDA at 0x{:08x}", lsda),
+            (true, None) => write!(f, "LSDA bit set, but no LSDA descriptor found"),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Just enough mach-O parsing to find segments and sections.
+
+use zerocopy_derive::*;
+
+use crate::raw::{U32, U64};
+use crate::reader::SliceReader;
+
+pub const MH_MAGIC_64: u32 = 0xfeed_facf;
+pub const LC_SEGMENT_64: u32 = 0x19;
+
+#[derive(Unaligned, FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct MachHeader64 {
+    pub magic: U32,
+    pub cputype: U32,
+    pub cpusubtype: U32,
+    pub filetype: U32,
+    pub ncmds: U32,
+    pub sizeofcmds: U32,
+    pub flags: U32,
+    pub reserved: U32,
+}
+
+#[derive(Unaligned, FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct LoadCommand {
+    pub cmd: U32,
+    pub cmdsize: U32,
+}
+
+#[derive(Unaligned, FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct SegmentCommand64 {
+    pub cmd: U32,
+    pub cmdsize: U32,
+    pub segname: [u8; 16],
+    pub vmaddr: U64,
+    pub vmsize: U64,
+    pub fileoff: U64,
+    pub filesize: U64,
+    pub maxprot: U32,
+    pub initprot: U32,
+    pub nsects: U32,
+    pub flags: U32,
+}
+
+#[derive(Unaligned, FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Section64 {
+    pub sectname: [u8; 16],
+    pub segname: [u8; 16],
+    pub addr: U64,
+    pub size: U64,
+    pub offset: U32,
+    pub align: U32,
+    pub reloff: U32,
+    pub nreloc: U32,
+    pub flags: U32,
+    pub reserved1: U32,
+    pub reserved2: U32,
+    pub reserved3: U32,
+}
+
+/// Compare a fixed-size, zero-padded mach-O name with `name`.
+pub fn name_eq(padded: &[u8; 16], name: &[u8]) -> bool {
+    let len = padded.iter().position(|&b| b == 0).unwrap_or(16);
+    &padded[..len] == name
+}
+
+/// An iterator over the `LC_SEGMENT_64` commands of a 64-bit mach-O header,
+/// and the sections of each segment.
+pub struct Segments<'data> {
+    data: &'data [u8],
+    offset: u64,
+    remaining_commands: u32,
+}
+
+impl<'data> Segments<'data> {
+    /// Parse the mach-O header at `header_offset` in `data`. Returns `None` if
+    /// the header can't be read or is not a 64-bit little-endian mach-O header.
+    pub fn parse(data: &'data [u8], header_offset: u64) -> Option<Self> {
+        let header = data.read_ref_at::<MachHeader64>(header_offset)?;
+        if u32::from(header.magic) != MH_MAGIC_64 {
+            return None;
+        }
+        Some(Self {
+            data,
+            offset: header_offset + core::mem::size_of::<MachHeader64>() as u64,
+            remaining_commands: header.ncmds.into(),
+        })
+    }
+}
+
+impl<'data> Iterator for Segments<'data> {
+    type Item = (&'data SegmentCommand64, &'data [Section64]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining_commands > 0 {
+            self.remaining_commands -= 1;
+            let command = self.data.read_ref_at::<LoadCommand>(self.offset)?;
+            let command_offset = self.offset;
+            let cmdsize: u32 = command.cmdsize.into();
+            if cmdsize == 0 {
+                return None;
+            }
+            self.offset += u64::from(cmdsize);
+            if u32::from(command.cmd) != LC_SEGMENT_64 {
+                continue;
+            }
+            let segment = self.data.read_ref_at::<SegmentCommand64>(command_offset)?;
+            let sections = self.data.read_slice_at::<Section64>(
+                command_offset + core::mem::size_of::<SegmentCommand64>() as u64,
+                u32::from(segment.nsects) as usize,
+            )?;
+            return Some((segment, sections));
+        }
+        None
+    }
+}
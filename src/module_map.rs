@@ -0,0 +1,253 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::stack_walker::FunctionLookup;
+use crate::{Error, Function, OwnedUnwindInfo};
+
+/// Identifies a module in a [`ModuleMap`], as returned by [`ModuleMap::add`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ModuleId(u64);
+
+/// A loaded image: where its `__TEXT` segment is mapped, its unwind info,
+/// and optionally its `__eh_frame` section.
+pub struct Module<D: AsRef<[u8]> = Arc<[u8]>> {
+    text_vmaddr: u64,
+    slide: u64,
+    text_size: u64,
+    unwind_info: OwnedUnwindInfo<D>,
+    eh_frame: Option<(D, u64)>,
+}
+
+impl<D: AsRef<[u8]>> Module<D> {
+    /// Creates a module from the `__TEXT` segment's `vmaddr` and `vmsize`, as
+    /// found in the mach-O file, and the slide which the image was loaded
+    /// with.
+    pub fn new(
+        unwind_info: OwnedUnwindInfo<D>,
+        text_vmaddr: u64,
+        text_size: u64,
+        slide: u64,
+    ) -> Self {
+        Self {
+            text_vmaddr,
+            slide,
+            text_size,
+            unwind_info,
+            eh_frame: None,
+        }
+    }
+
+    /// Adds the `__eh_frame` section data. `address` is the section's address
+    /// relative to the mach-O header, as passed to `EhFrame::parse`.
+    pub fn with_eh_frame(mut self, data: D, address: u64) -> Self {
+        self.eh_frame = Some((data, address));
+        self
+    }
+
+    /// The `__TEXT` segment's address in the mach-O file.
+    pub fn text_vmaddr(&self) -> u64 {
+        self.text_vmaddr
+    }
+
+    /// The difference between the address the image was loaded at and its
+    /// address in the mach-O file.
+    pub fn slide(&self) -> u64 {
+        self.slide
+    }
+
+    /// The address of the image's mach-O header in the process. The
+    /// addresses in the unwind info are relative to this address.
+    pub fn base_address(&self) -> u64 {
+        self.text_vmaddr.wrapping_add(self.slide)
+    }
+
+    /// The addresses of the `__TEXT` segment in the process.
+    pub fn address_range(&self) -> Range<u64> {
+        let base_address = self.base_address();
+        base_address..base_address.saturating_add(self.text_size)
+    }
+
+    pub fn unwind_info(&self) -> &OwnedUnwindInfo<D> {
+        &self.unwind_info
+    }
+
+    /// Returns the `__eh_frame` section data and its address relative to the
+    /// mach-O header, if the module has one.
+    pub fn eh_frame(&self) -> Option<(&[u8], u64)> {
+        self.eh_frame
+            .as_ref()
+            .map(|(data, address)| (data.as_ref(), *address))
+    }
+}
+
+/// The result of [`ModuleMap::lookup`].
+pub struct ModuleLookup<D: AsRef<[u8]> = Arc<[u8]>> {
+    pub id: ModuleId,
+    pub module: Arc<Module<D>>,
+
+    /// The function entry covering the address, with addresses relative to
+    /// [`Module::base_address`], or `None` if the address is in the module's
+    /// `__TEXT` segment but not covered by its unwind info.
+    pub function: Option<Function>,
+}
+
+struct ModuleEntry<D: AsRef<[u8]>> {
+    id: ModuleId,
+    address_range: Range<u64>,
+    module: Arc<Module<D>>,
+}
+
+impl<D: AsRef<[u8]>> Clone for ModuleEntry<D> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            address_range: self.address_range.clone(),
+            module: self.module.clone(),
+        }
+    }
+}
+
+/// The loaded images of a process, for looking up absolute addresses.
+///
+/// Modules are added and removed as images are loaded and unloaded, for
+/// example from dyld's image notifications. All methods take `&self`, so the
+/// map can be shared between threads.
+///
+/// Readers never hold a lock while they search: they take a snapshot of the
+/// module list, which is an `Arc` clone. Writers build a new list and swap it
+/// in, so they only wait for other writers and for these clones.
+pub struct ModuleMap<D: AsRef<[u8]> = Arc<[u8]>> {
+    /// The current modules, sorted by address and without overlaps.
+    modules: RwLock<Arc<Vec<ModuleEntry<D>>>>,
+
+    /// Serializes the writers, so that none of them loses another's change.
+    write_lock: Mutex<()>,
+
+    next_id: AtomicU64,
+}
+
+impl<D: AsRef<[u8]>> Default for ModuleMap<D> {
+    fn default() -> Self {
+        Self {
+            modules: RwLock::new(Arc::new(Vec::new())),
+            write_lock: Mutex::new(()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<D: AsRef<[u8]>> ModuleMap<D> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a module and returns its id.
+    ///
+    /// Modules whose address ranges overlap the new module's are removed:
+    /// two images can't be mapped at the same addresses, so they must have
+    /// been unloaded without a call to [`ModuleMap::remove`].
+    pub fn add(&self, module: Module<D>) -> ModuleId {
+        let id = ModuleId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let entry = ModuleEntry {
+            id,
+            address_range: module.address_range(),
+            module: Arc::new(module),
+        };
+        self.update(|modules| {
+            let range = &entry.address_range;
+            modules.retain(|other| {
+                other.address_range.end <= range.start || other.address_range.start >= range.end
+            });
+            let index = modules.partition_point(|other| other.address_range.start < range.start);
+            modules.insert(index, entry);
+        });
+        id
+    }
+
+    /// Removes the module with the given id, and returns it if it was still
+    /// in the map.
+    pub fn remove(&self, id: ModuleId) -> Option<Arc<Module<D>>> {
+        let mut removed = None;
+        self.update(|modules| {
+            if let Some(index) = modules.iter().position(|entry| entry.id == id) {
+                removed = Some(modules.remove(index).module);
+            }
+        });
+        removed
+    }
+
+    /// Returns the module with the given id.
+    pub fn get(&self, id: ModuleId) -> Option<Arc<Module<D>>> {
+        self.snapshot()
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| entry.module.clone())
+    }
+
+    /// Returns the module whose `__TEXT` segment contains `address`.
+    pub fn module_at(&self, address: u64) -> Option<(ModuleId, Arc<Module<D>>)> {
+        let modules = self.snapshot();
+        let index = modules.partition_point(|entry| entry.address_range.start <= address);
+        let entry = modules[..index].last()?;
+        if !entry.address_range.contains(&address) {
+            return None;
+        }
+        Some((entry.id, entry.module.clone()))
+    }
+
+    /// Looks up the module whose `__TEXT` segment contains `absolute_pc`,
+    /// and the function entry covering it. Returns `Ok(None)` if no module
+    /// contains the address.
+    pub fn lookup(&self, absolute_pc: u64) -> Result<Option<ModuleLookup<D>>, Error> {
+        let Some((id, module)) = self.module_at(absolute_pc) else {
+            return Ok(None);
+        };
+        let relative_pc = absolute_pc - module.base_address();
+        let function = match u32::try_from(relative_pc) {
+            Ok(relative_pc) => module.unwind_info.lookup(relative_pc)?,
+            Err(_) => None,
+        };
+        Ok(Some(ModuleLookup {
+            id,
+            module,
+            function,
+        }))
+    }
+
+    /// The number of modules.
+    pub fn len(&self) -> usize {
+        self.snapshot().len()
+    }
+
+    /// Returns true if the map has no modules.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn snapshot(&self) -> Arc<Vec<ModuleEntry<D>>> {
+        // The lock only guards the pointer, so a panicking thread can't have
+        // left the list in an inconsistent state.
+        let modules = self.modules.read().unwrap_or_else(|err| err.into_inner());
+        Arc::clone(&modules)
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Vec<ModuleEntry<D>>)) {
+        let _write_guard = self
+            .write_lock
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let mut modules = Vec::clone(&self.snapshot());
+        f(&mut modules);
+        *self.modules.write().unwrap_or_else(|err| err.into_inner()) = Arc::new(modules);
+    }
+}
+
+impl<D: AsRef<[u8]>> FunctionLookup for ModuleMap<D> {
+    fn lookup_function(&self, address: u64) -> Result<Option<(u64, Function)>, Error> {
+        Ok(self.lookup(address)?.and_then(|lookup| {
+            let base_address = lookup.module.base_address();
+            lookup.function.map(|function| (base_address, function))
+        }))
+    }
+}
@@ -0,0 +1,320 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::raw::consts::{PAGE_KIND_COMPRESSED, PAGE_KIND_REGULAR};
+use crate::raw::{CompactUnwindInfoHeader, CompressedFunctionEntry, LsdaEntry, PageEntry, U32};
+use crate::reader::{ArrayRef, Reader};
+use crate::{
+    compressed_entry_address, resolve_opcode_and_source, Error, PageFunctions, ReadError,
+    UnwindInfo,
+};
+
+/// The contents of an `__unwind_info` section in the layout of
+/// `llvm-objdump --unwind-info`, as returned by
+/// [`UnwindInfo::objdump_listing`].
+///
+/// The [`Display`](fmt::Display) implementation prints everything from the
+/// `Contents of __unwind_info section:` line onwards, with llvm-objdump's
+/// field names and number formatting, so that the two outputs can be diffed.
+#[derive(Clone, Debug)]
+pub struct ObjdumpListing {
+    header: CompactUnwindInfoHeader,
+    common_encodings: Vec<u32>,
+    personalities: Vec<u32>,
+    indices: Vec<PageEntry>,
+    lsdas: Vec<LsdaEntry>,
+    pages: Vec<ObjdumpPage>,
+}
+
+#[derive(Clone, Debug)]
+struct ObjdumpPage {
+    page_entry: PageEntry,
+    contents: ObjdumpPageContents,
+}
+
+#[derive(Clone, Debug)]
+enum ObjdumpPageContents {
+    /// The function offsets and encodings.
+    Regular(Vec<(u32, u32)>),
+    Compressed {
+        page_encodings: Vec<u32>,
+        /// The function offsets, encoding indexes and encodings.
+        entries: Vec<(u32, u8, u32)>,
+    },
+    UnknownKind(u32),
+}
+
+impl<R: Reader + ?Sized> UnwindInfo<'_, R> {
+    /// Reads the whole section for printing it in the layout of
+    /// `llvm-objdump --unwind-info`.
+    ///
+    /// Like llvm-objdump, this reads the LSDA index array from the first page
+    /// entry's LSDA offset up to the sentinel page entry's, and stops listing
+    /// second-level pages at the first page entry with a zero page offset.
+    pub fn objdump_listing(&self) -> Result<ObjdumpListing, Error> {
+        let header = *self
+            .data
+            .read_at::<CompactUnwindInfoHeader>(0)
+            .ok_or(Error::read(ReadError::Header, 0))?;
+        let mut listing = ObjdumpListing {
+            header,
+            common_encodings: Vec::new(),
+            personalities: Vec::new(),
+            indices: Vec::new(),
+            lsdas: Vec::new(),
+            pages: Vec::new(),
+        };
+        if header.version() != 1 {
+            return Ok(listing);
+        }
+
+        for i in 0..self.global_opcodes.len() {
+            let opcode = self.global_opcodes.get(self.data, i)?;
+            listing.common_encodings.push(opcode.opcode());
+        }
+        let personalities = ArrayRef::<U32>::new(
+            self.data,
+            header.personalities_offset().into(),
+            header.personalities_len() as usize,
+            ReadError::Personalities,
+        )?;
+        for i in 0..personalities.len() {
+            listing
+                .personalities
+                .push(personalities.get(self.data, i)?.into());
+        }
+        for i in 0..self.pages.len() {
+            listing.indices.push(self.pages.get(self.data, i)?);
+        }
+
+        if let (Some(first), Some(last)) = (listing.indices.first(), listing.indices.last()) {
+            let lsdas_size = last
+                .lsda_index_offset()
+                .saturating_sub(first.lsda_index_offset());
+            let lsdas = ArrayRef::<LsdaEntry>::new(
+                self.data,
+                first.lsda_index_offset().into(),
+                lsdas_size as usize / core::mem::size_of::<LsdaEntry>(),
+                ReadError::Lsdas,
+            )?;
+            for i in 0..lsdas.len() {
+                listing.lsdas.push(lsdas.get(self.data, i)?);
+            }
+        }
+
+        // The sentinel page entry has no second-level page.
+        for (page_index, page_entry) in listing.indices.iter().rev().skip(1).rev().enumerate() {
+            if page_entry.page_offset() == 0 {
+                break;
+            }
+            let kind: u32 = (*self
+                .data
+                .read_at::<U32>(page_entry.page_offset().into())
+                .ok_or(Error::read(
+                    ReadError::PageKind,
+                    page_entry.page_offset().into(),
+                ))?)
+            .into();
+            let contents = match kind {
+                PAGE_KIND_REGULAR | PAGE_KIND_COMPRESSED => self
+                    .objdump_page_contents(page_entry)
+                    .map_err(|err| err.with_page_index(page_index))?,
+                _ => ObjdumpPageContents::UnknownKind(kind),
+            };
+            listing.pages.push(ObjdumpPage {
+                page_entry: *page_entry,
+                contents,
+            });
+        }
+        Ok(listing)
+    }
+
+    fn objdump_page_contents(&self, page_entry: &PageEntry) -> Result<ObjdumpPageContents, Error> {
+        let data = self.data;
+        match PageFunctions::parse(data, page_entry)? {
+            PageFunctions::Regular { functions, .. } => {
+                let mut entries = Vec::with_capacity(functions.len());
+                for i in 0..functions.len() {
+                    let entry = functions
+                        .get(data, i)
+                        .map_err(|err| err.with_entry_index(i))?;
+                    entries.push((entry.address(), entry.opcode()));
+                }
+                Ok(ObjdumpPageContents::Regular(entries))
+            }
+            PageFunctions::Compressed {
+                functions,
+                local_opcodes,
+                ..
+            } => {
+                let mut page_encodings = Vec::with_capacity(local_opcodes.len());
+                for i in 0..local_opcodes.len() {
+                    page_encodings.push(local_opcodes.get(data, i)?.opcode());
+                }
+                let mut entries = Vec::with_capacity(functions.len());
+                for i in 0..functions.len() {
+                    let entry = functions
+                        .get(data, i)
+                        .map_err(|err| err.with_entry_index(i))?;
+                    let entry = CompressedFunctionEntry::new(entry.into());
+                    let (encoding, _) = resolve_opcode_and_source(
+                        data,
+                        &self.global_opcodes,
+                        &local_opcodes,
+                        entry,
+                    )
+                    .map_err(|err| err.with_entry_index(i))?;
+                    let address = compressed_entry_address(page_entry.first_address(), entry)
+                        .map_err(|err| err.with_entry_index(i))?;
+                    entries.push((address, entry.opcode_index(), encoding));
+                }
+                Ok(ObjdumpPageContents::Compressed {
+                    page_encodings,
+                    entries,
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for ObjdumpListing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = &self.header;
+        writeln!(f, "Contents of __unwind_info section:")?;
+        writeln!(
+            f,
+            "  Version:                                   0x{:x}",
+            header.version()
+        )?;
+        if header.version() != 1 {
+            return writeln!(f, "    Skipping section with unknown version");
+        }
+        writeln!(
+            f,
+            "  Common encodings array section offset:     0x{:x}",
+            header.global_opcodes_offset()
+        )?;
+        writeln!(
+            f,
+            "  Number of common encodings in array:       0x{:x}",
+            header.global_opcodes_len()
+        )?;
+        writeln!(
+            f,
+            "  Personality function array section offset: 0x{:x}",
+            header.personalities_offset()
+        )?;
+        writeln!(
+            f,
+            "  Number of personality functions in array:  0x{:x}",
+            header.personalities_len()
+        )?;
+        writeln!(
+            f,
+            "  Index array section offset:                0x{:x}",
+            header.pages_offset()
+        )?;
+        writeln!(
+            f,
+            "  Number of indices in array:                0x{:x}",
+            header.pages_len()
+        )?;
+
+        writeln!(
+            f,
+            "  Common encodings: (count = {})",
+            self.common_encodings.len()
+        )?;
+        for (i, encoding) in self.common_encodings.iter().enumerate() {
+            writeln!(f, "    encoding[{}]: 0x{:08x}", i, encoding)?;
+        }
+
+        writeln!(
+            f,
+            "  Personality functions: (count = {})",
+            self.personalities.len()
+        )?;
+        for (i, personality) in self.personalities.iter().enumerate() {
+            // Personality indexes in opcodes are one-based.
+            writeln!(f, "    personality[{}]: 0x{:08x}", i + 1, personality)?;
+        }
+
+        writeln!(f, "  Top level indices: (count = {})", self.indices.len())?;
+        for (i, index) in self.indices.iter().enumerate() {
+            writeln!(
+                f,
+                "    [{}]: function offset=0x{:08x}, 2nd level page offset=0x{:08x}, LSDA offset=0x{:08x}",
+                i,
+                index.first_address(),
+                index.page_offset(),
+                index.lsda_index_offset()
+            )?;
+        }
+
+        writeln!(f, "  LSDA descriptors:")?;
+        for (i, lsda) in self.lsdas.iter().enumerate() {
+            writeln!(
+                f,
+                "    [{}]: function offset=0x{:08x}, LSDA offset=0x{:08x}",
+                i,
+                lsda.function_offset(),
+                lsda.lsda_offset()
+            )?;
+        }
+
+        writeln!(f, "  Second level indices:")?;
+        for (i, page) in self.pages.iter().enumerate() {
+            writeln!(
+                f,
+                "    Second level index[{}]: offset in section=0x{:08x}, base function offset=0x{:08x}",
+                i,
+                page.page_entry.page_offset(),
+                page.page_entry.first_address()
+            )?;
+            match &page.contents {
+                ObjdumpPageContents::Regular(entries) => {
+                    for (i, (function_offset, encoding)) in entries.iter().enumerate() {
+                        writeln!(
+                            f,
+                            "      [{}]: function offset=0x{:08x}, encoding=0x{:08x}",
+                            i, function_offset, encoding
+                        )?;
+                    }
+                }
+                ObjdumpPageContents::Compressed {
+                    page_encodings,
+                    entries,
+                } => {
+                    if !page_encodings.is_empty() {
+                        writeln!(
+                            f,
+                            "      Page encodings: (count = {})",
+                            page_encodings.len()
+                        )?;
+                        for (i, encoding) in page_encodings.iter().enumerate() {
+                            writeln!(
+                                f,
+                                "        encoding[{}]: 0x{:08x}",
+                                i + self.common_encodings.len(),
+                                encoding
+                            )?;
+                        }
+                    }
+                    for (i, (function_offset, encoding_index, encoding)) in
+                        entries.iter().enumerate()
+                    {
+                        writeln!(
+                            f,
+                            "      [{}]: function offset=0x{:08x}, encoding[{}]=0x{:08x}",
+                            i, function_offset, encoding_index, encoding
+                        )?;
+                    }
+                }
+                ObjdumpPageContents::UnknownKind(kind) => {
+                    writeln!(f, "    Skipping 2nd level page with unknown kind {}", kind)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
-use std::fmt::Display;
+use core::fmt::Display;
 
 use super::bitfield::OpcodeBitfield;
+use super::register::{FormatOpcode, OpcodeWithNames, RegisterNames};
 use crate::raw::consts::*;
+use crate::Arch;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum OpcodeArm64 {
@@ -30,6 +32,106 @@ pub enum OpcodeArm64 {
     UnrecognizedKind(u8),
 }
 
+/// A pair of callee-saved registers which a frame-based arm64 function can
+/// save below its frame record.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RegisterPairArm64 {
+    X19X20,
+    X21X22,
+    X23X24,
+    X25X26,
+    X27X28,
+    D8D9,
+    D10D11,
+    D12D13,
+    D14D15,
+}
+
+impl RegisterPairArm64 {
+    /// All pairs, in the order in which they are laid out below the frame
+    /// record. This is also the order of their bits in the opcode.
+    pub const ALL: [RegisterPairArm64; 9] = [
+        RegisterPairArm64::X19X20,
+        RegisterPairArm64::X21X22,
+        RegisterPairArm64::X23X24,
+        RegisterPairArm64::X25X26,
+        RegisterPairArm64::X27X28,
+        RegisterPairArm64::D8D9,
+        RegisterPairArm64::D10D11,
+        RegisterPairArm64::D12D13,
+        RegisterPairArm64::D14D15,
+    ];
+
+    /// The DWARF numbers of the two registers. The d registers are numbered
+    /// from 64, so d8 is 72.
+    pub fn dwarf_numbers(self) -> (u16, u16) {
+        let first = match self {
+            RegisterPairArm64::X19X20 => 19,
+            RegisterPairArm64::X21X22 => 21,
+            RegisterPairArm64::X23X24 => 23,
+            RegisterPairArm64::X25X26 => 25,
+            RegisterPairArm64::X27X28 => 27,
+            RegisterPairArm64::D8D9 => 72,
+            RegisterPairArm64::D10D11 => 74,
+            RegisterPairArm64::D12D13 => 76,
+            RegisterPairArm64::D14D15 => 78,
+        };
+        (first, first + 1)
+    }
+
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+/// A register pair saved by a frame-based arm64 function, as returned by
+/// [`OpcodeArm64::saved_register_pairs`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SavedRegisterPairArm64 {
+    pub pair: RegisterPairArm64,
+
+    /// The CFA-relative offset at which the pair's first register is saved,
+    /// for example -24 for x19 if x19 and x20 are saved.
+    pub first_offset: i64,
+
+    /// The CFA-relative offset of the pair's second register, 8 bytes below
+    /// the first.
+    pub second_offset: i64,
+}
+
+/// An iterator over the register pairs saved by a frame-based arm64
+/// function, as returned by [`OpcodeArm64::saved_register_pairs`].
+#[derive(Clone, Debug)]
+pub struct SavedRegisterPairsArm64 {
+    remaining: u16,
+    next_first_offset: i64,
+}
+
+impl Iterator for SavedRegisterPairsArm64 {
+    type Item = SavedRegisterPairArm64;
+
+    fn next(&mut self) -> Option<SavedRegisterPairArm64> {
+        let pair = *RegisterPairArm64::ALL
+            .iter()
+            .find(|pair| self.remaining & pair.bit() != 0)?;
+        self.remaining &= !pair.bit();
+        let first_offset = self.next_first_offset;
+        self.next_first_offset -= 16;
+        Some(SavedRegisterPairArm64 {
+            pair,
+            first_offset,
+            second_offset: first_offset - 8,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.remaining.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for SavedRegisterPairsArm64 {}
+
 impl OpcodeArm64 {
     pub fn parse(opcode: u32) -> Self {
         match OpcodeBitfield::new(opcode).kind() {
@@ -58,10 +160,103 @@ impl OpcodeArm64 {
             kind => OpcodeArm64::UnrecognizedKind(kind),
         }
     }
+
+    /// Creates a `FrameBased` opcode which saves the given register pairs.
+    ///
+    /// The pairs must be in the order of [`RegisterPairArm64::ALL`], without
+    /// duplicates, because the opcode can't describe any other layout.
+    /// Returns `None` otherwise.
+    pub fn frame_based(pairs: &[RegisterPairArm64]) -> Option<Self> {
+        if pairs.windows(2).any(|w| w[0] >= w[1]) {
+            return None;
+        }
+        let bits = pairs.iter().fold(0, |bits, pair| bits | pair.bit());
+        let saved = |pair: RegisterPairArm64| bits & pair.bit() != 0;
+        Some(OpcodeArm64::FrameBased {
+            saved_reg_pair_count: pairs.len() as u8,
+            d14_and_d15_saved: saved(RegisterPairArm64::D14D15),
+            d12_and_d13_saved: saved(RegisterPairArm64::D12D13),
+            d10_and_d11_saved: saved(RegisterPairArm64::D10D11),
+            d8_and_d9_saved: saved(RegisterPairArm64::D8D9),
+            x27_and_x28_saved: saved(RegisterPairArm64::X27X28),
+            x25_and_x26_saved: saved(RegisterPairArm64::X25X26),
+            x23_and_x24_saved: saved(RegisterPairArm64::X23X24),
+            x21_and_x22_saved: saved(RegisterPairArm64::X21X22),
+            x19_and_x20_saved: saved(RegisterPairArm64::X19X20),
+        })
+    }
+
+    /// Returns the register pairs saved by a `FrameBased` opcode, with the
+    /// offsets at which they are saved, or an empty iterator for other
+    /// opcodes.
+    ///
+    /// The pairs are stored below the frame record at CFA-16, starting with
+    /// the x19/x20 pair (if saved) right below it and ending with the d14/d15
+    /// pair at the lowest address. This is the order in which libunwind
+    /// restores them.
+    pub fn saved_register_pairs(&self) -> SavedRegisterPairsArm64 {
+        let remaining = match *self {
+            OpcodeArm64::FrameBased {
+                d14_and_d15_saved,
+                d12_and_d13_saved,
+                d10_and_d11_saved,
+                d8_and_d9_saved,
+                x27_and_x28_saved,
+                x25_and_x26_saved,
+                x23_and_x24_saved,
+                x21_and_x22_saved,
+                x19_and_x20_saved,
+                ..
+            } => [
+                x19_and_x20_saved,
+                x21_and_x22_saved,
+                x23_and_x24_saved,
+                x25_and_x26_saved,
+                x27_and_x28_saved,
+                d8_and_d9_saved,
+                d10_and_d11_saved,
+                d12_and_d13_saved,
+                d14_and_d15_saved,
+            ]
+            .into_iter()
+            .zip(RegisterPairArm64::ALL)
+            .filter(|(saved, _)| *saved)
+            .fold(0, |bits, (_, pair)| bits | pair.bit()),
+            _ => 0,
+        };
+        SavedRegisterPairsArm64 {
+            remaining,
+            next_first_offset: -24,
+        }
+    }
+
+    /// Returns an object which formats the opcode with the chosen register
+    /// names. The `Display` implementation uses [`RegisterNames::Dwarf`].
+    pub fn display(&self, names: RegisterNames) -> impl Display + '_ {
+        OpcodeWithNames {
+            opcode: self,
+            names,
+        }
+    }
 }
 
 impl Display for OpcodeArm64 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.fmt_with(f, RegisterNames::Dwarf)
+    }
+}
+
+impl FormatOpcode for OpcodeArm64 {
+    fn fmt_with(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+        names: RegisterNames,
+    ) -> core::fmt::Result {
+        let arch = Arch::Arm64;
+        let register = |number| arch.display_register(number, names);
+        let fp = register(arch.frame_pointer_register());
+        let sp = register(arch.stack_pointer_register());
+        let lr = register(arch.return_address_register());
         match self {
             OpcodeArm64::Null => {
                 write!(f, "(uncovered)")?;
@@ -70,46 +265,27 @@ impl Display for OpcodeArm64 {
                 stack_size_in_bytes,
             } => {
                 if *stack_size_in_bytes == 0 {
-                    write!(f, "CFA=reg31")?;
+                    write!(f, "CFA={sp}")?;
                 } else {
-                    write!(f, "CFA=reg31+{}", stack_size_in_bytes)?;
+                    write!(f, "CFA={sp}+{}", stack_size_in_bytes)?;
                 }
             }
             OpcodeArm64::Dwarf { eh_frame_fde } => {
                 write!(f, "(check eh_frame FDE 0x{:x})", eh_frame_fde)?;
             }
-            OpcodeArm64::FrameBased {
-                d14_and_d15_saved,
-                d12_and_d13_saved,
-                d10_and_d11_saved,
-                d8_and_d9_saved,
-                x27_and_x28_saved,
-                x25_and_x26_saved,
-                x23_and_x24_saved,
-                x21_and_x22_saved,
-                x19_and_x20_saved,
-                ..
-            } => {
-                write!(f, "CFA=reg29+16: reg29=[CFA-16], reg30=[CFA-8]")?;
-                let mut offset = 32;
-                let mut next_pair = |pair_saved, a, b| {
-                    if pair_saved {
-                        let r = write!(f, ", {}=[CFA-{}], {}=[CFA-{}]", a, offset, b, offset + 8);
-                        offset += 16;
-                        r
-                    } else {
-                        Ok(())
-                    }
-                };
-                next_pair(*d14_and_d15_saved, "reg14", "reg15")?;
-                next_pair(*d12_and_d13_saved, "reg12", "reg13")?;
-                next_pair(*d10_and_d11_saved, "reg10", "reg11")?;
-                next_pair(*d8_and_d9_saved, "reg8", "reg9")?;
-                next_pair(*x27_and_x28_saved, "reg27", "reg28")?;
-                next_pair(*x25_and_x26_saved, "reg25", "reg26")?;
-                next_pair(*x23_and_x24_saved, "reg23", "reg24")?;
-                next_pair(*x21_and_x22_saved, "reg21", "reg22")?;
-                next_pair(*x19_and_x20_saved, "reg19", "reg20")?;
+            OpcodeArm64::FrameBased { .. } => {
+                write!(f, "CFA={fp}+16: {fp}=[CFA-16], {lr}=[CFA-8]")?;
+                for saved in self.saved_register_pairs() {
+                    let (first, second) = saved.pair.dwarf_numbers();
+                    write!(
+                        f,
+                        ", {}=[CFA{}], {}=[CFA{}]",
+                        register(first),
+                        saved.first_offset,
+                        register(second),
+                        saved.second_offset
+                    )?;
+                }
             }
             OpcodeArm64::UnrecognizedKind(kind) => {
                 write!(f, "!! Unrecognized kind {}", kind)?;
@@ -118,3 +294,64 @@ impl Display for OpcodeArm64 {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_saved_register_pairs() {
+        use RegisterPairArm64::*;
+        let opcode = OpcodeArm64::parse(0x0400_0103);
+        let mut pairs = opcode.saved_register_pairs();
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(
+            pairs.next(),
+            Some(SavedRegisterPairArm64 {
+                pair: X19X20,
+                first_offset: -24,
+                second_offset: -32,
+            })
+        );
+        assert_eq!(
+            pairs.next(),
+            Some(SavedRegisterPairArm64 {
+                pair: X21X22,
+                first_offset: -40,
+                second_offset: -48,
+            })
+        );
+        assert_eq!(
+            pairs.next(),
+            Some(SavedRegisterPairArm64 {
+                pair: D14D15,
+                first_offset: -56,
+                second_offset: -64,
+            })
+        );
+        assert_eq!(pairs.next(), None);
+        assert_eq!(D14D15.dwarf_numbers(), (78, 79));
+
+        for bits in 0..0x200 {
+            let opcode = OpcodeArm64::parse(0x0400_0000 | bits);
+            let mut pairs = [X19X20; 9];
+            let mut len = 0;
+            for saved in opcode.saved_register_pairs() {
+                pairs[len] = saved.pair;
+                len += 1;
+            }
+            assert_eq!(OpcodeArm64::frame_based(&pairs[..len]), Some(opcode));
+        }
+
+        assert_eq!(OpcodeArm64::frame_based(&[D14D15, X19X20]), None);
+        assert_eq!(OpcodeArm64::frame_based(&[X19X20, X19X20]), None);
+        assert_eq!(
+            OpcodeArm64::Frameless {
+                stack_size_in_bytes: 16
+            }
+            .saved_register_pairs()
+            .len(),
+            0
+        );
+    }
+}
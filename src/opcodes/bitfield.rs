@@ -1,5 +1,5 @@
 use crate::num_display::BinNum;
-use std::fmt::Debug;
+use core::fmt::Debug;
 
 pub struct OpcodeBitfield(pub u32);
 
@@ -8,8 +8,17 @@ impl OpcodeBitfield {
         Self(value)
     }
 
-    /// Whether this instruction is the start of a function.
+    /// Whether this instruction is the start of a function. This is the
+    /// inverse of [`is_not_function_start`](Self::is_not_function_start).
     pub fn is_function_start(&self) -> bool {
+        !self.is_not_function_start()
+    }
+
+    /// Whether the `UNWIND_IS_NOT_FUNCTION_START` bit is set, i.e. whether this
+    /// entry continues a function which started at an earlier address, for
+    /// example the cold part of a function which was split into hot and cold
+    /// parts.
+    pub fn is_not_function_start(&self) -> bool {
         self.0 >> 31 == 1
     }
 
@@ -43,7 +52,7 @@ impl From<u32> for OpcodeBitfield {
 }
 
 impl Debug for OpcodeBitfield {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Opcode")
             .field("kind", &self.kind())
             .field("is_function_start", &self.is_function_start())
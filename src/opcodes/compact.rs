@@ -0,0 +1,347 @@
+use core::fmt::{Debug, Display};
+use core::ops::Deref;
+
+use super::{OpcodeArm64, OpcodeX86, OpcodeX86_64};
+use crate::reader::Reader;
+use crate::{Arch, Error, Function, UnwindInfo};
+
+/// The concepts which the opcode types of all architectures share, for code
+/// which is generic over the architecture.
+///
+/// Register numbers and offsets are the ones of the CFI in `__eh_frame`: DWARF
+/// register numbers, and offsets from the CFA, the stack pointer value before
+/// the call instruction.
+pub trait CompactOpcode: Clone + Debug + Display {
+    /// Parses a raw opcode, such as [`Function::opcode`].
+    fn parse(opcode: u32) -> Self;
+
+    /// Whether the opcode is `Null`, i.e. the function has no unwind info.
+    fn is_null(&self) -> bool;
+
+    /// Whether the function's unwind info is in `__eh_frame`.
+    fn is_dwarf(&self) -> bool {
+        self.eh_frame_fde().is_some()
+    }
+
+    /// The offset of the function's FDE in `__eh_frame`, for `Dwarf` opcodes.
+    fn eh_frame_fde(&self) -> Option<u32>;
+
+    /// Whether the function sets up a frame pointer, so that the CFA is at a
+    /// fixed offset from it.
+    fn is_frame_based(&self) -> bool;
+
+    /// Where the caller's return address is, or `None` if the opcode doesn't
+    /// describe the frame: `Null`, `Dwarf` and invalid opcodes.
+    fn return_address_location(&self) -> Option<ReturnAddressLocation>;
+
+    /// The registers which the function saved on the stack, sorted by
+    /// register number. For frame-based functions, this includes the frame
+    /// pointer. The return address is not included.
+    fn saved_registers(&self) -> SavedRegisters;
+}
+
+/// The location of the return address, as returned by
+/// [`CompactOpcode::return_address_location`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReturnAddressLocation {
+    /// On the stack, at the CFA plus the offset.
+    AtCfaOffset(i64),
+
+    /// Still in the register with this DWARF number, such as `lr` in arm64
+    /// frameless functions.
+    InRegister(u16),
+}
+
+/// A register which was saved on the stack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SavedRegister {
+    pub dwarf_number: u16,
+
+    /// The location of the saved value, relative to the CFA.
+    pub cfa_offset: i64,
+}
+
+/// The registers returned by [`CompactOpcode::saved_registers`]. Derefs to a
+/// slice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SavedRegisters {
+    registers: [SavedRegister; Self::CAPACITY],
+    len: usize,
+}
+
+impl SavedRegisters {
+    /// The arm64 frame pointer and the 18 registers of the saved pairs.
+    const CAPACITY: usize = 19;
+
+    fn new() -> Self {
+        Self {
+            registers: [SavedRegister {
+                dwarf_number: 0,
+                cfa_offset: 0,
+            }; Self::CAPACITY],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, dwarf_number: u16, cfa_offset: i64) {
+        self.registers[self.len] = SavedRegister {
+            dwarf_number,
+            cfa_offset,
+        };
+        self.len += 1;
+    }
+
+    fn sorted(mut self) -> Self {
+        self.registers[..self.len].sort_unstable_by_key(|register| register.dwarf_number);
+        self
+    }
+}
+
+impl Deref for SavedRegisters {
+    type Target = [SavedRegister];
+
+    fn deref(&self) -> &[SavedRegister] {
+        &self.registers[..self.len]
+    }
+}
+
+/// A marker type for an architecture, which selects its opcode type, as in
+/// [`UnwindInfo::lookup_decoded`].
+pub trait CompactArch {
+    type Opcode: CompactOpcode;
+
+    const ARCH: Arch;
+}
+
+/// The marker type for [`Arch::X86`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct X86;
+
+/// The marker type for [`Arch::X86_64`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct X86_64;
+
+/// The marker type for [`Arch::Arm64`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Arm64;
+
+impl CompactArch for X86 {
+    type Opcode = OpcodeX86;
+    const ARCH: Arch = Arch::X86;
+}
+
+impl CompactArch for X86_64 {
+    type Opcode = OpcodeX86_64;
+    const ARCH: Arch = Arch::X86_64;
+}
+
+impl CompactArch for Arm64 {
+    type Opcode = OpcodeArm64;
+    const ARCH: Arch = Arch::Arm64;
+}
+
+impl<R: Reader + ?Sized> UnwindInfo<'_, R> {
+    /// Like [`UnwindInfo::lookup`], but also parses the function's opcode
+    /// for the architecture `A`, for example with
+    /// `lookup_decoded::<Arm64>(pc)`.
+    pub fn lookup_decoded<A: CompactArch>(
+        &self,
+        pc: u32,
+    ) -> Result<Option<(Function, A::Opcode)>, Error> {
+        Ok(self.lookup(pc)?.map(|function| {
+            let opcode = A::Opcode::parse(function.opcode);
+            (function, opcode)
+        }))
+    }
+}
+
+/// The saved registers of x86 and x86_64 frame-based functions. `saved_regs`
+/// are the DWARF numbers from the opcode, in push order.
+fn x86_frame_based(
+    arch: Arch,
+    word_size: i64,
+    stack_offset_in_bytes: u16,
+    saved_regs: impl DoubleEndedIterator<Item = Option<u16>>,
+) -> SavedRegisters {
+    let mut registers = SavedRegisters::new();
+    registers.push(arch.frame_pointer_register(), -2 * word_size);
+    // Only the registers which fit into the stack offset are saved.
+    let max_count = (i64::from(stack_offset_in_bytes) / word_size) as usize;
+    let mut offset = -i64::from(stack_offset_in_bytes) - 2 * word_size;
+    for register in saved_regs.rev().take(max_count) {
+        if let Some(register) = register {
+            registers.push(register, offset);
+        }
+        offset += word_size;
+    }
+    registers.sorted()
+}
+
+/// The saved registers of x86 and x86_64 frameless functions, which are
+/// pushed right below the return address.
+fn x86_frameless(
+    word_size: i64,
+    saved_regs: impl DoubleEndedIterator<Item = Option<u16>>,
+) -> SavedRegisters {
+    let mut registers = SavedRegisters::new();
+    let mut offset = -2 * word_size;
+    for register in saved_regs.rev().flatten() {
+        registers.push(register, offset);
+        offset -= word_size;
+    }
+    registers.sorted()
+}
+
+impl CompactOpcode for OpcodeX86_64 {
+    fn parse(opcode: u32) -> Self {
+        OpcodeX86_64::parse(opcode)
+    }
+
+    fn is_null(&self) -> bool {
+        matches!(self, OpcodeX86_64::Null)
+    }
+
+    fn eh_frame_fde(&self) -> Option<u32> {
+        match self {
+            OpcodeX86_64::Dwarf { eh_frame_fde } => Some(*eh_frame_fde),
+            _ => None,
+        }
+    }
+
+    fn is_frame_based(&self) -> bool {
+        matches!(self, OpcodeX86_64::FrameBased { .. })
+    }
+
+    fn return_address_location(&self) -> Option<ReturnAddressLocation> {
+        match self {
+            OpcodeX86_64::FrameBased { .. }
+            | OpcodeX86_64::FramelessImmediate { .. }
+            | OpcodeX86_64::FramelessIndirect { .. } => {
+                Some(ReturnAddressLocation::AtCfaOffset(-8))
+            }
+            _ => None,
+        }
+    }
+
+    fn saved_registers(&self) -> SavedRegisters {
+        match self {
+            OpcodeX86_64::FrameBased {
+                stack_offset_in_bytes,
+                saved_regs,
+            } => x86_frame_based(
+                Arch::X86_64,
+                8,
+                *stack_offset_in_bytes,
+                saved_regs
+                    .iter()
+                    .map(|reg| reg.map(|reg| reg.dwarf_number())),
+            ),
+            OpcodeX86_64::FramelessImmediate { saved_regs, .. }
+            | OpcodeX86_64::FramelessIndirect { saved_regs, .. } => x86_frameless(
+                8,
+                saved_regs
+                    .iter()
+                    .map(|reg| reg.map(|reg| reg.dwarf_number())),
+            ),
+            _ => SavedRegisters::new(),
+        }
+    }
+}
+
+impl CompactOpcode for OpcodeX86 {
+    fn parse(opcode: u32) -> Self {
+        OpcodeX86::parse(opcode)
+    }
+
+    fn is_null(&self) -> bool {
+        matches!(self, OpcodeX86::Null)
+    }
+
+    fn eh_frame_fde(&self) -> Option<u32> {
+        match self {
+            OpcodeX86::Dwarf { eh_frame_fde } => Some(*eh_frame_fde),
+            _ => None,
+        }
+    }
+
+    fn is_frame_based(&self) -> bool {
+        matches!(self, OpcodeX86::FrameBased { .. })
+    }
+
+    fn return_address_location(&self) -> Option<ReturnAddressLocation> {
+        match self {
+            OpcodeX86::FrameBased { .. }
+            | OpcodeX86::FramelessImmediate { .. }
+            | OpcodeX86::FramelessIndirect { .. } => Some(ReturnAddressLocation::AtCfaOffset(-4)),
+            _ => None,
+        }
+    }
+
+    fn saved_registers(&self) -> SavedRegisters {
+        match self {
+            OpcodeX86::FrameBased {
+                stack_offset_in_bytes,
+                saved_regs,
+            } => x86_frame_based(
+                Arch::X86,
+                4,
+                *stack_offset_in_bytes,
+                saved_regs
+                    .iter()
+                    .map(|reg| reg.map(|reg| reg.dwarf_number())),
+            ),
+            OpcodeX86::FramelessImmediate { saved_regs, .. }
+            | OpcodeX86::FramelessIndirect { saved_regs, .. } => x86_frameless(
+                4,
+                saved_regs
+                    .iter()
+                    .map(|reg| reg.map(|reg| reg.dwarf_number())),
+            ),
+            _ => SavedRegisters::new(),
+        }
+    }
+}
+
+impl CompactOpcode for OpcodeArm64 {
+    fn parse(opcode: u32) -> Self {
+        OpcodeArm64::parse(opcode)
+    }
+
+    fn is_null(&self) -> bool {
+        matches!(self, OpcodeArm64::Null)
+    }
+
+    fn eh_frame_fde(&self) -> Option<u32> {
+        match self {
+            OpcodeArm64::Dwarf { eh_frame_fde } => Some(*eh_frame_fde),
+            _ => None,
+        }
+    }
+
+    fn is_frame_based(&self) -> bool {
+        matches!(self, OpcodeArm64::FrameBased { .. })
+    }
+
+    fn return_address_location(&self) -> Option<ReturnAddressLocation> {
+        match self {
+            OpcodeArm64::FrameBased { .. } => Some(ReturnAddressLocation::AtCfaOffset(-8)),
+            OpcodeArm64::Frameless { .. } => Some(ReturnAddressLocation::InRegister(
+                Arch::Arm64.return_address_register(),
+            )),
+            _ => None,
+        }
+    }
+
+    fn saved_registers(&self) -> SavedRegisters {
+        let mut registers = SavedRegisters::new();
+        if self.is_frame_based() {
+            registers.push(Arch::Arm64.frame_pointer_register(), -16);
+            for saved in self.saved_register_pairs() {
+                let (first, second) = saved.pair.dwarf_numbers();
+                registers.push(first, saved.first_offset);
+                registers.push(second, saved.second_offset);
+            }
+        }
+        registers.sorted()
+    }
+}
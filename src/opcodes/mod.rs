@@ -1,10 +1,14 @@
 mod arm64;
 mod bitfield;
-mod permutation;
+mod compact;
+pub(crate) mod permutation;
+mod register;
 mod x86;
 mod x86_64;
 
 pub use arm64::*;
 pub use bitfield::*;
+pub use compact::*;
+pub use register::RegisterNames;
 pub use x86::*;
 pub use x86_64::*;
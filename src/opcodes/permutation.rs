@@ -34,7 +34,7 @@
 ///
 /// We encode as (a, b, c) as c + 4 * (b + 5 * a)
 /// [...]
-pub fn decode_permutation_6(count: u32, mut encoding: u32) -> std::result::Result<[u8; 6], ()> {
+pub fn decode_permutation_6(count: u32, mut encoding: u32) -> core::result::Result<[u8; 6], ()> {
     if count > 6 {
         return Err(());
     }
@@ -79,3 +79,32 @@ pub fn decode_permutation_6(count: u32, mut encoding: u32) -> std::result::Resul
     }
     Ok(registers)
 }
+
+/// The inverse of [`decode_permutation_6`]: packs up to 6 distinct register
+/// numbers in 1..=6 into 10 bits.
+#[cfg(feature = "eh-frame")]
+pub fn encode_permutation_6(registers: &[u8]) -> u32 {
+    let mut encoding = 0;
+    for (i, &register) in registers.iter().enumerate() {
+        // The index of the register among the registers which haven't been
+        // used by the earlier digits.
+        let used_below = registers[..i].iter().filter(|r| **r < register).count();
+        let compressed_regindex = u32::from(register) - 1 - used_below as u32;
+        encoding = encoding * (6 - i as u32) + compressed_regindex;
+    }
+    encoding
+}
+
+#[cfg(all(test, feature = "eh-frame"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode_permutation_roundtrip() {
+        for registers in [&[][..], &[6], &[1, 2], &[4, 2, 6], &[6, 5, 4, 3, 2, 1]] {
+            let encoding = encode_permutation_6(registers);
+            let decoded = decode_permutation_6(registers.len() as u32, encoding).unwrap();
+            assert_eq!(&decoded[..registers.len()], registers);
+        }
+    }
+}
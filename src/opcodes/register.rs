@@ -0,0 +1,174 @@
+use core::fmt;
+
+use crate::Arch;
+
+/// How registers are named when formatting opcodes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RegisterNames {
+    /// DWARF register numbers, such as `reg6`, as used in `__eh_frame` CFI.
+    #[default]
+    Dwarf,
+
+    /// Assembly names, such as `rbp`, `x19` or `d8`. Registers without a
+    /// known name fall back to their DWARF number.
+    Assembly,
+}
+
+/// x86_64 register names, indexed by DWARF register number. Number 16 is the
+/// return address column.
+const X86_64_REGISTERS: [&str; 33] = [
+    "rax", "rdx", "rcx", "rbx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12", "r13",
+    "r14", "r15", "rip", "xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7", "xmm8",
+    "xmm9", "xmm10", "xmm11", "xmm12", "xmm13", "xmm14", "xmm15",
+];
+
+/// i386 register names, indexed by the register numbers of Darwin's
+/// `__eh_frame`. These have ebp (4) and esp (5) swapped compared to the SysV
+/// numbering. Number 8 is the return address column.
+const X86_REGISTERS: [&str; 9] = [
+    "eax", "ecx", "edx", "ebx", "ebp", "esp", "esi", "edi", "eip",
+];
+
+/// arm64 general-purpose register names, indexed by DWARF register number.
+const ARM64_REGISTERS: [&str; 32] = [
+    "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12", "x13", "x14",
+    "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27",
+    "x28", "fp", "lr", "sp",
+];
+
+/// arm64 floating-point register names, indexed by DWARF register number
+/// minus 64. Compact unwind info only saves the low 64 bits of v8-v15.
+const ARM64_FP_REGISTERS: [&str; 32] = [
+    "d0", "d1", "d2", "d3", "d4", "d5", "d6", "d7", "d8", "d9", "d10", "d11", "d12", "d13", "d14",
+    "d15", "d16", "d17", "d18", "d19", "d20", "d21", "d22", "d23", "d24", "d25", "d26", "d27",
+    "d28", "d29", "d30", "d31",
+];
+
+impl Arch {
+    /// Returns the assembly name of the register with DWARF number
+    /// `dwarf_number`, or `None` if the number is unknown.
+    ///
+    /// For x86, this uses the numbering of Darwin's `__eh_frame`.
+    pub fn register_name(self, dwarf_number: u16) -> Option<&'static str> {
+        let n = dwarf_number as usize;
+        match self {
+            Arch::X86 => X86_REGISTERS.get(n).copied(),
+            Arch::X86_64 => X86_64_REGISTERS.get(n).copied(),
+            Arch::Arm64 => match n {
+                0..=31 => Some(ARM64_REGISTERS[n]),
+                64..=95 => Some(ARM64_FP_REGISTERS[n - 64]),
+                _ => None,
+            },
+        }
+    }
+
+    /// The DWARF number of the frame pointer register.
+    pub fn frame_pointer_register(self) -> u16 {
+        match self {
+            Arch::X86 => 4,
+            Arch::X86_64 => 6,
+            Arch::Arm64 => 29,
+        }
+    }
+
+    /// The DWARF number of the stack pointer register.
+    pub fn stack_pointer_register(self) -> u16 {
+        match self {
+            Arch::X86 => 5,
+            Arch::X86_64 => 7,
+            Arch::Arm64 => 31,
+        }
+    }
+
+    /// The DWARF number of the return address column: the instruction
+    /// pointer on x86 and x86_64, the link register on arm64.
+    pub fn return_address_register(self) -> u16 {
+        match self {
+            Arch::X86 => 8,
+            Arch::X86_64 => 16,
+            Arch::Arm64 => 30,
+        }
+    }
+
+    /// Returns an object which formats the register with DWARF number
+    /// `dwarf_number`, named according to `names`.
+    pub fn display_register(self, dwarf_number: u16, names: RegisterNames) -> impl fmt::Display {
+        RegisterDisplay {
+            arch: self,
+            dwarf_number,
+            names,
+        }
+    }
+}
+
+struct RegisterDisplay {
+    arch: Arch,
+    dwarf_number: u16,
+    names: RegisterNames,
+}
+
+impl fmt::Display for RegisterDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.names, self.arch.register_name(self.dwarf_number)) {
+            (RegisterNames::Assembly, Some(name)) => f.write_str(name),
+            _ => write!(f, "reg{}", self.dwarf_number),
+        }
+    }
+}
+
+/// Implemented by the opcode enums, whose `Display` implementations use
+/// [`RegisterNames::Dwarf`].
+pub(crate) trait FormatOpcode {
+    fn fmt_with(&self, f: &mut fmt::Formatter<'_>, names: RegisterNames) -> fmt::Result;
+}
+
+/// Formats an opcode with the chosen register names, as returned by the
+/// opcode enums' `display` methods.
+pub(crate) struct OpcodeWithNames<'a, T> {
+    pub(crate) opcode: &'a T,
+    pub(crate) names: RegisterNames,
+}
+
+impl<T: FormatOpcode> fmt::Display for OpcodeWithNames<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.opcode.fmt_with(f, self.names)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn test_register_names() {
+        assert_eq!(Arch::X86_64.register_name(3), Some("rbx"));
+        assert_eq!(Arch::X86_64.register_name(16), Some("rip"));
+        assert_eq!(Arch::X86.register_name(4), Some("ebp"));
+        assert_eq!(Arch::X86.register_name(5), Some("esp"));
+        assert_eq!(Arch::X86.register_name(9), None);
+        assert_eq!(Arch::Arm64.register_name(19), Some("x19"));
+        assert_eq!(Arch::Arm64.register_name(29), Some("fp"));
+        assert_eq!(Arch::Arm64.register_name(72), Some("d8"));
+        assert_eq!(Arch::Arm64.register_name(40), None);
+        assert_eq!(
+            Arch::Arm64
+                .display_register(79, RegisterNames::Assembly)
+                .to_string(),
+            "d15"
+        );
+        assert_eq!(
+            Arch::Arm64
+                .display_register(40, RegisterNames::Assembly)
+                .to_string(),
+            "reg40"
+        );
+        assert_eq!(
+            Arch::Arm64
+                .display_register(79, RegisterNames::Dwarf)
+                .to_string(),
+            "reg79"
+        );
+    }
+}
@@ -1,8 +1,10 @@
-use std::fmt::Display;
+use core::fmt::Display;
 
 use super::bitfield::OpcodeBitfield;
 use super::permutation::decode_permutation_6;
+use super::register::{FormatOpcode, OpcodeWithNames, RegisterNames};
 use crate::consts::*;
+use crate::Arch;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum RegisterNameX86 {
@@ -34,7 +36,32 @@ impl RegisterNameX86 {
             RegisterNameX86::Edx => "reg2",
             RegisterNameX86::Edi => "reg7",
             RegisterNameX86::Esi => "reg6",
-            RegisterNameX86::Ebp => "reg5",
+            RegisterNameX86::Ebp => "reg4",
+        }
+    }
+
+    /// The register number in Darwin's `__eh_frame`, which has ebp and esp
+    /// swapped compared to the SysV numbering.
+    pub fn dwarf_number(&self) -> u16 {
+        match self {
+            RegisterNameX86::Ebx => 3,
+            RegisterNameX86::Ecx => 1,
+            RegisterNameX86::Edx => 2,
+            RegisterNameX86::Edi => 7,
+            RegisterNameX86::Esi => 6,
+            RegisterNameX86::Ebp => 4,
+        }
+    }
+
+    /// The assembly name, such as `ebx`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RegisterNameX86::Ebx => "ebx",
+            RegisterNameX86::Ecx => "ecx",
+            RegisterNameX86::Edx => "edx",
+            RegisterNameX86::Edi => "edi",
+            RegisterNameX86::Esi => "esi",
+            RegisterNameX86::Ebp => "ebp",
         }
     }
 }
@@ -142,10 +169,34 @@ impl OpcodeX86 {
             kind => OpcodeX86::UnrecognizedKind(kind),
         }
     }
+
+    /// Returns an object which formats the opcode with the chosen register
+    /// names. The `Display` implementation uses [`RegisterNames::Dwarf`].
+    pub fn display(&self, names: RegisterNames) -> impl Display + '_ {
+        OpcodeWithNames {
+            opcode: self,
+            names,
+        }
+    }
 }
 
 impl Display for OpcodeX86 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.fmt_with(f, RegisterNames::Dwarf)
+    }
+}
+
+impl FormatOpcode for OpcodeX86 {
+    fn fmt_with(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+        names: RegisterNames,
+    ) -> core::fmt::Result {
+        let arch = Arch::X86;
+        let register = |number| arch.display_register(number, names);
+        let bp = register(arch.frame_pointer_register());
+        let sp = register(arch.stack_pointer_register());
+        let ra = register(arch.return_address_register());
         match self {
             OpcodeX86::Null => {
                 write!(f, "(uncovered)")?;
@@ -156,12 +207,12 @@ impl Display for OpcodeX86 {
             } => {
                 // ebp was set to esp before the saved registers were pushed.
                 // The first pushed register is at ebp - 4 (== CFA - 12), the last at ebp - stack_offset_in_bytes.
-                write!(f, "CFA=reg6+8: reg6=[CFA-8], reg16=[CFA-4]")?;
+                write!(f, "CFA={bp}+8: {bp}=[CFA-8], {ra}=[CFA-4]")?;
                 let max_count = (*stack_offset_in_bytes / 4) as usize;
-                let mut offset = *stack_offset_in_bytes + 8; // + 2 for rbp, return address
+                let mut offset = *stack_offset_in_bytes + 8; // + 2 for ebp, return address
                 for reg in saved_regs.iter().rev().take(max_count) {
                     if let Some(reg) = reg {
-                        write!(f, ", {}=[CFA-{}]", reg.dwarf_name(), offset)?;
+                        write!(f, ", {}=[CFA-{}]", register(reg.dwarf_number()), offset)?;
                     }
                     offset -= 4;
                 }
@@ -171,14 +222,14 @@ impl Display for OpcodeX86 {
                 saved_regs,
             } => {
                 if *stack_size_in_bytes == 0 {
-                    write!(f, "CFA=reg7:",)?;
+                    write!(f, "CFA={sp}:")?;
                 } else {
-                    write!(f, "CFA=reg7+{}:", *stack_size_in_bytes)?;
+                    write!(f, "CFA={sp}+{}:", *stack_size_in_bytes)?;
                 }
-                write!(f, " reg16=[CFA-4]")?;
+                write!(f, " {ra}=[CFA-4]")?;
                 let mut offset = 2 * 4;
                 for reg in saved_regs.iter().rev().flatten() {
-                    write!(f, ", {}=[CFA-{}]", reg.dwarf_name(), offset)?;
+                    write!(f, ", {}=[CFA-{}]", register(reg.dwarf_number()), offset)?;
                     offset += 4;
                 }
             }
@@ -192,10 +243,10 @@ impl Display for OpcodeX86 {
                     "CFA=[function_start+{}]+{}",
                     immediate_offset_from_function_start, stack_adjust_in_bytes
                 )?;
-                write!(f, " reg16=[CFA-4]")?;
+                write!(f, " {ra}=[CFA-4]")?;
                 let mut offset = 2 * 4;
                 for reg in saved_regs.iter().rev().flatten() {
-                    write!(f, ", {}=[CFA-{}]", reg.dwarf_name(), offset)?;
+                    write!(f, ", {}=[CFA-{}]", register(reg.dwarf_number()), offset)?;
                     offset += 4;
                 }
             }
@@ -1,8 +1,10 @@
-use std::fmt::Display;
+use core::fmt::Display;
 
 use super::bitfield::OpcodeBitfield;
 use super::permutation::decode_permutation_6;
+use super::register::{FormatOpcode, OpcodeWithNames, RegisterNames};
 use crate::consts::*;
+use crate::Arch;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum RegisterNameX86_64 {
@@ -37,6 +39,30 @@ impl RegisterNameX86_64 {
             RegisterNameX86_64::Rbp => "reg6",
         }
     }
+
+    /// The DWARF register number.
+    pub fn dwarf_number(&self) -> u16 {
+        match self {
+            RegisterNameX86_64::Rbx => 3,
+            RegisterNameX86_64::R12 => 12,
+            RegisterNameX86_64::R13 => 13,
+            RegisterNameX86_64::R14 => 14,
+            RegisterNameX86_64::R15 => 15,
+            RegisterNameX86_64::Rbp => 6,
+        }
+    }
+
+    /// The assembly name, such as `rbx`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RegisterNameX86_64::Rbx => "rbx",
+            RegisterNameX86_64::R12 => "r12",
+            RegisterNameX86_64::R13 => "r13",
+            RegisterNameX86_64::R14 => "r14",
+            RegisterNameX86_64::R15 => "r15",
+            RegisterNameX86_64::Rbp => "rbp",
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -150,10 +176,34 @@ impl OpcodeX86_64 {
             kind => OpcodeX86_64::UnrecognizedKind(kind),
         }
     }
+
+    /// Returns an object which formats the opcode with the chosen register
+    /// names. The `Display` implementation uses [`RegisterNames::Dwarf`].
+    pub fn display(&self, names: RegisterNames) -> impl Display + '_ {
+        OpcodeWithNames {
+            opcode: self,
+            names,
+        }
+    }
 }
 
 impl Display for OpcodeX86_64 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.fmt_with(f, RegisterNames::Dwarf)
+    }
+}
+
+impl FormatOpcode for OpcodeX86_64 {
+    fn fmt_with(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+        names: RegisterNames,
+    ) -> core::fmt::Result {
+        let arch = Arch::X86_64;
+        let register = |number| arch.display_register(number, names);
+        let bp = register(arch.frame_pointer_register());
+        let sp = register(arch.stack_pointer_register());
+        let ra = register(arch.return_address_register());
         match self {
             OpcodeX86_64::Null => {
                 write!(f, "(uncovered)")?;
@@ -164,12 +214,12 @@ impl Display for OpcodeX86_64 {
             } => {
                 // rbp was set to rsp before the saved registers were pushed.
                 // The first pushed register is at rbp - 8 (== CFA - 24), the last at rbp - stack_offset_in_bytes.
-                write!(f, "CFA=reg6+16: reg6=[CFA-16], reg16=[CFA-8]")?;
+                write!(f, "CFA={bp}+16: {bp}=[CFA-16], {ra}=[CFA-8]")?;
                 let max_count = (*stack_offset_in_bytes / 8) as usize;
                 let mut offset = *stack_offset_in_bytes + 16; // + 2 for rbp, return address
                 for reg in saved_regs.iter().rev().take(max_count) {
                     if let Some(reg) = reg {
-                        write!(f, ", {}=[CFA-{}]", reg.dwarf_name(), offset)?;
+                        write!(f, ", {}=[CFA-{}]", register(reg.dwarf_number()), offset)?;
                     }
                     offset -= 8;
                 }
@@ -179,14 +229,14 @@ impl Display for OpcodeX86_64 {
                 saved_regs,
             } => {
                 if *stack_size_in_bytes == 0 {
-                    write!(f, "CFA=reg7:",)?;
+                    write!(f, "CFA={sp}:")?;
                 } else {
-                    write!(f, "CFA=reg7+{}:", *stack_size_in_bytes)?;
+                    write!(f, "CFA={sp}+{}:", *stack_size_in_bytes)?;
                 }
-                write!(f, " reg16=[CFA-8]")?;
+                write!(f, " {ra}=[CFA-8]")?;
                 let mut offset = 2 * 8;
                 for reg in saved_regs.iter().rev().flatten() {
-                    write!(f, ", {}=[CFA-{}]", reg.dwarf_name(), offset)?;
+                    write!(f, ", {}=[CFA-{}]", register(reg.dwarf_number()), offset)?;
                     offset += 8;
                 }
             }
@@ -200,10 +250,10 @@ impl Display for OpcodeX86_64 {
                     "CFA=[function_start+{}]+{}",
                     immediate_offset_from_function_start, stack_adjust_in_bytes
                 )?;
-                write!(f, " reg16=[CFA-8]")?;
+                write!(f, " {ra}=[CFA-8]")?;
                 let mut offset = 2 * 8;
                 for reg in saved_regs.iter().rev().flatten() {
-                    write!(f, ", {}=[CFA-{}]", reg.dwarf_name(), offset)?;
+                    write!(f, ", {}=[CFA-{}]", register(reg.dwarf_number()), offset)?;
                     offset += 8;
                 }
             }
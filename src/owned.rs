@@ -0,0 +1,77 @@
+use core::ops::Range;
+
+use crate::raw::{Opcode, PageEntry};
+use crate::reader::ArrayRef;
+use crate::{Error, Function, FunctionIter, UnwindInfo};
+
+/// An [`UnwindInfo`] which owns its section data.
+///
+/// The data can be any container which derefs to the section bytes, for
+/// example `Arc<[u8]>`, `Vec<u8>` or `&'static [u8]`. With `Arc<[u8]>`, the
+/// unwind info is `Send + Sync` and cheap to clone, so it can be stored in a
+/// module cache and shared between threads.
+///
+/// The header is parsed once, in [`OwnedUnwindInfo::parse`]. After that,
+/// [`OwnedUnwindInfo::unwind_info`] returns a borrowed [`UnwindInfo`] without
+/// any further parsing, so lookups are just as cheap as with the borrowed
+/// type.
+#[derive(Clone)]
+pub struct OwnedUnwindInfo<D: AsRef<[u8]>> {
+    data: D,
+    global_opcodes: ArrayRef<Opcode>,
+    pages: ArrayRef<PageEntry>,
+}
+
+impl<D: AsRef<[u8]>> OwnedUnwindInfo<D> {
+    /// Wraps the `__unwind_info` section data, see [`UnwindInfo::parse`].
+    ///
+    /// `data.as_ref()` must return the same bytes on every call.
+    pub fn parse(data: D) -> Result<Self, Error> {
+        let UnwindInfo {
+            global_opcodes,
+            pages,
+            ..
+        } = UnwindInfo::parse(data.as_ref())?;
+        Ok(Self {
+            data,
+            global_opcodes,
+            pages,
+        })
+    }
+
+    /// Returns the borrowed unwind info, which has the full API.
+    pub fn unwind_info(&self) -> UnwindInfo<'_> {
+        UnwindInfo {
+            data: self.data.as_ref(),
+            global_opcodes: self.global_opcodes,
+            pages: self.pages,
+        }
+    }
+
+    /// Returns the section data.
+    pub fn data(&self) -> &D {
+        &self.data
+    }
+
+    /// Unwraps the section data.
+    pub fn into_data(self) -> D {
+        self.data
+    }
+
+    /// Returns an iterator over all the functions, see [`UnwindInfo::functions`].
+    pub fn functions(&self) -> FunctionIter<'_> {
+        self.unwind_info().functions()
+    }
+
+    /// Returns the range of addresses covered by unwind information, see
+    /// [`UnwindInfo::address_range`].
+    pub fn address_range(&self) -> Range<u32> {
+        self.unwind_info().address_range()
+    }
+
+    /// Looks up the function that covers the given address, see
+    /// [`UnwindInfo::lookup`].
+    pub fn lookup(&self, pc: u32) -> Result<Option<Function>, Error> {
+        self.unwind_info().lookup(pc)
+    }
+}
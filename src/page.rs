@@ -0,0 +1,369 @@
+use core::ops::Range;
+
+use crate::raw::*;
+use crate::reader::{ArrayRef, Reader};
+use crate::{
+    compressed_entry_address, resolve_opcode_and_source, Error, ErrorKind, Function, PageFunctions,
+    UnwindInfo,
+};
+
+/// Where the opcode of a function entry is stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OpcodeSource {
+    /// A compressed entry's opcode index refers to the global opcode palette
+    /// in the section header, at `index`.
+    GlobalPalette { index: u8 },
+
+    /// A compressed entry's opcode index refers to the page's local opcode
+    /// palette, at `index`. This is the local index, i.e. the entry's opcode
+    /// index minus the size of the global palette.
+    LocalPalette { index: u8 },
+
+    /// A regular entry stores its opcode inline.
+    Inline,
+}
+
+/// A function entry of a page, as returned by [`PageView::function_at`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PageFunction {
+    pub function: Function,
+    pub opcode_source: OpcodeSource,
+}
+
+/// Random access to the function entries of a second-level page, as returned
+/// by [`UnwindInfo::page`].
+pub enum PageView<'a, R: Reader + ?Sized = [u8]> {
+    Regular(RegularPageView<'a, R>),
+    Compressed(CompressedPageView<'a, R>),
+}
+
+/// A view of a [`RegularPage`], whose entries store their opcodes inline.
+pub struct RegularPageView<'a, R: Reader + ?Sized = [u8]> {
+    data: &'a R,
+    page_index: usize,
+    page: RegularPage,
+    address_range: Range<u32>,
+    functions: ArrayRef<RegularFunctionEntry>,
+}
+
+/// A view of a [`CompressedPage`], whose entries refer to the global and
+/// the local opcode palettes.
+pub struct CompressedPageView<'a, R: Reader + ?Sized = [u8]> {
+    data: &'a R,
+    page_index: usize,
+    page: CompressedPage,
+    address_range: Range<u32>,
+    global_opcodes: ArrayRef<Opcode>,
+    local_opcodes: ArrayRef<Opcode>,
+    functions: ArrayRef<U32>,
+}
+
+impl<R: Reader + ?Sized> Clone for PageView<'_, R> {
+    fn clone(&self) -> Self {
+        match self {
+            PageView::Regular(page) => PageView::Regular(page.clone()),
+            PageView::Compressed(page) => PageView::Compressed(page.clone()),
+        }
+    }
+}
+
+impl<R: Reader + ?Sized> Clone for RegularPageView<'_, R> {
+    fn clone(&self) -> Self {
+        Self {
+            address_range: self.address_range.clone(),
+            ..*self
+        }
+    }
+}
+
+impl<R: Reader + ?Sized> Clone for CompressedPageView<'_, R> {
+    fn clone(&self) -> Self {
+        Self {
+            address_range: self.address_range.clone(),
+            ..*self
+        }
+    }
+}
+
+impl<'a, R: Reader + ?Sized> UnwindInfo<'a, R> {
+    /// Returns the number of second-level pages, not counting the sentinel
+    /// page entry at the end.
+    pub fn page_count(&self) -> usize {
+        self.pages.len().saturating_sub(1)
+    }
+
+    /// Returns a view of the page at `index`, or `None` if `index` is not
+    /// less than [`UnwindInfo::page_count`].
+    ///
+    /// Errors from the view's methods have the page index in their
+    /// [`ErrorContext`](crate::ErrorContext), and the entry index where it
+    /// applies.
+    pub fn page(&self, index: usize) -> Result<Option<PageView<'a, R>>, Error> {
+        if index >= self.page_count() {
+            return Ok(None);
+        }
+        self.page_impl(index)
+            .map(Some)
+            .map_err(|err| err.with_page_index(index))
+    }
+
+    fn page_impl(&self, index: usize) -> Result<PageView<'a, R>, Error> {
+        let data = self.data;
+        let page_entry = self.pages.get(data, index)?;
+        let next_page_entry = self.pages.get(data, index + 1)?;
+        let address_range = page_entry.first_address()..next_page_entry.first_address();
+        let view = match PageFunctions::parse(data, &page_entry)? {
+            PageFunctions::Regular { page, functions } => PageView::Regular(RegularPageView {
+                data,
+                page_index: index,
+                page,
+                address_range,
+                functions,
+            }),
+            PageFunctions::Compressed {
+                page,
+                functions,
+                local_opcodes,
+            } => PageView::Compressed(CompressedPageView {
+                data,
+                page_index: index,
+                page,
+                address_range,
+                global_opcodes: self.global_opcodes,
+                local_opcodes,
+                functions,
+            }),
+        };
+        Ok(view)
+    }
+
+    /// Returns the index of the page which covers `address`, or `None` if the
+    /// address is outside of [`UnwindInfo::address_range`].
+    pub fn page_index_of(&self, address: u32) -> Result<Option<usize>, Error> {
+        let page_index = match self
+            .pages
+            .binary_search_by_key(self.data, &address, PageEntry::first_address)
+            .map_err(|err| err.with_address(address))?
+        {
+            Ok(i) => i,
+            Err(0) => return Ok(None),
+            Err(insertion_index) => insertion_index - 1,
+        };
+        // The sentinel page entry marks the end of the covered range.
+        Ok(Some(page_index).filter(|i| *i < self.page_count()))
+    }
+}
+
+impl<R: Reader + ?Sized> PageView<'_, R> {
+    /// The number of function entries in the page.
+    pub fn len(&self) -> usize {
+        match self {
+            PageView::Regular(page) => page.len(),
+            PageView::Compressed(page) => page.len(),
+        }
+    }
+
+    /// Returns true if the page has no function entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The addresses covered by the page: from the page entry's first address
+    /// to the next page entry's first address.
+    pub fn address_range(&self) -> Range<u32> {
+        match self {
+            PageView::Regular(page) => page.address_range(),
+            PageView::Compressed(page) => page.address_range(),
+        }
+    }
+
+    /// Returns the function entry at `index`, or `None` if the index is out
+    /// of bounds. The function's end address is the next entry's address, or
+    /// the end of the page for the last entry.
+    pub fn function_at(&self, index: usize) -> Result<Option<PageFunction>, Error> {
+        match self {
+            PageView::Regular(page) => page.function_at(index),
+            PageView::Compressed(page) => page.function_at(index),
+        }
+    }
+
+    /// Returns the index of the function entry which covers `address`, or
+    /// `None` if the address is outside of [`PageView::address_range`].
+    ///
+    /// Returns [`ErrorKind::InvalidPageEntryFirstAddress`] if the address is
+    /// inside the page's range but before its first entry.
+    pub fn index_of(&self, address: u32) -> Result<Option<usize>, Error> {
+        match self {
+            PageView::Regular(page) => page.index_of(address),
+            PageView::Compressed(page) => page.index_of(address),
+        }
+    }
+}
+
+impl<R: Reader + ?Sized> RegularPageView<'_, R> {
+    /// The raw page header.
+    pub fn page(&self) -> &RegularPage {
+        &self.page
+    }
+
+    /// See [`PageView::len`].
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    /// See [`PageView::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// See [`PageView::address_range`].
+    pub fn address_range(&self) -> Range<u32> {
+        self.address_range.clone()
+    }
+
+    /// Returns the raw function entry at `index`, or `None` if the index is
+    /// out of bounds.
+    pub fn entry_at(&self, index: usize) -> Result<Option<RegularFunctionEntry>, Error> {
+        if index >= self.len() {
+            return Ok(None);
+        }
+        let entry = self
+            .functions
+            .get(self.data, index)
+            .map_err(|err| err.with_page_index(self.page_index).with_entry_index(index))?;
+        Ok(Some(entry))
+    }
+
+    /// See [`PageView::function_at`].
+    pub fn function_at(&self, index: usize) -> Result<Option<PageFunction>, Error> {
+        let Some(entry) = self.entry_at(index)? else {
+            return Ok(None);
+        };
+        let end_address = match self.entry_at(index + 1)? {
+            Some(next_entry) => next_entry.address(),
+            None => self.address_range.end,
+        };
+        Ok(Some(PageFunction {
+            function: Function {
+                start_address: entry.address(),
+                end_address,
+                opcode: entry.opcode(),
+            },
+            opcode_source: OpcodeSource::Inline,
+        }))
+    }
+
+    /// See [`PageView::index_of`].
+    pub fn index_of(&self, address: u32) -> Result<Option<usize>, Error> {
+        self.index_of_impl(address)
+            .map_err(|err| err.with_page_index(self.page_index).with_address(address))
+    }
+
+    fn index_of_impl(&self, address: u32) -> Result<Option<usize>, Error> {
+        if !self.address_range.contains(&address) {
+            return Ok(None);
+        }
+        let index = match self.functions.binary_search_by_key(
+            self.data,
+            &address,
+            RegularFunctionEntry::address,
+        )? {
+            Ok(i) => i,
+            Err(0) => return Err(ErrorKind::InvalidPageEntryFirstAddress.into()),
+            Err(insertion_index) => insertion_index - 1,
+        };
+        Ok(Some(index))
+    }
+}
+
+impl<R: Reader + ?Sized> CompressedPageView<'_, R> {
+    /// The raw page header.
+    pub fn page(&self) -> &CompressedPage {
+        &self.page
+    }
+
+    /// See [`PageView::len`].
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    /// See [`PageView::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// See [`PageView::address_range`].
+    pub fn address_range(&self) -> Range<u32> {
+        self.address_range.clone()
+    }
+
+    /// The number of opcodes in the page's local palette.
+    pub fn local_opcodes_len(&self) -> usize {
+        self.local_opcodes.len()
+    }
+
+    /// Returns the raw function entry at `index`, or `None` if the index is
+    /// out of bounds.
+    pub fn entry_at(&self, index: usize) -> Result<Option<CompressedFunctionEntry>, Error> {
+        if index >= self.len() {
+            return Ok(None);
+        }
+        let entry = self
+            .functions
+            .get(self.data, index)
+            .map_err(|err| err.with_page_index(self.page_index).with_entry_index(index))?;
+        Ok(Some(CompressedFunctionEntry::new(entry.into())))
+    }
+
+    /// See [`PageView::function_at`].
+    pub fn function_at(&self, index: usize) -> Result<Option<PageFunction>, Error> {
+        let Some(entry) = self.entry_at(index)? else {
+            return Ok(None);
+        };
+        let with_context =
+            |err: Error| err.with_page_index(self.page_index).with_entry_index(index);
+        let page_address = self.address_range.start;
+        let start_address = compressed_entry_address(page_address, entry).map_err(with_context)?;
+        let end_address = match self.entry_at(index + 1)? {
+            Some(next_entry) => {
+                compressed_entry_address(page_address, next_entry).map_err(with_context)?
+            }
+            None => self.address_range.end,
+        };
+        let (opcode, opcode_source) =
+            resolve_opcode_and_source(self.data, &self.global_opcodes, &self.local_opcodes, entry)
+                .map_err(with_context)?;
+        Ok(Some(PageFunction {
+            function: Function {
+                start_address,
+                end_address,
+                opcode,
+            },
+            opcode_source,
+        }))
+    }
+
+    /// See [`PageView::index_of`].
+    pub fn index_of(&self, address: u32) -> Result<Option<usize>, Error> {
+        self.index_of_impl(address)
+            .map_err(|err| err.with_page_index(self.page_index).with_address(address))
+    }
+
+    fn index_of_impl(&self, address: u32) -> Result<Option<usize>, Error> {
+        if !self.address_range.contains(&address) {
+            return Ok(None);
+        }
+        let relative_address = address - self.address_range.start;
+        let index =
+            match self
+                .functions
+                .binary_search_by_key(self.data, &relative_address, |&entry| {
+                    CompressedFunctionEntry::new(entry.into()).relative_address()
+                })? {
+                Ok(i) => i,
+                Err(0) => return Err(ErrorKind::InvalidPageEntryFirstAddress.into()),
+                Err(insertion_index) => insertion_index - 1,
+            };
+        Ok(Some(index))
+    }
+}
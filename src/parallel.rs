@@ -0,0 +1,51 @@
+use rayon::prelude::*;
+
+use crate::reader::Reader;
+use crate::{Error, Function, FunctionIter, UnwindInfo};
+
+impl<'a, R: Reader + Sync + ?Sized> UnwindInfo<'a, R> {
+    /// Returns a parallel iterator over all the functions in this UnwindInfo,
+    /// which splits the work at page granularity.
+    ///
+    /// The functions are the same as the ones from [`UnwindInfo::functions`],
+    /// including the end address of each page's last function, which comes
+    /// from the next page entry. Order-preserving consumers, such as
+    /// `collect`, receive them in address order. Other consumers, such as
+    /// `for_each`, process the pages in any order.
+    ///
+    /// If a page can't be read, its functions up to the error are followed
+    /// by the error, and the other pages are unaffected.
+    pub fn par_functions(&self) -> impl ParallelIterator<Item = Result<Function, Error>> + 'a {
+        let info = UnwindInfo {
+            data: self.data,
+            global_opcodes: self.global_opcodes,
+            pages: self.pages,
+        };
+        (0..self.page_count())
+            .into_par_iter()
+            .flat_map_iter(move |page_index| {
+                let mut functions = Some(info.page_functions(page_index));
+                core::iter::from_fn(move || {
+                    let result = functions.as_mut()?.next().transpose()?;
+                    if result.is_err() {
+                        functions = None;
+                    }
+                    Some(result)
+                })
+            })
+    }
+
+    /// Returns an iterator over the functions of the page at `page_index`,
+    /// which must be less than [`UnwindInfo::page_count`].
+    fn page_functions(&self, page_index: usize) -> FunctionIter<'a, R> {
+        FunctionIter {
+            data: self.data,
+            global_opcodes: self.global_opcodes,
+            // The page's entry, and the next page entry, which ends the page.
+            pages: self.pages.split_at(page_index).1.split_at(2).0,
+            pages_start_index: page_index,
+            front: None,
+            back: None,
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use core::ops::Range;
+
+use crate::reader::Reader;
+use crate::{Error, Function, PageView, UnwindInfo};
+
+/// How [`UnwindInfo::functions_in_range`] reports functions which extend
+/// past the edges of the queried range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RangeExtent {
+    /// The start and end addresses are clipped to the queried range.
+    #[default]
+    Clipped,
+
+    /// The functions are reported with the full extent of their entries.
+    Full,
+}
+
+/// An iterator over the functions which overlap an address range, as
+/// returned by [`UnwindInfo::functions_in_range`].
+pub struct FunctionsInRange<'a, R: Reader + ?Sized = [u8]> {
+    info: UnwindInfo<'a, R>,
+
+    /// The queried range.
+    range: Range<u32>,
+
+    extent: RangeExtent,
+
+    /// The page of the next function, or `None` once iteration has ended.
+    page: Option<PageView<'a, R>>,
+
+    page_index: usize,
+
+    /// The index of the next function in `page`.
+    entry_index: usize,
+}
+
+impl<'a, R: Reader + ?Sized> UnwindInfo<'a, R> {
+    /// Returns an iterator over the functions which overlap `range`, in
+    /// address order.
+    ///
+    /// The first function is found with the same two binary searches as
+    /// [`UnwindInfo::lookup`], so `functions_in_range(pc..pc + 1)` yields the
+    /// function that `lookup(pc)` returns. Iteration stops at the first
+    /// function which starts at or after `range.end`.
+    pub fn functions_in_range(
+        &self,
+        range: Range<u32>,
+        extent: RangeExtent,
+    ) -> Result<FunctionsInRange<'a, R>, Error> {
+        let info = UnwindInfo {
+            data: self.data,
+            global_opcodes: self.global_opcodes,
+            pages: self.pages,
+        };
+        let mut iter = FunctionsInRange {
+            info,
+            range: range.clone(),
+            extent,
+            page: None,
+            page_index: 0,
+            entry_index: 0,
+        };
+        let covered = self.address_range();
+        if range.is_empty() || range.end <= covered.start || range.start >= covered.end {
+            return Ok(iter);
+        }
+        // Ranges which start before the first page start at its first entry.
+        let start = range.start.max(covered.start);
+        let Some(page_index) = self.page_index_of(start)? else {
+            return Ok(iter);
+        };
+        let Some(page) = self.page(page_index)? else {
+            return Ok(iter);
+        };
+        let Some(entry_index) = page.index_of(start)? else {
+            return Ok(iter);
+        };
+        iter.page = Some(page);
+        iter.page_index = page_index;
+        iter.entry_index = entry_index;
+        Ok(iter)
+    }
+}
+
+impl<R: Reader + ?Sized> FunctionsInRange<'_, R> {
+    /// Returns the next function which overlaps the range.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<Function>, Error> {
+        loop {
+            let Some(page) = &self.page else {
+                return Ok(None);
+            };
+            let Some(page_function) = page.function_at(self.entry_index)? else {
+                self.page_index += 1;
+                self.entry_index = 0;
+                self.page = self.info.page(self.page_index)?;
+                continue;
+            };
+            let mut function = page_function.function;
+            if function.start_address >= self.range.end {
+                self.page = None;
+                return Ok(None);
+            }
+            self.entry_index += 1;
+            if function.end_address <= self.range.start {
+                // A zero-length entry at `range.start`, if the binary search
+                // landed on one of several entries with the same address.
+                continue;
+            }
+            if self.extent == RangeExtent::Clipped {
+                function.start_address = function.start_address.max(self.range.start);
+                function.end_address = function.end_address.min(self.range.end);
+            }
+            return Ok(Some(function));
+        }
+    }
+}
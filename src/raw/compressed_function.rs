@@ -1,8 +1,8 @@
 use crate::num_display::HexNum;
-use std::fmt::Debug;
+use core::fmt::Debug;
 
 /// Allows accessing the two packed values from a "compressed" function entry.
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CompressedFunctionEntry(pub u32);
 
 /// Entries are a u32 that contains two packed values (from high to low):
@@ -35,7 +35,7 @@ impl From<u32> for CompressedFunctionEntry {
 }
 
 impl Debug for CompressedFunctionEntry {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("CompressedFunctionEntry")
             .field("opcode_index", &HexNum(self.opcode_index()))
             .field("relative_address", &HexNum(self.relative_address()))
@@ -1,21 +1,26 @@
-use std::fmt::Debug;
+use core::fmt::Debug;
 
 use super::format::{
-    CompactUnwindInfoHeader, CompressedPage, Opcode, PageEntry, RegularFunctionEntry, RegularPage,
+    CompactUnwindInfoHeader, CompressedPage, LsdaEntry, Opcode, PageEntry, RegularFunctionEntry,
+    RegularPage,
 };
 use super::unaligned::U32;
 use crate::error::ReadError;
 use crate::num_display::HexNum;
-use crate::reader::Reader;
+use crate::reader::SliceReader;
 
-type Result<T> = std::result::Result<T, ReadError>;
+type Result<T> = core::result::Result<T, ReadError>;
 
 impl CompactUnwindInfoHeader {
     pub fn parse(data: &[u8]) -> Result<&Self> {
-        data.read_at::<CompactUnwindInfoHeader>(0)
+        data.read_ref_at::<CompactUnwindInfoHeader>(0)
             .ok_or(ReadError::Header)
     }
 
+    pub fn version(&self) -> u32 {
+        self.version.into()
+    }
+
     pub fn global_opcodes_offset(&self) -> u32 {
         self.global_opcodes_offset.into()
     }
@@ -24,6 +29,14 @@ impl CompactUnwindInfoHeader {
         self.global_opcodes_len.into()
     }
 
+    pub fn personalities_offset(&self) -> u32 {
+        self.personalities_offset.into()
+    }
+
+    pub fn personalities_len(&self) -> u32 {
+        self.personalities_len.into()
+    }
+
     pub fn pages_offset(&self) -> u32 {
         self.pages_offset.into()
     }
@@ -50,7 +63,7 @@ impl CompactUnwindInfoHeader {
 
 impl RegularPage {
     pub fn parse(data: &[u8], page_offset: u64) -> Result<&Self> {
-        data.read_at::<Self>(page_offset)
+        data.read_ref_at::<Self>(page_offset)
             .ok_or(ReadError::RegularPage)
     }
 
@@ -77,7 +90,7 @@ impl RegularPage {
 
 impl CompressedPage {
     pub fn parse(data: &[u8], page_offset: u64) -> Result<&Self> {
-        data.read_at::<Self>(page_offset)
+        data.read_ref_at::<Self>(page_offset)
             .ok_or(ReadError::CompressedPage)
     }
 
@@ -135,6 +148,16 @@ impl RegularFunctionEntry {
     }
 }
 
+impl LsdaEntry {
+    pub fn function_offset(&self) -> u32 {
+        self.function_offset.into()
+    }
+
+    pub fn lsda_offset(&self) -> u32 {
+        self.lsda_offset.into()
+    }
+}
+
 impl PageEntry {
     pub fn page_offset(&self) -> u32 {
         self.page_offset.into()
@@ -150,14 +173,14 @@ impl PageEntry {
 
     pub fn page_kind(&self, data: &[u8]) -> Result<u32> {
         let kind = *data
-            .read_at::<U32>(self.page_offset().into())
+            .read_ref_at::<U32>(self.page_offset().into())
             .ok_or(ReadError::PageKind)?;
         Ok(kind.into())
     }
 }
 
 impl Debug for PageEntry {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("PageEntry")
             .field("first_address", &HexNum(self.first_address()))
             .field("page_offset", &HexNum(self.page_offset()))
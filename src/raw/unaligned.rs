@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use core::fmt::Debug;
 
 use zerocopy_derive::*;
 
@@ -6,6 +6,7 @@ use zerocopy_derive::*;
 #[derive(
     Unaligned,
     FromBytes,
+    IntoBytes,
     KnownLayout,
     Immutable,
     Default,
@@ -33,7 +34,7 @@ impl From<U32> for u32 {
 }
 
 impl Debug for U32 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         u32::fmt(&(*self).into(), f)
     }
 }
@@ -42,6 +43,7 @@ impl Debug for U32 {
 #[derive(
     Unaligned,
     FromBytes,
+    IntoBytes,
     KnownLayout,
     Immutable,
     Default,
@@ -69,7 +71,44 @@ impl From<U16> for u16 {
 }
 
 impl Debug for U16 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         u16::fmt(&(*self).into(), f)
     }
 }
+
+/// An unaligned little-endian `u64` value.
+#[derive(
+    Unaligned,
+    FromBytes,
+    IntoBytes,
+    KnownLayout,
+    Immutable,
+    Default,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[repr(transparent)]
+pub struct U64([u8; 8]);
+
+impl From<u64> for U64 {
+    fn from(n: u64) -> Self {
+        U64(n.to_le_bytes())
+    }
+}
+
+impl From<U64> for u64 {
+    fn from(n: U64) -> Self {
+        u64::from_le_bytes(n.0)
+    }
+}
+
+impl Debug for U64 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        u64::fmt(&(*self).into(), f)
+    }
+}
@@ -1,7 +1,145 @@
-use zerocopy::{FromBytes, Immutable, KnownLayout, Ref};
+use core::marker::PhantomData;
+use core::ops::Deref;
 
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Ref, Unaligned};
+
+use crate::error::{Error, ReadError};
+
+/// Random access to the bytes of an `__unwind_info` section.
+///
+/// [`UnwindInfo`](crate::UnwindInfo) is generic over this trait, so the section
+/// data doesn't need to be available as one contiguous slice. Only a handful of
+/// small structs are read for each lookup: the header, the page entries that
+/// are visited by the binary search, and the entries of a single second-level
+/// page.
+///
+/// Implementors only need to provide [`Reader::read_bytes_at`]. Sources which
+/// have the data in memory can also override [`Reader::read_at`] in order to
+/// hand out references instead of copies.
 pub trait Reader {
-    fn read_at<T>(&self, offset: u64) -> Option<&T>
+    /// Fills `buf` with the bytes at `offset`, relative to the start of the
+    /// section. Returns `false` if the bytes could not be read, for example
+    /// because the range is out of bounds.
+    fn read_bytes_at(&self, offset: u64, buf: &mut [u8]) -> bool;
+
+    /// Reads a `T` at `offset`, relative to the start of the section.
+    fn read_at<T>(&self, offset: u64) -> Option<MaybeOwned<'_, T>>
+    where
+        T: FromBytes + IntoBytes + KnownLayout + Immutable + Unaligned,
+    {
+        let mut value = T::new_zeroed();
+        if self.read_bytes_at(offset, value.as_mut_bytes()) {
+            Some(MaybeOwned::Owned(value))
+        } else {
+            None
+        }
+    }
+}
+
+/// A value returned by [`Reader::read_at`], either borrowed from the
+/// underlying data or copied out of it.
+#[derive(Debug, Clone, Copy)]
+pub enum MaybeOwned<'a, T> {
+    Borrowed(&'a T),
+    Owned(T),
+}
+
+impl<T> Deref for MaybeOwned<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            MaybeOwned::Borrowed(value) => value,
+            MaybeOwned::Owned(value) => value,
+        }
+    }
+}
+
+impl Reader for [u8] {
+    fn read_bytes_at(&self, offset: u64, buf: &mut [u8]) -> bool {
+        let Ok(offset) = usize::try_from(offset) else {
+            return false;
+        };
+        let Some(end) = offset.checked_add(buf.len()) else {
+            return false;
+        };
+        match self.get(offset..end) {
+            Some(bytes) => {
+                buf.copy_from_slice(bytes);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn read_at<T>(&self, offset: u64) -> Option<MaybeOwned<'_, T>>
+    where
+        T: FromBytes + IntoBytes + KnownLayout + Immutable + Unaligned,
+    {
+        self.read_ref_at(offset).map(MaybeOwned::Borrowed)
+    }
+}
+
+/// A [`Reader`] which reads the section from an [`std::io::Read`] +
+/// [`std::io::Seek`] source, for example a [`std::fs::File`].
+///
+/// The section is located at `section_offset` in the source and is
+/// `section_size` bytes long. Nothing is read until a struct is requested.
+#[cfg(feature = "std")]
+pub struct ReadSeekReader<R: std::io::Read + std::io::Seek> {
+    inner: core::cell::RefCell<R>,
+    section_offset: u64,
+    section_size: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read + std::io::Seek> ReadSeekReader<R> {
+    /// Wrap `inner`. The section data starts at `section_offset`.
+    pub fn new(inner: R, section_offset: u64, section_size: u64) -> Self {
+        Self {
+            inner: core::cell::RefCell::new(inner),
+            section_offset,
+            section_size,
+        }
+    }
+
+    /// Unwrap the underlying source.
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read + std::io::Seek> Reader for ReadSeekReader<R> {
+    fn read_bytes_at(&self, offset: u64, buf: &mut [u8]) -> bool {
+        match offset.checked_add(buf.len() as u64) {
+            Some(end) if end <= self.section_size => {}
+            _ => return false,
+        }
+        let Ok(mut inner) = self.inner.try_borrow_mut() else {
+            return false;
+        };
+        let pos = std::io::SeekFrom::Start(self.section_offset + offset);
+        inner.seek(pos).is_ok() && inner.read_exact(buf).is_ok()
+    }
+}
+
+/// A [`Reader`] which calls a user-supplied function for each read.
+///
+/// The function gets the offset relative to the start of the section and a
+/// buffer to fill, and returns whether the read succeeded. This can be used
+/// to read the unwind info out of another process's memory, for example.
+pub struct FnReader<F: Fn(u64, &mut [u8]) -> bool>(pub F);
+
+impl<F: Fn(u64, &mut [u8]) -> bool> Reader for FnReader<F> {
+    fn read_bytes_at(&self, offset: u64, buf: &mut [u8]) -> bool {
+        (self.0)(offset, buf)
+    }
+}
+
+/// Zero-copy access to slices, used by the slice-based APIs in the `raw` module.
+pub(crate) trait SliceReader {
+    fn read_ref_at<T>(&self, offset: u64) -> Option<&T>
     where
         T: FromBytes + KnownLayout + Immutable;
     fn read_slice_at<T>(&self, offset: u64, len: usize) -> Option<&[T]>
@@ -9,8 +147,8 @@ pub trait Reader {
         T: FromBytes + KnownLayout + Immutable;
 }
 
-impl Reader for [u8] {
-    fn read_at<T>(&self, offset: u64) -> Option<&T>
+impl SliceReader for [u8] {
+    fn read_ref_at<T>(&self, offset: u64) -> Option<&T>
     where
         T: FromBytes + KnownLayout + Immutable,
     {
@@ -30,3 +168,115 @@ impl Reader for [u8] {
         Some(Ref::into_ref(lv))
     }
 }
+
+/// An array of `T`s somewhere in the section data, accessed one element at a
+/// time through a [`Reader`]. This is the [`Reader`] counterpart of a slice.
+pub(crate) struct ArrayRef<T> {
+    offset: u64,
+    len: usize,
+    error: ReadError,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for ArrayRef<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ArrayRef<T> {}
+
+impl<T> ArrayRef<T>
+where
+    T: FromBytes + IntoBytes + KnownLayout + Immutable + Unaligned + Copy,
+{
+    /// Create an array of `len` elements at `offset`. Checks that the last
+    /// element can be read, and returns `error` otherwise. The same error is
+    /// returned by later failed element reads, with the element's offset as
+    /// the error context.
+    pub fn new<R: Reader + ?Sized>(
+        data: &R,
+        offset: u64,
+        len: usize,
+        error: ReadError,
+    ) -> Result<Self, Error> {
+        let array = Self {
+            offset,
+            len,
+            error,
+            _phantom: PhantomData,
+        };
+        if let Some(last_index) = len.checked_sub(1) {
+            array.get(data, last_index)?;
+        }
+        Ok(array)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Read the element at `index`. The index must be in bounds.
+    pub fn get<R: Reader + ?Sized>(&self, data: &R, index: usize) -> Result<T, Error> {
+        debug_assert!(index < self.len);
+        let offset = (index as u64)
+            .checked_mul(core::mem::size_of::<T>() as u64)
+            .and_then(|o| o.checked_add(self.offset))
+            .ok_or(Error::read(self.error, self.offset))?;
+        let value = data
+            .read_at::<T>(offset)
+            .ok_or(Error::read(self.error, offset))?;
+        Ok(*value)
+    }
+
+    pub fn first<R: Reader + ?Sized>(&self, data: &R) -> Result<Option<T>, Error> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        self.get(data, 0).map(Some)
+    }
+
+    pub fn last<R: Reader + ?Sized>(&self, data: &R) -> Result<Option<T>, Error> {
+        match self.len.checked_sub(1) {
+            Some(last_index) => self.get(data, last_index).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Like `<[T]>::split_at`, but doesn't read anything. Panics if `mid`
+    /// is greater than the length.
+    pub fn split_at(&self, mid: usize) -> (Self, Self) {
+        assert!(mid <= self.len);
+        let head = Self { len: mid, ..*self };
+        let tail = Self {
+            offset: self.offset + mid as u64 * core::mem::size_of::<T>() as u64,
+            len: self.len - mid,
+            ..*self
+        };
+        (head, tail)
+    }
+
+    /// Like `<[T]>::binary_search_by_key`.
+    pub fn binary_search_by_key<R: Reader + ?Sized, K: Ord>(
+        &self,
+        data: &R,
+        key: &K,
+        mut f: impl FnMut(&T) -> K,
+    ) -> Result<Result<usize, usize>, Error> {
+        let mut low = 0;
+        let mut high = self.len;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match f(&self.get(data, mid)?).cmp(key) {
+                core::cmp::Ordering::Less => low = mid + 1,
+                core::cmp::Ordering::Greater => high = mid,
+                core::cmp::Ordering::Equal => return Ok(Ok(mid)),
+            }
+        }
+        Ok(Err(low))
+    }
+}
@@ -0,0 +1,178 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::opcodes::{OpcodeBitfield, RegisterNames};
+use crate::raw::consts::OPCODE_KIND_NULL;
+use crate::reader::Reader;
+use crate::symbol::sorted_symbols;
+use crate::{Arch, Error, Function, Symbol, UnwindInfo};
+
+/// A listing of all function entries in an `__unwind_info` section, joined
+/// with the symbol table, as returned by [`UnwindInfo::symbolized_report`].
+///
+/// The [`Display`](fmt::Display) implementation prints one line per entry,
+/// followed by indented lines for the entry's warnings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolizedReport<'s> {
+    /// The architecture used to format the opcodes.
+    pub arch: Arch,
+
+    /// How registers are named when formatting the opcodes. Defaults to
+    /// [`RegisterNames::Dwarf`].
+    pub register_names: RegisterNames,
+
+    /// One entry per function entry in the unwind info, in address order.
+    pub entries: Vec<ReportEntry<'s>>,
+}
+
+/// A function entry with the symbol which contains its start address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReportEntry<'s> {
+    pub function: Function,
+
+    /// The closest symbol at or below `function.start_address`, or `None`
+    /// if the entry starts before the first symbol.
+    pub symbol: Option<Symbol<'s>>,
+
+    /// The offset of `function.start_address` from the symbol's address.
+    pub offset_from_symbol: u32,
+
+    pub warnings: Vec<ReportWarning<'s>>,
+}
+
+/// A discrepancy between the unwind entries and the symbol table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReportWarning<'s> {
+    /// Other symbols start inside this entry's address range. This happens
+    /// when the linker merges adjacent functions with identical opcodes.
+    SpansMultipleSymbols(Vec<Symbol<'s>>),
+
+    /// The entry's symbol is covered by several entries; this entry is the
+    /// `index`-th of `count`, counting from zero. Entries with a `Null`
+    /// opcode, such as padding between functions, are not counted.
+    SymbolSplitAcrossEntries { index: usize, count: usize },
+}
+
+impl<R: Reader + ?Sized> UnwindInfo<'_, R> {
+    /// Joins each function entry with the symbol table.
+    ///
+    /// `symbols` doesn't need to be sorted. When several symbols share an
+    /// address, the first of them in `symbols` is used.
+    pub fn symbolized_report<'s>(
+        &self,
+        arch: Arch,
+        symbols: &[Symbol<'s>],
+    ) -> Result<SymbolizedReport<'s>, Error> {
+        let symbols = sorted_symbols(symbols);
+
+        let mut entries = Vec::new();
+        let mut symbol_indexes = Vec::new();
+        let mut iter = self.functions();
+        while let Some(function) = iter.next()? {
+            let next_symbol = symbols.partition_point(|s| s.address <= function.start_address);
+            let symbol_index = next_symbol.checked_sub(1);
+            let (symbol, offset_from_symbol) = match symbol_index {
+                Some(index) => (
+                    Some(symbols[index]),
+                    function.start_address - symbols[index].address,
+                ),
+                None => (None, 0),
+            };
+            let spanned_end = symbols.partition_point(|s| s.address < function.end_address);
+            let mut warnings = Vec::new();
+            if next_symbol < spanned_end {
+                warnings.push(ReportWarning::SpansMultipleSymbols(
+                    symbols[next_symbol..spanned_end].to_vec(),
+                ));
+            }
+            let is_null = OpcodeBitfield::new(function.opcode).kind() == OPCODE_KIND_NULL;
+            symbol_indexes.push(symbol_index.filter(|_| !is_null));
+            entries.push(ReportEntry {
+                function,
+                symbol,
+                offset_from_symbol,
+                warnings,
+            });
+        }
+
+        // Entries are sorted by address, so all entries of a symbol are
+        // adjacent, apart from Null entries.
+        let mut run_start = 0;
+        while run_start < entries.len() {
+            let Some(symbol_index) = symbol_indexes[run_start] else {
+                run_start += 1;
+                continue;
+            };
+            let mut run = Vec::new();
+            let mut i = run_start;
+            while i < entries.len() && entries[i].symbol == Some(symbols[symbol_index]) {
+                if symbol_indexes[i].is_some() {
+                    run.push(i);
+                }
+                i += 1;
+            }
+            if run.len() > 1 {
+                for (index, &entry_index) in run.iter().enumerate() {
+                    entries[entry_index]
+                        .warnings
+                        .push(ReportWarning::SymbolSplitAcrossEntries {
+                            index,
+                            count: run.len(),
+                        });
+                }
+            }
+            run_start = i;
+        }
+
+        Ok(SymbolizedReport {
+            arch,
+            register_names: RegisterNames::default(),
+            entries,
+        })
+    }
+}
+
+impl fmt::Display for SymbolizedReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            write!(f, "0x{:08x}", entry.function.start_address)?;
+            match &entry.symbol {
+                Some(symbol) => write!(f, " {}+0x{:x}", symbol.name, entry.offset_from_symbol)?,
+                None => write!(f, " (no symbol)")?,
+            }
+            writeln!(
+                f,
+                ": {}",
+                self.arch
+                    .display_opcode_with(entry.function.opcode, self.register_names)
+            )?;
+            for warning in &entry.warnings {
+                writeln!(f, "    warning: {}", warning)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ReportWarning<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportWarning::SpansMultipleSymbols(symbols) => {
+                write!(f, "entry also covers")?;
+                for (i, symbol) in symbols.iter().enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+                    write!(f, "{}{} (0x{:08x})", separator, symbol.name, symbol.address)?;
+                }
+                Ok(())
+            }
+            ReportWarning::SymbolSplitAcrossEntries { index, count } => {
+                write!(
+                    f,
+                    "symbol is split across {} entries, this is entry {}",
+                    count,
+                    index + 1
+                )
+            }
+        }
+    }
+}
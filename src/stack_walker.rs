@@ -0,0 +1,861 @@
+use crate::opcodes::{OpcodeArm64, OpcodeX86, OpcodeX86_64, RegisterNameX86, RegisterNameX86_64};
+use crate::reader::Reader;
+use crate::{Arch, Error, Function, UnwindInfo};
+
+/// Read access to the memory of the unwound thread.
+pub trait ReadMemory {
+    /// Reads the 8 bytes at `address`, interpreted as a little-endian `u64`.
+    /// On 32-bit x86, only the lower 32 bits of the result are used.
+    /// Returns `None` if the memory cannot be read.
+    fn read_memory(&mut self, address: u64) -> Option<u64>;
+}
+
+impl<F: FnMut(u64) -> Option<u64>> ReadMemory for F {
+    fn read_memory(&mut self, address: u64) -> Option<u64> {
+        self(address)
+    }
+}
+
+/// Finds the unwind info function entry for an absolute address.
+pub trait FunctionLookup {
+    /// Returns the base address of the image that contains `address`, together
+    /// with the function entry covering it. The function entry's addresses are
+    /// relative to the returned base address.
+    fn lookup_function(&self, address: u64) -> Result<Option<(u64, Function)>, Error>;
+}
+
+/// A single image: its `__unwind_info`, and the address at which the image's
+/// mach-O header is located in the unwound process.
+pub struct ImageUnwindInfo<'u, 'a, R: Reader + ?Sized = [u8]> {
+    pub base_address: u64,
+    pub unwind_info: &'u UnwindInfo<'a, R>,
+}
+
+impl<R: Reader + ?Sized> FunctionLookup for ImageUnwindInfo<'_, '_, R> {
+    fn lookup_function(&self, address: u64) -> Result<Option<(u64, Function)>, Error> {
+        let Some(relative_address) = address.checked_sub(self.base_address) else {
+            return Ok(None);
+        };
+        let Ok(relative_address) = u32::try_from(relative_address) else {
+            return Ok(None);
+        };
+        Ok(self
+            .unwind_info
+            .lookup(relative_address)?
+            .map(|function| (self.base_address, function)))
+    }
+}
+
+/// How the registers of a frame were obtained.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnwindMethod {
+    /// This is the first frame; its registers were passed to the walker.
+    InitialRegisters,
+
+    /// The callee had a frame-based opcode: the return address and the
+    /// caller's frame pointer were read from the callee's frame record.
+    FramePointer,
+
+    /// The callee had a frameless opcode with a stack size encoded in the opcode.
+    FramelessImmediate,
+
+    /// The callee had a frameless opcode with a stack size read from the
+    /// callee's `sub` instruction (x86 and x86_64 only).
+    FramelessIndirect,
+
+    /// The callee was an arm64 frameless function, and the return address was
+    /// taken from the `lr` register.
+    FramelessLr,
+
+    /// The unwind info didn't apply to the callee, and the registers were
+    /// recovered with a rule of the walker's [`FallbackPolicy`]. This is a
+    /// heuristic, so the frame may be wrong.
+    Fallback {
+        rule: FallbackRule,
+        reason: MissingUnwindInfo,
+    },
+}
+
+impl UnwindMethod {
+    /// Whether the frame was found with a heuristic instead of with unwind
+    /// info, i.e. with a [`FallbackRule`].
+    pub fn is_heuristic(&self) -> bool {
+        matches!(self, UnwindMethod::Fallback { .. })
+    }
+}
+
+/// Why the compact unwind info doesn't describe how to unwind a function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MissingUnwindInfo {
+    /// No function entry covers the address.
+    NotCovered,
+
+    /// The function entry has a `Null` opcode.
+    NullOpcode,
+
+    /// The opcode's kind is not defined for the architecture.
+    UnrecognizedKind(u8),
+}
+
+/// A heuristic for unwinding a frame without unwind info, as chosen by a
+/// [`FallbackPolicy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FallbackRule {
+    /// Assume that the frame pointer points at a frame record with the
+    /// caller's frame pointer and the return address, as in frame-based
+    /// functions. The caller's callee-saved registers become unknown.
+    FramePointer,
+
+    /// Assume that the function is an arm64 leaf function which hasn't
+    /// touched the stack, so that the return address is still in `lr`. Only
+    /// applies to the innermost frame, where `lr` is known.
+    ReturnAddressInLr,
+
+    /// Don't guess, and stop the walk with an error.
+    Stop,
+}
+
+/// Chooses the [`FallbackRule`] for frames without usable unwind info.
+///
+/// The default policy is [`FallbackPolicy::STOP`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FallbackPolicy {
+    /// The rule for addresses which no function entry covers.
+    pub not_covered: FallbackRule,
+
+    /// The rule for functions with a `Null` opcode.
+    pub null_opcode: FallbackRule,
+
+    /// The rule for opcodes with a kind which the architecture doesn't define.
+    pub unrecognized_kind: FallbackRule,
+
+    /// Whether the innermost arm64 frame uses
+    /// [`FallbackRule::ReturnAddressInLr`] instead of the rules above, unless
+    /// they are [`FallbackRule::Stop`].
+    pub arm64_innermost_lr: bool,
+}
+
+impl FallbackPolicy {
+    /// Never guess: stop the walk at the first frame without unwind info.
+    pub const STOP: Self = Self {
+        not_covered: FallbackRule::Stop,
+        null_opcode: FallbackRule::Stop,
+        unrecognized_kind: FallbackRule::Stop,
+        arm64_innermost_lr: false,
+    };
+
+    /// Walk the frame pointer chain through frames without unwind info, but
+    /// take the return address from `lr` in the innermost arm64 frame.
+    pub const FRAME_POINTER: Self = Self {
+        not_covered: FallbackRule::FramePointer,
+        null_opcode: FallbackRule::FramePointer,
+        unrecognized_kind: FallbackRule::FramePointer,
+        arm64_innermost_lr: true,
+    };
+
+    /// Returns the rule for a frame of `arch` without unwind info.
+    ///
+    /// [`FallbackRule::ReturnAddressInLr`] is only returned for the innermost
+    /// arm64 frame; elsewhere, it's replaced by [`FallbackRule::Stop`].
+    pub fn rule(
+        &self,
+        arch: Arch,
+        reason: MissingUnwindInfo,
+        is_innermost_frame: bool,
+    ) -> FallbackRule {
+        let rule = match reason {
+            MissingUnwindInfo::NotCovered => self.not_covered,
+            MissingUnwindInfo::NullOpcode => self.null_opcode,
+            MissingUnwindInfo::UnrecognizedKind(_) => self.unrecognized_kind,
+        };
+        let lr_is_known = arch == Arch::Arm64 && is_innermost_frame;
+        match rule {
+            FallbackRule::Stop => FallbackRule::Stop,
+            _ if lr_is_known && self.arm64_innermost_lr => FallbackRule::ReturnAddressInLr,
+            FallbackRule::ReturnAddressInLr if !lr_is_known => FallbackRule::Stop,
+            rule => rule,
+        }
+    }
+}
+
+impl Default for FallbackPolicy {
+    fn default() -> Self {
+        Self::STOP
+    }
+}
+
+impl Arch {
+    /// Returns why `opcode` doesn't describe how to unwind its function, or
+    /// `None` if it does. `Dwarf` opcodes describe the unwinding, with the
+    /// help of `__eh_frame`.
+    pub fn missing_unwind_info(self, opcode: u32) -> Option<MissingUnwindInfo> {
+        match self {
+            Arch::X86 => match OpcodeX86::parse(opcode) {
+                OpcodeX86::Null => Some(MissingUnwindInfo::NullOpcode),
+                OpcodeX86::UnrecognizedKind(kind) => {
+                    Some(MissingUnwindInfo::UnrecognizedKind(kind))
+                }
+                _ => None,
+            },
+            Arch::X86_64 => match OpcodeX86_64::parse(opcode) {
+                OpcodeX86_64::Null => Some(MissingUnwindInfo::NullOpcode),
+                OpcodeX86_64::UnrecognizedKind(kind) => {
+                    Some(MissingUnwindInfo::UnrecognizedKind(kind))
+                }
+                _ => None,
+            },
+            Arch::Arm64 => match OpcodeArm64::parse(opcode) {
+                OpcodeArm64::Null => Some(MissingUnwindInfo::NullOpcode),
+                OpcodeArm64::UnrecognizedKind(kind) => {
+                    Some(MissingUnwindInfo::UnrecognizedKind(kind))
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+/// The reason why the walker stopped with an error.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackWalkError {
+    /// There was a problem with the format of the `__unwind_info` data.
+    #[error("Unwind info error: {0}")]
+    UnwindInfo(#[from] Error),
+
+    /// No function entry covers the address, or its opcode is `Null`.
+    #[error("No unwind info for address 0x{0:x}")]
+    NoUnwindInfo(u64),
+
+    /// The function at this address needs to be unwound with `__eh_frame`,
+    /// which the walker does not support.
+    #[error("The function at 0x{address:x} needs eh_frame FDE 0x{eh_frame_fde:x}")]
+    NeedsDwarf { address: u64, eh_frame_fde: u32 },
+
+    /// The opcode for the function at this address could not be interpreted.
+    #[error("Invalid opcode 0x{opcode:x} for address 0x{address:x}")]
+    InvalidOpcode { address: u64, opcode: u32 },
+
+    /// Reading the stack or the function's code failed at this address.
+    #[error("Could not read memory at 0x{0:x}")]
+    MemoryReadFailed(u64),
+
+    /// The return address of an arm64 frameless function was needed, but `lr`
+    /// is unknown because this is not the innermost frame.
+    #[error("The value of lr is unknown")]
+    LrUnknown,
+
+    /// The stack pointer of the caller frame was not above the stack pointer
+    /// of the callee frame.
+    #[error("The stack pointer did not increase: 0x{callee_sp:x} -> 0x{caller_sp:x}")]
+    NonIncreasingSp { callee_sp: u64, caller_sp: u64 },
+
+    /// The walker produced the same frame twice in a row.
+    #[error("Loop detected at pc 0x{0:x}")]
+    Loop(u64),
+
+    /// The walker has produced the maximum number of frames.
+    #[error("Reached the maximum of {0} frames")]
+    TooManyFrames(usize),
+}
+
+/// A frame produced by the [`StackWalker`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame<Regs> {
+    /// The register values in this frame. For all frames but the first one,
+    /// the program counter is a return address.
+    pub regs: Regs,
+
+    /// The address that was used to look up the unwind info for this frame.
+    /// This is the program counter for the first frame, and the return
+    /// address minus one for the caller frames, so that calls at the very end
+    /// of a function are attributed to the right function.
+    pub lookup_address: u64,
+
+    /// How the registers of this frame were recovered.
+    pub unwound_by: UnwindMethod,
+}
+
+/// The architecture-specific register sets which the [`StackWalker`] can unwind.
+pub trait UnwindRegs: Clone + private::Sealed {
+    /// The program counter.
+    fn pc(&self) -> u64;
+
+    /// The stack pointer.
+    fn sp(&self) -> u64;
+
+    #[doc(hidden)]
+    const ARCH: Arch;
+
+    #[doc(hidden)]
+    fn unwind_fallback<M: ReadMemory>(
+        &self,
+        lookup_address: u64,
+        rule: FallbackRule,
+        memory: &mut M,
+    ) -> Result<Self, StackWalkError>;
+
+    #[doc(hidden)]
+    fn unwind<M: ReadMemory>(
+        &self,
+        lookup_address: u64,
+        base_address: u64,
+        function: &Function,
+        is_first_frame: bool,
+        memory: &mut M,
+    ) -> Result<(Self, UnwindMethod), StackWalkError>;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for super::RegsX86_64 {}
+    impl Sealed for super::RegsX86 {}
+    impl Sealed for super::RegsArm64 {}
+}
+
+/// The x86_64 registers which are restored by compact unwinding.
+///
+/// Callee-saved registers are `None` when their value is unknown.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegsX86_64 {
+    pub rip: u64,
+    pub rsp: u64,
+    pub rbp: u64,
+    pub rbx: Option<u64>,
+    pub r12: Option<u64>,
+    pub r13: Option<u64>,
+    pub r14: Option<u64>,
+    pub r15: Option<u64>,
+}
+
+impl RegsX86_64 {
+    fn set(&mut self, reg: RegisterNameX86_64, value: u64) {
+        match reg {
+            RegisterNameX86_64::Rbx => self.rbx = Some(value),
+            RegisterNameX86_64::R12 => self.r12 = Some(value),
+            RegisterNameX86_64::R13 => self.r13 = Some(value),
+            RegisterNameX86_64::R14 => self.r14 = Some(value),
+            RegisterNameX86_64::R15 => self.r15 = Some(value),
+            RegisterNameX86_64::Rbp => self.rbp = value,
+        }
+    }
+}
+
+/// The x86 registers which are restored by compact unwinding.
+///
+/// Callee-saved registers are `None` when their value is unknown.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegsX86 {
+    pub eip: u32,
+    pub esp: u32,
+    pub ebp: u32,
+    pub ebx: Option<u32>,
+    pub ecx: Option<u32>,
+    pub edx: Option<u32>,
+    pub edi: Option<u32>,
+    pub esi: Option<u32>,
+}
+
+impl RegsX86 {
+    fn set(&mut self, reg: RegisterNameX86, value: u32) {
+        match reg {
+            RegisterNameX86::Ebx => self.ebx = Some(value),
+            RegisterNameX86::Ecx => self.ecx = Some(value),
+            RegisterNameX86::Edx => self.edx = Some(value),
+            RegisterNameX86::Edi => self.edi = Some(value),
+            RegisterNameX86::Esi => self.esi = Some(value),
+            RegisterNameX86::Ebp => self.ebp = value,
+        }
+    }
+}
+
+/// The arm64 registers which are restored by compact unwinding.
+///
+/// `lr` is only known in the first frame. The callee-saved registers x19 to
+/// x28 are `None` when their value is unknown. The floating point registers
+/// d8 to d15 are not tracked.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegsArm64 {
+    pub pc: u64,
+    pub sp: u64,
+    pub fp: u64,
+    pub lr: Option<u64>,
+    /// x19 to x28.
+    pub x19_to_x28: [Option<u64>; 10],
+}
+
+fn read<M: ReadMemory>(memory: &mut M, address: u64) -> Result<u64, StackWalkError> {
+    memory
+        .read_memory(address)
+        .ok_or(StackWalkError::MemoryReadFailed(address))
+}
+
+fn read_u32<M: ReadMemory>(memory: &mut M, address: u64) -> Result<u32, StackWalkError> {
+    Ok(read(memory, address)? as u32)
+}
+
+impl UnwindRegs for RegsX86_64 {
+    fn pc(&self) -> u64 {
+        self.rip
+    }
+
+    fn sp(&self) -> u64 {
+        self.rsp
+    }
+
+    const ARCH: Arch = Arch::X86_64;
+
+    fn unwind_fallback<M: ReadMemory>(
+        &self,
+        lookup_address: u64,
+        rule: FallbackRule,
+        memory: &mut M,
+    ) -> Result<Self, StackWalkError> {
+        if rule != FallbackRule::FramePointer {
+            return Err(StackWalkError::NoUnwindInfo(lookup_address));
+        }
+        let rbp = self.rbp;
+        Ok(Self {
+            rip: read(memory, rbp.wrapping_add(8))?,
+            rsp: rbp.wrapping_add(16),
+            rbp: read(memory, rbp)?,
+            ..Default::default()
+        })
+    }
+
+    fn unwind<M: ReadMemory>(
+        &self,
+        lookup_address: u64,
+        base_address: u64,
+        function: &Function,
+        _is_first_frame: bool,
+        memory: &mut M,
+    ) -> Result<(Self, UnwindMethod), StackWalkError> {
+        let mut caller = self.clone();
+        let (cfa, saved_regs, method) = match OpcodeX86_64::parse(function.opcode) {
+            OpcodeX86_64::FrameBased {
+                stack_offset_in_bytes,
+                saved_regs,
+            } => {
+                let rbp = self.rbp;
+                // The registers were saved below rbp, starting with the last
+                // array element at the lowest address.
+                let mut location = rbp.wrapping_sub(stack_offset_in_bytes.into());
+                for reg in saved_regs.iter().rev() {
+                    if let Some(reg) = reg {
+                        caller.set(*reg, read(memory, location)?);
+                    }
+                    location = location.wrapping_add(8);
+                }
+                caller.rbp = read(memory, rbp)?;
+                caller.rip = read(memory, rbp.wrapping_add(8))?;
+                caller.rsp = rbp.wrapping_add(16);
+                return Ok((caller, UnwindMethod::FramePointer));
+            }
+            OpcodeX86_64::FramelessImmediate {
+                stack_size_in_bytes,
+                saved_regs,
+            } => (
+                self.rsp.wrapping_add(stack_size_in_bytes.into()),
+                saved_regs,
+                UnwindMethod::FramelessImmediate,
+            ),
+            OpcodeX86_64::FramelessIndirect {
+                immediate_offset_from_function_start,
+                stack_adjust_in_bytes,
+                saved_regs,
+            } => {
+                let function_start = base_address.wrapping_add(u64::from(function.start_address));
+                let sub_immediate = read_u32(
+                    memory,
+                    function_start.wrapping_add(u64::from(immediate_offset_from_function_start)),
+                )?;
+                let stack_size = u64::from(sub_immediate) + u64::from(stack_adjust_in_bytes);
+                (
+                    self.rsp.wrapping_add(stack_size),
+                    saved_regs,
+                    UnwindMethod::FramelessIndirect,
+                )
+            }
+            OpcodeX86_64::Null => return Err(StackWalkError::NoUnwindInfo(lookup_address)),
+            OpcodeX86_64::Dwarf { eh_frame_fde } => {
+                return Err(StackWalkError::NeedsDwarf {
+                    address: lookup_address,
+                    eh_frame_fde,
+                })
+            }
+            OpcodeX86_64::InvalidFrameless | OpcodeX86_64::UnrecognizedKind(_) => {
+                return Err(StackWalkError::InvalidOpcode {
+                    address: lookup_address,
+                    opcode: function.opcode,
+                })
+            }
+        };
+        // The return address is at CFA - 8, and the saved registers are below
+        // it, with the last register closest to the return address.
+        let mut location = cfa.wrapping_sub(16);
+        for reg in saved_regs.iter().rev().flatten() {
+            caller.set(*reg, read(memory, location)?);
+            location = location.wrapping_sub(8);
+        }
+        caller.rip = read(memory, cfa.wrapping_sub(8))?;
+        caller.rsp = cfa;
+        Ok((caller, method))
+    }
+}
+
+impl UnwindRegs for RegsX86 {
+    fn pc(&self) -> u64 {
+        self.eip.into()
+    }
+
+    fn sp(&self) -> u64 {
+        self.esp.into()
+    }
+
+    const ARCH: Arch = Arch::X86;
+
+    fn unwind_fallback<M: ReadMemory>(
+        &self,
+        lookup_address: u64,
+        rule: FallbackRule,
+        memory: &mut M,
+    ) -> Result<Self, StackWalkError> {
+        if rule != FallbackRule::FramePointer {
+            return Err(StackWalkError::NoUnwindInfo(lookup_address));
+        }
+        let ebp = self.ebp;
+        Ok(Self {
+            eip: read_u32(memory, ebp.wrapping_add(4).into())?,
+            esp: ebp.wrapping_add(8),
+            ebp: read_u32(memory, ebp.into())?,
+            ..Default::default()
+        })
+    }
+
+    fn unwind<M: ReadMemory>(
+        &self,
+        lookup_address: u64,
+        base_address: u64,
+        function: &Function,
+        _is_first_frame: bool,
+        memory: &mut M,
+    ) -> Result<(Self, UnwindMethod), StackWalkError> {
+        let mut caller = self.clone();
+        let (cfa, saved_regs, method) = match OpcodeX86::parse(function.opcode) {
+            OpcodeX86::FrameBased {
+                stack_offset_in_bytes,
+                saved_regs,
+            } => {
+                let ebp = self.ebp;
+                let mut location = ebp.wrapping_sub(stack_offset_in_bytes.into());
+                for reg in saved_regs.iter().rev() {
+                    if let Some(reg) = reg {
+                        caller.set(*reg, read_u32(memory, location.into())?);
+                    }
+                    location = location.wrapping_add(4);
+                }
+                caller.ebp = read_u32(memory, ebp.into())?;
+                caller.eip = read_u32(memory, ebp.wrapping_add(4).into())?;
+                caller.esp = ebp.wrapping_add(8);
+                return Ok((caller, UnwindMethod::FramePointer));
+            }
+            OpcodeX86::FramelessImmediate {
+                stack_size_in_bytes,
+                saved_regs,
+            } => (
+                self.esp.wrapping_add(stack_size_in_bytes.into()),
+                saved_regs,
+                UnwindMethod::FramelessImmediate,
+            ),
+            OpcodeX86::FramelessIndirect {
+                immediate_offset_from_function_start,
+                stack_adjust_in_bytes,
+                saved_regs,
+            } => {
+                let function_start = base_address.wrapping_add(u64::from(function.start_address));
+                let sub_immediate = read_u32(
+                    memory,
+                    function_start.wrapping_add(u64::from(immediate_offset_from_function_start)),
+                )?;
+                let stack_size = sub_immediate.wrapping_add(stack_adjust_in_bytes.into());
+                (
+                    self.esp.wrapping_add(stack_size),
+                    saved_regs,
+                    UnwindMethod::FramelessIndirect,
+                )
+            }
+            OpcodeX86::Null => return Err(StackWalkError::NoUnwindInfo(lookup_address)),
+            OpcodeX86::Dwarf { eh_frame_fde } => {
+                return Err(StackWalkError::NeedsDwarf {
+                    address: lookup_address,
+                    eh_frame_fde,
+                })
+            }
+            OpcodeX86::InvalidFrameless | OpcodeX86::UnrecognizedKind(_) => {
+                return Err(StackWalkError::InvalidOpcode {
+                    address: lookup_address,
+                    opcode: function.opcode,
+                })
+            }
+        };
+        let mut location = cfa.wrapping_sub(8);
+        for reg in saved_regs.iter().rev().flatten() {
+            caller.set(*reg, read_u32(memory, location.into())?);
+            location = location.wrapping_sub(4);
+        }
+        caller.eip = read_u32(memory, cfa.wrapping_sub(4).into())?;
+        caller.esp = cfa;
+        Ok((caller, method))
+    }
+}
+
+impl UnwindRegs for RegsArm64 {
+    fn pc(&self) -> u64 {
+        self.pc
+    }
+
+    fn sp(&self) -> u64 {
+        self.sp
+    }
+
+    const ARCH: Arch = Arch::Arm64;
+
+    fn unwind_fallback<M: ReadMemory>(
+        &self,
+        lookup_address: u64,
+        rule: FallbackRule,
+        memory: &mut M,
+    ) -> Result<Self, StackWalkError> {
+        match rule {
+            FallbackRule::FramePointer => {
+                let fp = self.fp;
+                Ok(Self {
+                    pc: read(memory, fp.wrapping_add(8))?,
+                    sp: fp.wrapping_add(16),
+                    fp: read(memory, fp)?,
+                    ..Default::default()
+                })
+            }
+            FallbackRule::ReturnAddressInLr => Ok(Self {
+                pc: self.lr.ok_or(StackWalkError::LrUnknown)?,
+                lr: None,
+                ..self.clone()
+            }),
+            FallbackRule::Stop => Err(StackWalkError::NoUnwindInfo(lookup_address)),
+        }
+    }
+
+    fn unwind<M: ReadMemory>(
+        &self,
+        lookup_address: u64,
+        _base_address: u64,
+        function: &Function,
+        is_first_frame: bool,
+        memory: &mut M,
+    ) -> Result<(Self, UnwindMethod), StackWalkError> {
+        let mut caller = self.clone();
+        let opcode = OpcodeArm64::parse(function.opcode);
+        match opcode {
+            OpcodeArm64::FrameBased { .. } => {
+                // fp points at the frame record, which is at CFA-16. The d8..d15
+                // pairs are not tracked.
+                let fp = self.fp;
+                let cfa = fp.wrapping_add(16);
+                for saved in opcode.saved_register_pairs() {
+                    let (first, second) = saved.pair.dwarf_numbers();
+                    if !(19..=28).contains(&first) {
+                        continue;
+                    }
+                    for (reg, offset) in
+                        [(first, saved.first_offset), (second, saved.second_offset)]
+                    {
+                        let location = cfa.wrapping_add_signed(offset);
+                        caller.x19_to_x28[usize::from(reg - 19)] = Some(read(memory, location)?);
+                    }
+                }
+                caller.fp = read(memory, fp)?;
+                caller.pc = read(memory, fp.wrapping_add(8))?;
+                caller.sp = fp.wrapping_add(16);
+                caller.lr = None;
+                Ok((caller, UnwindMethod::FramePointer))
+            }
+            OpcodeArm64::Frameless {
+                stack_size_in_bytes,
+            } => {
+                if !is_first_frame {
+                    return Err(StackWalkError::LrUnknown);
+                }
+                caller.pc = self.lr.ok_or(StackWalkError::LrUnknown)?;
+                caller.sp = self.sp.wrapping_add(stack_size_in_bytes.into());
+                caller.lr = None;
+                Ok((caller, UnwindMethod::FramelessLr))
+            }
+            OpcodeArm64::Null => Err(StackWalkError::NoUnwindInfo(lookup_address)),
+            OpcodeArm64::Dwarf { eh_frame_fde } => Err(StackWalkError::NeedsDwarf {
+                address: lookup_address,
+                eh_frame_fde,
+            }),
+            OpcodeArm64::UnrecognizedKind(_) => Err(StackWalkError::InvalidOpcode {
+                address: lookup_address,
+                opcode: function.opcode,
+            }),
+        }
+    }
+}
+
+/// Walks the stack, starting from a set of initial registers.
+///
+/// The architecture is determined by the register type: [`RegsX86_64`],
+/// [`RegsX86`] or [`RegsArm64`].
+pub struct StackWalker<L: FunctionLookup, M: ReadMemory, Regs: UnwindRegs> {
+    lookup: L,
+    memory: M,
+    state: WalkerState<Regs>,
+    frame_count: usize,
+    max_frames: usize,
+    fallback_policy: FallbackPolicy,
+}
+
+enum WalkerState<Regs> {
+    Initial(Regs),
+    Unwinding(Frame<Regs>),
+    Done,
+}
+
+impl<L: FunctionLookup, M: ReadMemory, Regs: UnwindRegs> StackWalker<L, M, Regs> {
+    /// The default value for [`StackWalker::set_max_frames`].
+    pub const DEFAULT_MAX_FRAMES: usize = 1024;
+
+    /// Create a walker which starts at the frame described by `regs`.
+    pub fn new(lookup: L, memory: M, regs: Regs) -> Self {
+        Self {
+            lookup,
+            memory,
+            state: WalkerState::Initial(regs),
+            frame_count: 0,
+            max_frames: Self::DEFAULT_MAX_FRAMES,
+            fallback_policy: FallbackPolicy::STOP,
+        }
+    }
+
+    /// Set the policy for frames without usable unwind info: addresses which
+    /// aren't covered, `Null` opcodes and unrecognized opcode kinds. Frames
+    /// which were unwound with a fallback rule have an
+    /// [`UnwindMethod::Fallback`] method. The default is
+    /// [`FallbackPolicy::STOP`].
+    pub fn set_fallback_policy(&mut self, policy: FallbackPolicy) {
+        self.fallback_policy = policy;
+    }
+
+    /// Limit the number of frames the walker produces before it stops with
+    /// [`StackWalkError::TooManyFrames`].
+    pub fn set_max_frames(&mut self, max_frames: usize) {
+        self.max_frames = max_frames;
+    }
+
+    /// Returns the next frame. The first call returns the frame for the
+    /// initial registers.
+    ///
+    /// Returns `Ok(None)` when the stack was walked successfully to the end,
+    /// i.e. when a return address of zero was found. After an error, the
+    /// walker is done and further calls return `Ok(None)`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<Frame<Regs>>, StackWalkError> {
+        let result = self.next_impl();
+        if !matches!(result, Ok(Some(_))) {
+            self.state = WalkerState::Done;
+        }
+        result
+    }
+
+    fn next_impl(&mut self) -> Result<Option<Frame<Regs>>, StackWalkError> {
+        let frame = match core::mem::replace(&mut self.state, WalkerState::Done) {
+            WalkerState::Initial(regs) => Frame {
+                lookup_address: regs.pc(),
+                regs,
+                unwound_by: UnwindMethod::InitialRegisters,
+            },
+            WalkerState::Unwinding(callee) => {
+                let is_first_frame = callee.unwound_by == UnwindMethod::InitialRegisters;
+                let lookup = self.lookup.lookup_function(callee.lookup_address)?;
+                let missing = match &lookup {
+                    Some((_, function)) => Regs::ARCH.missing_unwind_info(function.opcode),
+                    None => Some(MissingUnwindInfo::NotCovered),
+                };
+                let fallback = missing
+                    .map(|reason| {
+                        let rule = self
+                            .fallback_policy
+                            .rule(Regs::ARCH, reason, is_first_frame);
+                        (rule, reason)
+                    })
+                    .filter(|(rule, _)| *rule != FallbackRule::Stop);
+                let (caller_regs, unwound_by) = match (fallback, lookup) {
+                    (Some((rule, reason)), _) => {
+                        let caller_regs = callee.regs.unwind_fallback(
+                            callee.lookup_address,
+                            rule,
+                            &mut self.memory,
+                        )?;
+                        (caller_regs, UnwindMethod::Fallback { rule, reason })
+                    }
+                    // Without a fallback, unwinding a Null or unrecognized
+                    // opcode fails with the usual error.
+                    (None, Some((base_address, function))) => callee.regs.unwind(
+                        callee.lookup_address,
+                        base_address,
+                        &function,
+                        is_first_frame,
+                        &mut self.memory,
+                    )?,
+                    (None, None) => {
+                        return Err(StackWalkError::NoUnwindInfo(callee.lookup_address))
+                    }
+                };
+                if caller_regs.pc() == 0 {
+                    return Ok(None);
+                }
+                let (callee_sp, caller_sp) = (callee.regs.sp(), caller_regs.sp());
+                if caller_sp < callee_sp {
+                    return Err(StackWalkError::NonIncreasingSp {
+                        callee_sp,
+                        caller_sp,
+                    });
+                }
+                if caller_sp == callee_sp {
+                    // Only an arm64 leaf function without stack frame can leave
+                    // the stack pointer unchanged.
+                    let is_leaf = matches!(
+                        unwound_by,
+                        UnwindMethod::FramelessLr
+                            | UnwindMethod::Fallback {
+                                rule: FallbackRule::ReturnAddressInLr,
+                                ..
+                            }
+                    );
+                    if !is_leaf {
+                        return Err(StackWalkError::NonIncreasingSp {
+                            callee_sp,
+                            caller_sp,
+                        });
+                    }
+                    if caller_regs.pc() == callee.regs.pc() {
+                        return Err(StackWalkError::Loop(caller_regs.pc()));
+                    }
+                }
+                Frame {
+                    lookup_address: caller_regs.pc() - 1,
+                    regs: caller_regs,
+                    unwound_by,
+                }
+            }
+            WalkerState::Done => return Ok(None),
+        };
+        if self.frame_count == self.max_frames {
+            return Err(StackWalkError::TooManyFrames(self.max_frames));
+        }
+        self.frame_count += 1;
+        self.state = WalkerState::Unwinding(frame.clone());
+        Ok(Some(frame))
+    }
+}
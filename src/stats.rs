@@ -0,0 +1,252 @@
+use core::mem::size_of;
+
+use crate::opcodes::OpcodeBitfield;
+use crate::raw::consts::*;
+use crate::raw::*;
+use crate::reader::Reader;
+use crate::{Arch, Error, OpcodeSource, PageFunction, PageView, ReadError, UnwindInfo};
+
+/// Summary statistics about an `__unwind_info` section, as returned by
+/// [`UnwindInfo::stats`].
+///
+/// The opcode counts are keyed by the raw 4-bit opcode kind, because the
+/// meaning of each kind depends on the architecture. Use
+/// [`UnwindInfoStats::opcode_kinds`] to get the architecture-specific view.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UnwindInfoStats {
+    /// The number of function entries, indexed by opcode kind.
+    pub function_count_by_kind: [u32; 16],
+
+    /// The number of bytes of address space covered by the function entries,
+    /// indexed by opcode kind.
+    pub address_range_size_by_kind: [u64; 16],
+
+    /// The number of regular (uncompressed) second-level pages.
+    pub regular_page_count: u32,
+
+    /// The number of compressed second-level pages.
+    pub compressed_page_count: u32,
+
+    /// The number of function entries in regular pages. These entries store
+    /// their opcode inline.
+    pub regular_entry_count: u32,
+
+    /// The number of function entries in compressed pages whose opcode comes
+    /// from the global opcode palette.
+    pub global_palette_entry_count: u32,
+
+    /// The number of function entries in compressed pages whose opcode comes
+    /// from the page's local opcode palette.
+    pub local_palette_entry_count: u32,
+
+    /// The number of opcodes in the global palette.
+    pub global_opcode_count: u32,
+
+    /// The total number of opcodes in all local palettes.
+    pub local_opcode_count: u32,
+
+    /// The number of bytes used by each structure of the section.
+    pub sizes: UnwindInfoSizes,
+}
+
+/// The number of bytes used by the different structures of an `__unwind_info`
+/// section.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UnwindInfoSizes {
+    /// The size of the section header.
+    pub header: u32,
+
+    /// The size of the global opcode palette.
+    pub global_opcodes: u32,
+
+    /// The size of the personality function array.
+    pub personalities: u32,
+
+    /// The size of the first-level page entry array, including the sentinel
+    /// page entry.
+    pub page_entries: u32,
+
+    /// The size of the LSDA index array.
+    pub lsdas: u32,
+
+    /// The size of all regular second-level pages, including their entries.
+    pub regular_pages: u32,
+
+    /// The size of all compressed second-level pages, including their entries
+    /// and local opcode palettes.
+    pub compressed_pages: u32,
+
+    /// The size of the section, up to the end of the last structure that is
+    /// referenced from the header or from a page entry.
+    pub total: u32,
+}
+
+/// The number of function entries of each opcode kind, interpreted for a
+/// specific architecture.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpcodeKindCounts {
+    pub null: u32,
+    pub frame_based: u32,
+    /// On arm64, this counts all frameless entries.
+    pub frameless_immediate: u32,
+    /// Always zero on arm64.
+    pub frameless_indirect: u32,
+    pub dwarf: u32,
+    pub unrecognized: u32,
+}
+
+impl UnwindInfoStats {
+    /// The total number of function entries.
+    pub fn function_count(&self) -> u32 {
+        self.function_count_by_kind.iter().sum()
+    }
+
+    /// The number of second-level pages, not counting the sentinel page.
+    pub fn page_count(&self) -> u32 {
+        self.regular_page_count + self.compressed_page_count
+    }
+
+    /// The average number of function entries per second-level page.
+    pub fn average_functions_per_page(&self) -> f64 {
+        match self.page_count() {
+            0 => 0.0,
+            page_count => self.function_count() as f64 / page_count as f64,
+        }
+    }
+
+    /// The number of function entries of each opcode kind, interpreted for `arch`.
+    pub fn opcode_kinds(&self, arch: Arch) -> OpcodeKindCounts {
+        let mut counts = OpcodeKindCounts::default();
+        for (kind, count) in self.function_count_by_kind.iter().enumerate() {
+            let slot = match (arch, kind as u8) {
+                (_, OPCODE_KIND_NULL) => &mut counts.null,
+                (Arch::X86 | Arch::X86_64, OPCODE_KIND_X86_FRAMEBASED) => &mut counts.frame_based,
+                (Arch::X86 | Arch::X86_64, OPCODE_KIND_X86_FRAMELESS_IMMEDIATE) => {
+                    &mut counts.frameless_immediate
+                }
+                (Arch::X86 | Arch::X86_64, OPCODE_KIND_X86_FRAMELESS_INDIRECT) => {
+                    &mut counts.frameless_indirect
+                }
+                (Arch::X86 | Arch::X86_64, OPCODE_KIND_X86_DWARF) => &mut counts.dwarf,
+                (Arch::Arm64, OPCODE_KIND_ARM64_FRAMEBASED) => &mut counts.frame_based,
+                (Arch::Arm64, OPCODE_KIND_ARM64_FRAMELESS) => &mut counts.frameless_immediate,
+                (Arch::Arm64, OPCODE_KIND_ARM64_DWARF) => &mut counts.dwarf,
+                _ => &mut counts.unrecognized,
+            };
+            *slot += count;
+        }
+        counts
+    }
+
+    /// The fraction (0.0 to 1.0) of the covered address range whose unwind
+    /// information needs to be looked up in `__eh_frame`.
+    pub fn eh_frame_fraction(&self, arch: Arch) -> f64 {
+        let dwarf_kind = match arch {
+            Arch::X86 | Arch::X86_64 => OPCODE_KIND_X86_DWARF,
+            Arch::Arm64 => OPCODE_KIND_ARM64_DWARF,
+        };
+        let total: u64 = self.address_range_size_by_kind.iter().sum();
+        if total == 0 {
+            return 0.0;
+        }
+        self.address_range_size_by_kind[dwarf_kind as usize] as f64 / total as f64
+    }
+
+    fn add_function(&mut self, start_address: u32, end_address: u32, opcode: u32) {
+        let kind = OpcodeBitfield::new(opcode).kind() as usize;
+        self.function_count_by_kind[kind] += 1;
+        self.address_range_size_by_kind[kind] +=
+            u64::from(end_address.saturating_sub(start_address));
+    }
+}
+
+impl<R: Reader + ?Sized> UnwindInfo<'_, R> {
+    /// Walks all pages and function entries and collects statistics about the
+    /// contents and the size of this unwind info.
+    pub fn stats(&self) -> Result<UnwindInfoStats, Error> {
+        let data = self.data;
+        let header = *data
+            .read_at::<CompactUnwindInfoHeader>(0)
+            .ok_or(Error::read(ReadError::Header, 0))?;
+        let mut stats = UnwindInfoStats {
+            global_opcode_count: self.global_opcodes.len() as u32,
+            sizes: UnwindInfoSizes {
+                header: size_of::<CompactUnwindInfoHeader>() as u32,
+                global_opcodes: header.global_opcodes_len() * size_of::<Opcode>() as u32,
+                personalities: header
+                    .personalities_len()
+                    .saturating_mul(size_of::<U32>() as u32),
+                page_entries: header.pages_len() * size_of::<PageEntry>() as u32,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut section_end = [
+            (0, stats.sizes.header),
+            (header.global_opcodes_offset(), stats.sizes.global_opcodes),
+            (header.personalities_offset(), stats.sizes.personalities),
+            (header.pages_offset(), stats.sizes.page_entries),
+        ]
+        .iter()
+        .map(|&(offset, size)| u64::from(offset) + u64::from(size))
+        .max()
+        .unwrap_or(0);
+
+        if let (Some(first_page), Some(last_page)) =
+            (self.pages.first(data)?, self.pages.last(data)?)
+        {
+            stats.sizes.lsdas = last_page
+                .lsda_index_offset()
+                .saturating_sub(first_page.lsda_index_offset());
+            section_end = section_end.max(last_page.lsda_index_offset().into());
+        }
+
+        for page_index in 0..self.page_count() {
+            let Some(page) = self.page(page_index)? else {
+                break;
+            };
+            let page_offset = u64::from(self.pages.get(data, page_index)?.page_offset());
+            let size = match &page {
+                PageView::Regular(page) => {
+                    let size =
+                        size_of::<RegularPage>() + page.len() * size_of::<RegularFunctionEntry>();
+                    stats.regular_page_count += 1;
+                    stats.regular_entry_count += page.len() as u32;
+                    stats.sizes.regular_pages += size as u32;
+                    size
+                }
+                PageView::Compressed(page) => {
+                    let size = size_of::<CompressedPage>()
+                        + page.len() * size_of::<U32>()
+                        + page.local_opcodes_len() * size_of::<Opcode>();
+                    stats.compressed_page_count += 1;
+                    stats.local_opcode_count += page.local_opcodes_len() as u32;
+                    stats.sizes.compressed_pages += size as u32;
+                    size
+                }
+            };
+            section_end = section_end.max(page_offset + size as u64);
+            for i in 0..page.len() {
+                let Some(PageFunction {
+                    function,
+                    opcode_source,
+                }) = page.function_at(i)?
+                else {
+                    break;
+                };
+                match opcode_source {
+                    OpcodeSource::GlobalPalette { .. } => stats.global_palette_entry_count += 1,
+                    OpcodeSource::LocalPalette { .. } => stats.local_palette_entry_count += 1,
+                    OpcodeSource::Inline => {}
+                }
+                stats.add_function(
+                    function.start_address,
+                    function.end_address,
+                    function.opcode,
+                );
+            }
+        }
+        stats.sizes.total = section_end as u32;
+        Ok(stats)
+    }
+}
@@ -0,0 +1,19 @@
+/// A function symbol from the binary's symbol table.
+///
+/// The address is relative to the image's mach-O header, like the addresses
+/// in [`Function`](crate::Function).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Symbol<'s> {
+    pub address: u32,
+    pub name: &'s str,
+}
+
+/// Returns the symbols sorted by address, keeping only the first symbol for
+/// each address.
+#[cfg(feature = "alloc")]
+pub(crate) fn sorted_symbols<'s>(symbols: &[Symbol<'s>]) -> alloc::vec::Vec<Symbol<'s>> {
+    let mut symbols = symbols.to_vec();
+    symbols.sort_by_key(|symbol| symbol.address);
+    symbols.dedup_by_key(|symbol| symbol.address);
+    symbols
+}
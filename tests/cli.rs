@@ -0,0 +1,158 @@
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_macho-unwind-info"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_lookup_exit_codes() {
+    let path = "fixtures/arm64/fp/query-api.__unwind_info";
+    let output = run(&["--arch", "arm64", "lookup", path, "0x4000"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("0x4000: 0x00003f60..0x00004064 CFA=reg29+16"));
+
+    let output = run(&[
+        "--arch",
+        "arm64",
+        "--registers",
+        "assembly",
+        "lookup",
+        path,
+        "0x4000",
+    ]);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("0x4000: 0x00003f60..0x00004064 CFA=fp+16: fp=[CFA-16], lr=[CFA-8]"));
+
+    let output = run(&["--arch", "arm64", "lookup", "--explain", path, "0x4000"]);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("\n    page 0 (compressed), entry 38\n"));
+
+    let output = run(&["--arch", "arm64", "lookup", path, "0x4000", "0x10"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    // A raw section needs an explicit architecture.
+    let output = run(&["lookup", path, "0x4000"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_fat_binary_slices() {
+    let path = "fixtures/arm64/fp/libmozglue-fat.dylib";
+    assert_eq!(run(&["validate", path]).status.code(), Some(2));
+    let output = run(&["--arch", "arm64", "--format", "json", "validate", path]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains(r#""valid":true"#));
+}
+
+#[test]
+fn test_validate_malformed() {
+    let data = std::fs::read("fixtures/arm64/fp/query-api.__unwind_info").unwrap();
+    let path = std::env::temp_dir().join("macho-unwind-info-truncated.__unwind_info");
+    std::fs::write(&path, &data[..100]).unwrap();
+    let output = run(&["--arch", "arm64", "validate", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_coverage() {
+    // Merged symbols are reported, but don't fail the check.
+    let output = run(&["coverage", "fixtures/x86_64/nofp/libmozglue.dylib"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("_calloc: merged into the entry at 0x00006e60\n"));
+
+    let path = "fixtures/arm64/fp/query-api.__unwind_info";
+    let output = run(&["--arch", "arm64", "coverage", path]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_executable_symbols() {
+    // The __TEXT segment of an executable starts at 0x100000000, not at 0.
+    let path = "fixtures/arm64/fp/query-api";
+    let output = run(&["dump", path]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(
+        "\n0x00003f60 __ZN4core3ptr45drop_in_place$LT$serde_json..value..Value$GT$17h6dc13701a293b590E+0x0: CFA=reg29+16"
+    ));
+    assert!(!stdout.contains("(no symbol)"));
+
+    let output = run(&["coverage", path]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_coverage_executable() {
+    use object::{Object, ObjectSection};
+
+    // Move the end of the last page from the end of __text to 0x1d2d00, which
+    // leaves a gap at the end of the last function.
+    let mut data = std::fs::read("fixtures/arm64/fp/query-api").unwrap();
+    let file = object::File::parse(&*data).unwrap();
+    let section = file.section_by_name_bytes(b"__unwind_info").unwrap();
+    let section_offset = section.file_range().unwrap().0 as usize;
+    drop(file);
+    let read_u32 = |data: &[u8], offset: usize| {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize
+    };
+    let pages_offset = read_u32(&data, section_offset + 20);
+    let pages_len = read_u32(&data, section_offset + 24);
+    let sentinel = section_offset + pages_offset + (pages_len - 1) * 12;
+    assert_eq!(read_u32(&data, sentinel), 0x1d2d19);
+    data[sentinel..sentinel + 4].copy_from_slice(&0x1d2d00u32.to_le_bytes());
+
+    let path = std::env::temp_dir().join("macho-unwind-info-coverage-gap");
+    std::fs::write(&path, &data).unwrap();
+    let output = run(&["coverage", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("0x001d2d00-0x001d2d18: not covered by any page\n"));
+    assert!(stdout.contains(
+        "\n__ZN3std3sys4unix4weak18DlsymWeak$LT$F$GT$10initialize17had59083bdd5f61c7E: 0x001d2d00-0x001d2d18 not covered by any page\n"
+    ));
+    assert!(stdout.contains(
+        "\n__ZN41_$LT$T$u20$as$u20$serde..de..Expected$GT$3fmt17h3f219e7792fa18f7E: merged into the entry at 0x0000271c\n"
+    ));
+
+    // The unmodified binary has no gaps, only merged symbols, so it passes.
+    let output = run(&["coverage", "fixtures/arm64/fp/query-api"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("not covered"));
+    assert_eq!(stdout.lines().count(), 767);
+    assert!(stdout
+        .lines()
+        .all(|line| line.contains(": merged into the entry at 0x")));
+}
+
+#[test]
+fn test_closed_stdout() {
+    // Like `macho-unwind-info dump query-api | head -1`.
+    let mut child = Command::new(env!("CARGO_BIN_EXE_macho-unwind-info"))
+        .args(["dump", "fixtures/arm64/fp/query-api"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let mut line = [0; 10];
+    stdout.read_exact(&mut line).unwrap();
+    drop(stdout);
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+}
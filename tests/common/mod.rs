@@ -0,0 +1,23 @@
+// Helpers shared by the integration tests. Not every test binary uses all of them.
+#![allow(dead_code)]
+
+/// Builds an `__unwind_info` section with a single regular page, covering
+/// `functions[0].0..end_address`. Each function is an `(address, opcode)` pair.
+pub fn unwind_info(functions: &[(u32, u32)], end_address: u32) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let page_offset = 28 + 24;
+    for value in [1, 28, 0, 28, 0, 28, 2] {
+        buf.extend_from_slice(&u32::to_le_bytes(value));
+    }
+    for value in [functions[0].0, page_offset, 0, end_address, 0, 0] {
+        buf.extend_from_slice(&u32::to_le_bytes(value));
+    }
+    buf.extend_from_slice(&2u32.to_le_bytes());
+    buf.extend_from_slice(&8u16.to_le_bytes());
+    buf.extend_from_slice(&(functions.len() as u16).to_le_bytes());
+    for (address, opcode) in functions {
+        buf.extend_from_slice(&address.to_le_bytes());
+        buf.extend_from_slice(&opcode.to_le_bytes());
+    }
+    buf
+}
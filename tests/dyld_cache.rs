@@ -0,0 +1,189 @@
+use macho_unwind_info::dyld_cache::{DyldCache, DyldCacheError};
+use macho_unwind_info::{Arch, Function};
+
+const MAIN_UUID: [u8; 16] = [1; 16];
+const SUBCACHE_UUID: [u8; 16] = [2; 16];
+const IMAGE_A_ADDRESS: u64 = 0x1_8000_0000;
+const IMAGE_B_ADDRESS: u64 = 0x1_8000_4000;
+
+fn put(buf: &mut Vec<u8>, offset: usize, bytes: &[u8]) {
+    if buf.len() < offset + bytes.len() {
+        buf.resize(offset + bytes.len(), 0);
+    }
+    buf[offset..offset + bytes.len()].copy_from_slice(bytes);
+}
+
+fn put_u32(buf: &mut Vec<u8>, offset: usize, value: u32) {
+    put(buf, offset, &value.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, offset: usize, value: u64) {
+    put(buf, offset, &value.to_le_bytes());
+}
+
+fn name16(name: &str) -> [u8; 16] {
+    let mut padded = [0; 16];
+    padded[..name.len()].copy_from_slice(name.as_bytes());
+    padded
+}
+
+/// A cache file with a v2 header (0x1d0 bytes) and a single 0x1000 byte
+/// mapping at file offset 0x1000.
+fn cache_file(uuid: [u8; 16], mapping_address: u64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    put(&mut buf, 0, b"dyld_v1   arm64e");
+    put_u32(&mut buf, 16, 0x1d0); // mapping_offset
+    put_u32(&mut buf, 20, 1); // mapping_count
+    put(&mut buf, 88, &uuid);
+    put_u64(&mut buf, 0x1d0, mapping_address);
+    put_u64(&mut buf, 0x1d8, 0x1000); // size
+    put_u64(&mut buf, 0x1e0, 0x1000); // file_offset
+    buf
+}
+
+/// A small `__unwind_info` with two arm64 functions at 0x400 (frame-based) and
+/// 0x600 (frameless), ending at 0x800.
+fn unwind_info() -> Vec<u8> {
+    let mut buf = Vec::new();
+    put_u32(&mut buf, 0, 1); // version
+    put_u32(&mut buf, 4, 28); // global_opcodes_offset
+    put_u32(&mut buf, 8, 1); // global_opcodes_len
+    put_u32(&mut buf, 12, 32); // personalities_offset
+    put_u32(&mut buf, 16, 0); // personalities_len
+    put_u32(&mut buf, 20, 32); // pages_offset
+    put_u32(&mut buf, 24, 2); // pages_len
+    put_u32(&mut buf, 28, 0x0400_0000); // global opcode
+    put_u32(&mut buf, 32, 0x400); // page 0 first_address
+    put_u32(&mut buf, 36, 56); // page 0 page_offset
+    put_u32(&mut buf, 44, 0x800); // sentinel page first_address
+    put_u32(&mut buf, 56, 2); // regular page kind
+    put(&mut buf, 60, &8u16.to_le_bytes()); // functions_offset
+    put(&mut buf, 62, &2u16.to_le_bytes()); // functions_len
+    put_u32(&mut buf, 64, 0x400);
+    put_u32(&mut buf, 68, 0x0400_0000);
+    put_u32(&mut buf, 72, 0x600);
+    put_u32(&mut buf, 76, 0x0200_0000);
+    buf
+}
+
+/// Writes a 64-bit mach-O image at `offset` whose `__TEXT` segment starts at
+/// `address`, with `__unwind_info` at +0x200 and `__eh_frame` at +0x300.
+fn put_image(buf: &mut Vec<u8>, offset: usize, address: u64) {
+    put_u32(buf, offset, 0xfeed_facf);
+    put_u32(buf, offset + 16, 1); // ncmds
+    let segment = offset + 32;
+    put_u32(buf, segment, 0x19); // LC_SEGMENT_64
+    put_u32(buf, segment + 4, 72 + 2 * 80);
+    put(buf, segment + 8, &name16("__TEXT"));
+    put_u64(buf, segment + 24, address);
+    put_u64(buf, segment + 32, 0x1000);
+    put_u32(buf, segment + 64, 2); // nsects
+    for (i, (name, section_offset, size)) in [
+        ("__unwind_info", 0x200, unwind_info().len()),
+        ("__eh_frame", 0x300, 16),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let section = segment + 72 + i * 80;
+        put(buf, section, &name16(name));
+        put(buf, section + 16, &name16("__TEXT"));
+        put_u64(buf, section + 32, address + section_offset);
+        put_u64(buf, section + 40, size as u64);
+    }
+    put(buf, offset + 0x200, &unwind_info());
+    put(buf, offset + 0x300, &[0xee; 16]);
+}
+
+fn synthesized_cache() -> (Vec<u8>, Vec<u8>) {
+    let mut main = cache_file(MAIN_UUID, IMAGE_A_ADDRESS);
+    put_u32(&mut main, 392, 0x280); // sub_cache_array_offset
+    put_u32(&mut main, 396, 1); // sub_cache_array_count
+    put_u32(&mut main, 448, 0x200); // images_offset
+    put_u32(&mut main, 452, 2); // images_count
+    put_u64(&mut main, 136, 0x240); // images_text_offset
+    put_u64(&mut main, 144, 2); // images_text_count
+    for (i, (address, path_offset)) in [(IMAGE_A_ADDRESS, 0x2c0), (IMAGE_B_ADDRESS, 0x2e0)]
+        .into_iter()
+        .enumerate()
+    {
+        put_u64(&mut main, 0x200 + i * 32, address);
+        put_u32(&mut main, 0x200 + i * 32 + 24, path_offset);
+        put_u64(&mut main, 0x240 + i * 32 + 16, address);
+        put_u32(&mut main, 0x240 + i * 32 + 24, 0x1000);
+        put_u32(&mut main, 0x240 + i * 32 + 28, path_offset);
+    }
+    put(&mut main, 0x280, &SUBCACHE_UUID);
+    put_u64(&mut main, 0x290, IMAGE_B_ADDRESS - IMAGE_A_ADDRESS);
+    put(&mut main, 0x298, b".01\0");
+    put(&mut main, 0x2c0, b"/usr/lib/libA.dylib\0");
+    put(&mut main, 0x2e0, b"/usr/lib/libB.dylib\0");
+    put_image(&mut main, 0x1000, IMAGE_A_ADDRESS);
+    main.resize(0x2000, 0);
+
+    let mut subcache = cache_file(SUBCACHE_UUID, IMAGE_B_ADDRESS);
+    put_image(&mut subcache, 0x1000, IMAGE_B_ADDRESS);
+    subcache.resize(0x2000, 0);
+    (main, subcache)
+}
+
+#[test]
+fn test_subcaches() {
+    let (main, subcache) = synthesized_cache();
+    assert_eq!(DyldCache::subcache_suffixes(&main).unwrap(), vec![".01"]);
+    assert_eq!(
+        DyldCache::parse(&main, &[]).err(),
+        Some(DyldCacheError::SubCacheCount {
+            expected: 1,
+            actual: 0
+        })
+    );
+    assert_eq!(
+        DyldCache::parse(&main, &[&main]).err(),
+        Some(DyldCacheError::SubCacheUuidMismatch(0))
+    );
+
+    let cache = DyldCache::parse(&main, &[&subcache]).unwrap();
+    assert_eq!(cache.arch(), Some(Arch::Arm64));
+    let paths: Vec<&str> = cache.images().map(|image| image.path().unwrap()).collect();
+    assert_eq!(paths, ["/usr/lib/libA.dylib", "/usr/lib/libB.dylib"]);
+}
+
+#[test]
+fn test_image_sections_and_lookup() {
+    let (main, subcache) = synthesized_cache();
+    let cache = DyldCache::parse(&main, &[&subcache]).unwrap();
+
+    // Image B's mach-O header and sections live in the sub-cache.
+    let address = IMAGE_B_ADDRESS + 0x650;
+    let image = cache.image_for_address(address).unwrap().unwrap();
+    assert_eq!(image.index(), 1);
+    let sections = image.sections().unwrap();
+    assert_eq!(
+        sections.text_segment,
+        IMAGE_B_ADDRESS..IMAGE_B_ADDRESS + 0x1000
+    );
+    assert_eq!(sections.eh_frame.unwrap().address, IMAGE_B_ADDRESS + 0x300);
+    assert_eq!(sections.eh_frame.unwrap().data, &[0xee; 16]);
+
+    let unwind_info = image.unwind_info().unwrap().unwrap();
+    let relative_address = (address - image.header_address()) as u32;
+    assert_eq!(
+        unwind_info.lookup(relative_address).unwrap(),
+        Some(Function {
+            start_address: 0x600,
+            end_address: 0x800,
+            opcode: 0x0200_0000,
+        })
+    );
+
+    assert_eq!(
+        cache
+            .image_for_address(IMAGE_A_ADDRESS + 0x10)
+            .unwrap()
+            .unwrap()
+            .index(),
+        0
+    );
+    assert!(cache.image_for_address(0x1000).unwrap().is_none());
+}
@@ -0,0 +1,1258 @@
+use std::fs::File;
+use std::io::Read;
+
+use macho_unwind_info::{Arch, UnwindInfo};
+use object::{Object, ObjectSection};
+
+mod common;
+
+fn read_fixture(path: &str) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut file = File::open(path).unwrap();
+    file.read_to_end(&mut data).unwrap();
+    data
+}
+
+fn unwind_info_section(data: &[u8]) -> &[u8] {
+    let file = object::File::parse(data).unwrap();
+    let section = file.section_by_name_bytes(b"__unwind_info").unwrap();
+    section.data().unwrap()
+}
+
+#[test]
+fn test_stats_frame_pointers() {
+    let fp_data = read_fixture("fixtures/x86_64/fp/libmozglue.dylib");
+    let fp_info = UnwindInfo::parse(unwind_info_section(&fp_data)).unwrap();
+    let fp_stats = fp_info.stats().unwrap();
+
+    let nofp_data = read_fixture("fixtures/x86_64/nofp/libmozglue.dylib");
+    let nofp_info = UnwindInfo::parse(unwind_info_section(&nofp_data)).unwrap();
+    let nofp_stats = nofp_info.stats().unwrap();
+
+    let mut function_count = 0;
+    let mut function_iter = fp_info.functions();
+    while function_iter.next().unwrap().is_some() {
+        function_count += 1;
+    }
+    assert_eq!(fp_stats.function_count(), function_count);
+
+    let fp_kinds = fp_stats.opcode_kinds(Arch::X86_64);
+    let nofp_kinds = nofp_stats.opcode_kinds(Arch::X86_64);
+    assert!(fp_kinds.frame_based > nofp_kinds.frame_based);
+    assert!(nofp_kinds.frameless_immediate > fp_kinds.frameless_immediate);
+    assert_eq!(fp_kinds.unrecognized, 0);
+
+    let range = fp_info.address_range();
+    let covered: u64 = fp_stats.address_range_size_by_kind.iter().sum();
+    assert_eq!(covered, u64::from(range.end - range.start));
+    assert_eq!(
+        fp_stats.regular_entry_count
+            + fp_stats.global_palette_entry_count
+            + fp_stats.local_palette_entry_count,
+        function_count
+    );
+}
+
+#[test]
+fn test_stats_arm64_nofp_needs_eh_frame() {
+    let data = read_fixture("fixtures/arm64/nofp/rustup.__unwind_info");
+    let stats = UnwindInfo::parse(&data).unwrap().stats().unwrap();
+    assert!(stats.eh_frame_fraction(Arch::Arm64) > 0.5);
+    assert_eq!(stats.sizes.total as usize, data.len());
+}
+
+#[test]
+fn test_stats_malformed() {
+    use macho_unwind_info::{ErrorKind, ReadError};
+
+    // A compressed page at offset 52 whose second entry lies past 0xffffffff.
+    let mut data = Vec::new();
+    let header = [1, 28, 0, 28, 0, 28, 2];
+    let page_entries = [0xffff_ff00, 52, 0, 0xffff_ffff, 0, 0];
+    for value in header.into_iter().chain(page_entries).chain([3]) {
+        data.extend_from_slice(&u32::to_le_bytes(value));
+    }
+    for value in [12u16, 2, 20, 1] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    for value in [0, 0x200, 0x0200_0000] {
+        data.extend_from_slice(&u32::to_le_bytes(value));
+    }
+    let info = UnwindInfo::parse(&data).unwrap();
+    let err = info.stats().unwrap_err();
+    assert_eq!(err, ErrorKind::AddressOverflow);
+    assert_eq!(err.context().page_index, Some(0));
+    assert_eq!(err.context().entry_index, Some(0));
+    assert_eq!(
+        info.functions().next().unwrap_err(),
+        ErrorKind::AddressOverflow
+    );
+    assert_eq!(
+        info.lookup(0xffff_ff80).unwrap_err(),
+        ErrorKind::AddressOverflow
+    );
+
+    // Cut the section off in the middle of the first page's entries.
+    let data = read_fixture("fixtures/arm64/fp/query-api.__unwind_info");
+    let info = UnwindInfo::parse(&data[..0x2270 + 0x40]).unwrap();
+    assert_eq!(
+        info.stats().unwrap_err(),
+        ErrorKind::ReadError(ReadError::CompressedPageFunctions)
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_read_seek_reader_matches_slice() {
+    use macho_unwind_info::reader::ReadSeekReader;
+
+    let path = "fixtures/arm64/fp/query-api.__unwind_info";
+    let data = read_fixture(path);
+    let slice_info = UnwindInfo::parse(&data).unwrap();
+    let reader = ReadSeekReader::new(File::open(path).unwrap(), 0, data.len() as u64);
+    let reader_info = UnwindInfo::from_reader(&reader).unwrap();
+    assert_eq!(slice_info.address_range(), reader_info.address_range());
+
+    let mut slice_iter = slice_info.functions();
+    let mut reader_iter = reader_info.functions();
+    while let Some(function) = slice_iter.next().unwrap() {
+        assert_eq!(reader_iter.next().unwrap(), Some(function.clone()));
+        for pc in [function.start_address, function.end_address - 1] {
+            assert_eq!(reader_info.lookup(pc).unwrap(), Some(function.clone()));
+        }
+    }
+    assert_eq!(reader_iter.next().unwrap(), None);
+}
+
+#[test]
+fn test_fn_reader_lookup_touches_one_page() {
+    use macho_unwind_info::reader::FnReader;
+    use std::cell::RefCell;
+
+    let data = read_fixture("fixtures/x86_64/fp/libmozglue.dylib");
+    let data = unwind_info_section(&data);
+    let read_offsets = RefCell::new(Vec::new());
+    let reader = FnReader(|offset: u64, buf: &mut [u8]| {
+        read_offsets.borrow_mut().push(offset);
+        let Some(bytes) = data.get(offset as usize..offset as usize + buf.len()) else {
+            return false;
+        };
+        buf.copy_from_slice(bytes);
+        true
+    });
+    let info = UnwindInfo::from_reader(&reader).unwrap();
+    let pages_offset = u32::from_le_bytes(data[20..24].try_into().unwrap()) as u64;
+    let pages_len = u32::from_le_bytes(data[24..28].try_into().unwrap()) as u64;
+    let pages_end = pages_offset + pages_len * 12;
+
+    let range = info.address_range();
+    for pc in (range.start..range.end).step_by(0x1357) {
+        read_offsets.borrow_mut().clear();
+        assert!(info.lookup(pc).unwrap().is_some());
+        let page_reads: Vec<u64> = read_offsets
+            .borrow()
+            .iter()
+            .copied()
+            .filter(|offset| !(pages_offset..pages_end).contains(offset))
+            .collect();
+        let min = page_reads.iter().min().unwrap();
+        let max = page_reads.iter().max().unwrap();
+        assert!(max - min < 4096);
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_symbolized_report() {
+    use macho_unwind_info::{ReportWarning, Symbol};
+
+    // _a and _b were merged into one entry. _c has a cold part at 0x1300,
+    // which is separated from the hot part by Null padding.
+    let data = common::unwind_info(
+        &[
+            (0x1000, 0x0400_0000),
+            (0x1200, 0x0200_0000),
+            (0x1280, 0),
+            (0x1300, 0x0400_0000),
+        ],
+        0x1400,
+    );
+    let info = UnwindInfo::parse(&data).unwrap();
+    let symbols = [
+        Symbol {
+            address: 0x1200,
+            name: "_c",
+        },
+        Symbol {
+            address: 0x1000,
+            name: "_a",
+        },
+        Symbol {
+            address: 0x1100,
+            name: "_b",
+        },
+        Symbol {
+            address: 0x1100,
+            name: "_b_alias",
+        },
+    ];
+    let report = info.symbolized_report(Arch::Arm64, &symbols).unwrap();
+    let entries = &report.entries;
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0].symbol, Some(symbols[1]));
+    assert_eq!(
+        entries[0].warnings,
+        [ReportWarning::SpansMultipleSymbols(vec![symbols[2]])]
+    );
+    assert_eq!(
+        entries[1].warnings,
+        [ReportWarning::SymbolSplitAcrossEntries { index: 0, count: 2 }]
+    );
+    assert!(entries[2].warnings.is_empty());
+    assert_eq!(entries[3].symbol, Some(symbols[0]));
+    assert_eq!(entries[3].offset_from_symbol, 0x100);
+    assert_eq!(
+        entries[3].warnings,
+        [ReportWarning::SymbolSplitAcrossEntries { index: 1, count: 2 }]
+    );
+
+    let text = report.to_string();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(
+        lines[0],
+        "0x00001000 _a+0x0: CFA=reg29+16: reg29=[CFA-16], reg30=[CFA-8]"
+    );
+    assert_eq!(lines[1], "    warning: entry also covers _b (0x00001100)");
+    assert_eq!(
+        lines.last().unwrap(),
+        &"    warning: symbol is split across 2 entries, this is entry 2"
+    );
+}
+
+/// The function symbols of a mach-O file, relative to its __TEXT segment.
+#[cfg(feature = "alloc")]
+fn text_symbols(data: &[u8]) -> Vec<macho_unwind_info::Symbol<'_>> {
+    use object::{ObjectSegment, ObjectSymbol, SymbolKind};
+
+    let file = object::File::parse(data).unwrap();
+    let text_address = file
+        .segments()
+        .find(|segment| segment.name() == Ok(Some("__TEXT")))
+        .unwrap()
+        .address();
+    file.symbols()
+        .filter(|symbol| symbol.kind() == SymbolKind::Text && symbol.is_definition())
+        .map(|symbol| macho_unwind_info::Symbol {
+            address: u32::try_from(symbol.address() - text_address).unwrap(),
+            name: symbol.name().unwrap(),
+        })
+        .collect()
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_symbolized_report_executable() {
+    use macho_unwind_info::ReportWarning;
+
+    // Symbol addresses are relative to the __TEXT segment, which starts at
+    // 0x100000000 in an executable.
+    let data = read_fixture("fixtures/arm64/fp/query-api");
+    let symbols = text_symbols(&data);
+    assert_eq!(symbols.len(), 3330);
+    let info = UnwindInfo::parse(unwind_info_section(&data)).unwrap();
+    let report = info.symbolized_report(Arch::Arm64, &symbols).unwrap();
+    assert!(report.entries.iter().all(|entry| entry.symbol.is_some()));
+    let entry = report
+        .entries
+        .iter()
+        .find(|entry| entry.function.start_address == 0x3f60)
+        .unwrap();
+    assert_eq!(
+        entry.symbol.unwrap().name,
+        "__ZN4core3ptr45drop_in_place$LT$serde_json..value..Value$GT$17h6dc13701a293b590E"
+    );
+    assert_eq!(entry.offset_from_symbol, 0);
+    // The first entry covers five more symbols which the linker merged into it.
+    match &report.entries[0].warnings[..] {
+        [ReportWarning::SpansMultipleSymbols(merged)] => assert_eq!(merged.len(), 5),
+        warnings => panic!("unexpected warnings {:?}", warnings),
+    }
+}
+
+#[test]
+fn test_register_names() {
+    use macho_unwind_info::opcodes::RegisterNames;
+    use macho_unwind_info::Arch;
+
+    // x19/x20 right below the frame record, then d14/d15; the d registers
+    // are numbered from 64.
+    let opcode = 0x0400_0101;
+    assert_eq!(
+        Arch::Arm64.display_opcode(opcode).to_string(),
+        "CFA=reg29+16: reg29=[CFA-16], reg30=[CFA-8], reg19=[CFA-24], reg20=[CFA-32], reg78=[CFA-40], reg79=[CFA-48]"
+    );
+    assert_eq!(
+        Arch::Arm64
+            .display_opcode_with(opcode, RegisterNames::Assembly)
+            .to_string(),
+        "CFA=fp+16: fp=[CFA-16], lr=[CFA-8], x19=[CFA-24], x20=[CFA-32], d14=[CFA-40], d15=[CFA-48]"
+    );
+
+    // Frame-based with rbx saved at rbp-8.
+    let opcode = 0x0101_0001;
+    assert_eq!(
+        Arch::X86_64.display_opcode(opcode).to_string(),
+        "CFA=reg6+16: reg6=[CFA-16], reg16=[CFA-8], reg3=[CFA-24]"
+    );
+    assert_eq!(
+        Arch::X86_64
+            .display_opcode_with(opcode, RegisterNames::Assembly)
+            .to_string(),
+        "CFA=rbp+16: rbp=[CFA-16], rip=[CFA-8], rbx=[CFA-24]"
+    );
+
+    // Darwin's i386 eh_frame numbering has ebp at 4 and esp at 5.
+    assert_eq!(
+        Arch::X86.display_opcode(opcode).to_string(),
+        "CFA=reg4+8: reg4=[CFA-8], reg8=[CFA-4], reg3=[CFA-12]"
+    );
+    assert_eq!(
+        Arch::X86
+            .display_opcode_with(0x0202_0000, RegisterNames::Assembly)
+            .to_string(),
+        "CFA=esp+8: eip=[CFA-4]"
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_coverage() {
+    use macho_unwind_info::{CoverageIssue, Symbol};
+
+    // _b is merged into _a's entry, _c has no unwind info, and the text
+    // section extends past the covered range on both sides.
+    let data = common::unwind_info(
+        &[(0x1000, 0x0400_0000), (0x1200, 0), (0x1300, 0x0200_0000)],
+        0x1400,
+    );
+    let info = UnwindInfo::parse(&data).unwrap();
+    let symbols = [
+        Symbol {
+            address: 0xf00,
+            name: "_start",
+        },
+        Symbol {
+            address: 0x1000,
+            name: "_a",
+        },
+        Symbol {
+            address: 0x1100,
+            name: "_b",
+        },
+        Symbol {
+            address: 0x1200,
+            name: "_c",
+        },
+        Symbol {
+            address: 0x1300,
+            name: "_d",
+        },
+        Symbol {
+            address: 0x1380,
+            name: "_e",
+        },
+        Symbol {
+            address: 0x2000,
+            name: "_not_in_text",
+        },
+    ];
+    let report = info.coverage(0xf00..0x1480, &symbols).unwrap();
+    assert_eq!(report.uncovered_ranges, [0xf00..0x1000, 0x1400..0x1480]);
+    let issues: Vec<_> = report
+        .symbols
+        .iter()
+        .map(|symbol| (symbol.symbol.name, symbol.issues.clone()))
+        .collect();
+    assert_eq!(
+        issues,
+        [
+            ("_start", vec![CoverageIssue::Uncovered(0xf00..0x1000)]),
+            (
+                "_b",
+                vec![CoverageIssue::MergedIntoNeighbor {
+                    entry_start_address: 0x1000
+                }]
+            ),
+            ("_c", vec![CoverageIssue::NullOpcode(0x1200..0x1300)]),
+            (
+                "_e",
+                vec![
+                    CoverageIssue::Uncovered(0x1400..0x1480),
+                    CoverageIssue::MergedIntoNeighbor {
+                        entry_start_address: 0x1300
+                    }
+                ]
+            ),
+        ]
+    );
+    assert!(report
+        .to_string()
+        .contains("_c: 0x00001200-0x00001300 has a null opcode\n"));
+    assert!(report.has_uncovered());
+
+    // Merged symbols alone don't make the code uncovered.
+    let report = info.coverage(0x1000..0x1200, &symbols).unwrap();
+    assert_eq!(report.symbols.len(), 1);
+    assert!(!report.is_empty());
+    assert!(!report.has_uncovered());
+
+    // A healthy binary has merged symbols, but no uncovered code.
+    let data = read_fixture("fixtures/arm64/fp/query-api");
+    let info = UnwindInfo::parse(unwind_info_section(&data)).unwrap();
+    let report = info
+        .coverage(0xb64..0x1d2d18, &text_symbols(&data))
+        .unwrap();
+    assert_eq!(report.symbols.len(), 767);
+    assert!(!report.has_uncovered());
+}
+
+#[test]
+fn test_function_starts() {
+    use macho_unwind_info::FunctionStarts;
+
+    let data = [0x80, 0x20, 0x80, 0x02, 0x80, 0x02, 0x80, 0x02, 0x00, 0x10];
+    let starts: Vec<u32> = FunctionStarts::new(&data).collect();
+    assert_eq!(starts, [0x1000, 0x1100, 0x1200, 0x1300]);
+    assert_eq!(FunctionStarts::new(&[0x80, 0x80]).count(), 0);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_function_ranges() {
+    use macho_unwind_info::{Function, FunctionRange};
+
+    // 0xf00 is padding before the first function. 0x1000 and 0x1100 were
+    // merged into one entry. The function at 0x1200 changes its opcode at
+    // 0x1280. 0x1380 is the cold part of the function at 0x1100, placed after
+    // the other functions.
+    let data = common::unwind_info(
+        &[
+            (0xf00, 0),
+            (0x1000, 0x0400_0000),
+            (0x1200, 0x0400_0001),
+            (0x1280, 0x0200_0000),
+            (0x1300, 0x0400_0000),
+            (0x1380, 0x8200_0000),
+        ],
+        0x1400,
+    );
+    let info = UnwindInfo::parse(&data).unwrap();
+    let first = info.lookup(0x1000).unwrap().unwrap();
+    assert!(first.is_function_start() && !first.is_not_function_start());
+    let cold = info.lookup(0x1380).unwrap().unwrap();
+    assert!(cold.is_not_function_start() && !cold.is_function_start());
+
+    let piece = |start_address, end_address, opcode| Function {
+        start_address,
+        end_address,
+        opcode,
+    };
+    let range = |pieces: Vec<Function>| FunctionRange {
+        start_address: pieces[0].start_address,
+        end_address: pieces.last().unwrap().end_address,
+        pieces,
+    };
+    let ranges = info
+        .function_ranges(&[0x1000, 0x1100, 0x1200, 0x1300, 0x1380])
+        .unwrap();
+    assert_eq!(
+        ranges,
+        [
+            range(vec![piece(0x1000, 0x1100, 0x0400_0000)]),
+            range(vec![piece(0x1100, 0x1200, 0x0400_0000)]),
+            range(vec![
+                piece(0x1200, 0x1280, 0x0400_0001),
+                piece(0x1280, 0x1300, 0x0200_0000),
+            ]),
+            range(vec![piece(0x1300, 0x1380, 0x0400_0000)]),
+            // The cold part is not grouped with its hot part.
+            range(vec![piece(0x1380, 0x1400, 0x8200_0000)]),
+        ]
+    );
+    assert_eq!(info.function_ranges(&[]).unwrap(), []);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_lint_fixtures() {
+    use macho_unwind_info::opcodes::{OpcodeArm64, OpcodeX86_64};
+    use macho_unwind_info::OpcodeWarning;
+
+    for path in [
+        "fixtures/x86_64/fp/libmozglue.dylib",
+        "fixtures/x86_64/nofp/libmozglue.dylib",
+    ] {
+        let data = read_fixture(path);
+        let info = UnwindInfo::parse(unwind_info_section(&data)).unwrap();
+        assert_eq!(info.lint(Arch::X86_64).unwrap(), [], "{}", path);
+    }
+
+    // This binary has a few frame-based entries with bit 9 set.
+    let data = read_fixture("fixtures/arm64/fp/query-api");
+    let info = UnwindInfo::parse(unwind_info_section(&data)).unwrap();
+    let lints = info.lint(Arch::Arm64).unwrap();
+    assert_eq!(lints.len(), 4);
+    for lint in lints {
+        assert!(matches!(
+            OpcodeArm64::parse(lint.function.opcode),
+            OpcodeArm64::FrameBased { .. }
+        ));
+        assert_eq!(lint.warnings, [OpcodeWarning::UnusedBitsSet(0x200)]);
+    }
+
+    // rbx in slot 1 and 2, with a stack offset of 2 words, and bit 15 set.
+    assert_eq!(
+        OpcodeX86_64::lint(0x0102_8048),
+        [
+            OpcodeWarning::SavedRegisterBeyondStackOffset {
+                slot: 2,
+                stack_offset_in_words: 2
+            },
+            OpcodeWarning::DuplicateSavedRegister(1),
+            OpcodeWarning::UnusedBitsSet(0x8000),
+        ]
+    );
+    // Frameless immediate with 7 registers.
+    assert_eq!(
+        OpcodeX86_64::lint(0x0202_1c00),
+        [OpcodeWarning::InvalidRegisterCount(7)]
+    );
+    // Frameless immediate with 2 registers, permutation 30 (max is 29), and
+    // a stack size of 16 bytes.
+    assert_eq!(
+        OpcodeX86_64::lint(0x0202_081e),
+        [
+            OpcodeWarning::InvalidRegisterPermutation {
+                count: 2,
+                permutation: 30
+            },
+            OpcodeWarning::StackSizeTooSmall {
+                stack_size_in_bytes: 16,
+                register_count: 2
+            },
+        ]
+    );
+}
+
+#[cfg(feature = "eh-frame")]
+fn eh_frame_section(data: &[u8]) -> macho_unwind_info::EhFrame<'_> {
+    use object::ObjectSegment;
+
+    let file = object::File::parse(data).unwrap();
+    let text_address = file
+        .segments()
+        .find(|segment| segment.name() == Ok(Some("__TEXT")))
+        .unwrap()
+        .address();
+    let section = file.section_by_name_bytes(b"__eh_frame").unwrap();
+    macho_unwind_info::EhFrame::parse(section.data().unwrap(), section.address() - text_address)
+}
+
+#[cfg(feature = "eh-frame")]
+#[test]
+fn test_check_eh_frame() {
+    use macho_unwind_info::{CfaRule, CfiMismatch, FunctionCfiMismatch};
+
+    for (path, arch) in [
+        ("fixtures/x86_64/nofp/libmozglue.dylib", Arch::X86_64),
+        ("fixtures/arm64/fp/query-api", Arch::Arm64),
+    ] {
+        let data = read_fixture(path);
+        let eh_frame = eh_frame_section(&data);
+        assert!(eh_frame.is_complete());
+        let info = UnwindInfo::parse(unwind_info_section(&data)).unwrap();
+        assert_eq!(
+            info.check_eh_frame(arch, &eh_frame).unwrap(),
+            [],
+            "{}",
+            path
+        );
+    }
+
+    // The function at 0xfa0 has a frameless opcode and an FDE at offset
+    // 0x18, which covers 0xfa0..0x1015: CFA=rsp+32, with rbx, r14 and rbp
+    // saved.
+    let data = read_fixture("fixtures/x86_64/nofp/libmozglue.dylib");
+    let eh_frame = eh_frame_section(&data);
+    let info = UnwindInfo::parse(unwind_info_section(&data)).unwrap();
+    let opcode = info.lookup(0xfa0).unwrap().unwrap().opcode;
+    let wrong_stack_size = (opcode & !0xff_0000) | (3 << 16);
+    let section = common::unwind_info(
+        &[
+            (0xfa0, wrong_stack_size),
+            (0x1020, 0x0400_0018),
+            (0x1030, 0x0400_0019),
+        ],
+        0x1040,
+    );
+    let info = UnwindInfo::parse(&section).unwrap();
+    let mismatches = info.check_eh_frame(Arch::X86_64, &eh_frame).unwrap();
+    let function = |start_address, end_address, opcode| macho_unwind_info::Function {
+        start_address,
+        end_address,
+        opcode,
+    };
+    assert_eq!(
+        mismatches,
+        [
+            FunctionCfiMismatch {
+                function: function(0xfa0, 0x1020, wrong_stack_size),
+                eh_frame_fde: 0x18,
+                mismatches: vec![CfiMismatch::Cfa {
+                    compact: CfaRule::RegisterOffset {
+                        register: 7,
+                        offset: 24
+                    },
+                    eh_frame: CfaRule::RegisterOffset {
+                        register: 7,
+                        offset: 32
+                    },
+                }],
+            },
+            FunctionCfiMismatch {
+                function: function(0x1020, 0x1030, 0x0400_0018),
+                eh_frame_fde: 0x18,
+                mismatches: vec![CfiMismatch::FdeDoesNotCoverFunction {
+                    fde_address_range: 0xfa0..0x1015
+                }],
+            },
+            FunctionCfiMismatch {
+                function: function(0x1030, 0x1040, 0x0400_0019),
+                eh_frame_fde: 0x19,
+                mismatches: vec![CfiMismatch::InvalidFdeOffset],
+            },
+        ]
+    );
+    assert_eq!(
+        mismatches[0].mismatches[0].to_string(),
+        "CFA is reg7+24 in the opcode, reg7+32 in the FDE"
+    );
+}
+
+#[cfg(feature = "eh-frame")]
+#[test]
+fn test_compactify() {
+    use macho_unwind_info::opcodes::{OpcodeX86_64, RegisterNameX86_64};
+    use macho_unwind_info::{CfaRule, CfiRow, NotCompactable, RegisterRule};
+
+    // This library was linked without converting FDEs to compact opcodes, so
+    // most functions use Dwarf opcodes even though they don't need to.
+    let data = read_fixture("fixtures/x86_64/nofp/libmozglue.dylib");
+    let eh_frame = eh_frame_section(&data);
+    let info = UnwindInfo::parse(unwind_info_section(&data)).unwrap();
+    let mut functions = info.functions();
+    let mut dwarf_count = 0;
+    while let Some(function) = functions.next().unwrap() {
+        let Some(fde) = eh_frame.fde_for_address(function.start_address.into()) else {
+            continue;
+        };
+        let compact = eh_frame.compactify(Arch::X86_64, fde);
+        match OpcodeX86_64::parse(function.opcode) {
+            OpcodeX86_64::Dwarf { eh_frame_fde } => {
+                assert_eq!(eh_frame_fde, fde);
+                assert!(compact.is_ok(), "0x{:x}", function.start_address);
+                dwarf_count += 1;
+            }
+            _ => assert_eq!(compact, Ok(function.opcode & 0x0fff_ffff)),
+        }
+    }
+    assert_eq!(dwarf_count, 179);
+
+    use RegisterNameX86_64::*;
+    let fde = eh_frame.fde_for_address(0x1030).unwrap();
+    assert_eq!(
+        OpcodeX86_64::parse(eh_frame.compactify(Arch::X86_64, fde).unwrap()),
+        OpcodeX86_64::FramelessImmediate {
+            stack_size_in_bytes: 64,
+            saved_regs: [Some(Rbx), Some(R14), Some(R15), Some(Rbp), None, None],
+        }
+    );
+    assert_eq!(
+        eh_frame.compactify(Arch::X86_64, 0),
+        Err(NotCompactable::InvalidCfi)
+    );
+
+    // These functions store the frame record without setting up the frame
+    // pointer.
+    let data = read_fixture("fixtures/arm64/fp/query-api");
+    let eh_frame = eh_frame_section(&data);
+    for fde in [0x14, 0x34, 0x5c] {
+        assert_eq!(
+            eh_frame.compactify(Arch::Arm64, fde),
+            Err(NotCompactable::FramelessWithSavedRegisters)
+        );
+    }
+
+    let frame_record = |registers| CfiRow {
+        cfa: CfaRule::RegisterOffset {
+            register: 29,
+            offset: 16,
+        },
+        return_address: RegisterRule::AtCfaOffset(-8),
+        registers,
+    };
+    assert_eq!(
+        Arch::Arm64.compactify(&frame_record(vec![
+            (19, RegisterRule::AtCfaOffset(-24)),
+            (20, RegisterRule::AtCfaOffset(-32)),
+            (29, RegisterRule::AtCfaOffset(-16)),
+            (72, RegisterRule::AtCfaOffset(-40)),
+            (73, RegisterRule::AtCfaOffset(-48)),
+        ])),
+        Ok(0x0400_0021)
+    );
+    assert_eq!(
+        Arch::Arm64.compactify(&frame_record(vec![
+            (19, RegisterRule::AtCfaOffset(-32)),
+            (20, RegisterRule::AtCfaOffset(-24)),
+            (29, RegisterRule::AtCfaOffset(-16)),
+        ])),
+        Err(NotCompactable::NonStandardSaveOrder)
+    );
+    assert_eq!(
+        Arch::Arm64.compactify(&frame_record(vec![
+            (18, RegisterRule::AtCfaOffset(-24)),
+            (29, RegisterRule::AtCfaOffset(-16)),
+        ])),
+        Err(NotCompactable::UnsupportedRegister(18))
+    );
+
+    let frameless = |offset, registers| CfiRow {
+        cfa: CfaRule::RegisterOffset {
+            register: 7,
+            offset,
+        },
+        return_address: RegisterRule::AtCfaOffset(-8),
+        registers,
+    };
+    assert_eq!(
+        Arch::X86_64.compactify(&frameless(12, vec![])),
+        Err(NotCompactable::UnalignedCfaOffset(12))
+    );
+    assert_eq!(
+        Arch::X86_64.compactify(&frameless(24, vec![(0, RegisterRule::AtCfaOffset(-16))])),
+        Err(NotCompactable::UnsupportedRegister(0))
+    );
+    // rbx is not pushed right below the return address.
+    assert_eq!(
+        Arch::X86_64.compactify(&frameless(32, vec![(3, RegisterRule::AtCfaOffset(-24))])),
+        Err(NotCompactable::NonStandardSaveOrder)
+    );
+}
+
+#[test]
+fn test_owned_unwind_info() {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use macho_unwind_info::OwnedUnwindInfo;
+
+    fn assert_send_sync<T: Send + Sync + 'static>(_: &T) {}
+
+    let data = read_fixture("fixtures/x86_64/fp/libmozglue.dylib");
+    let section = unwind_info_section(&data);
+    let info = UnwindInfo::parse(section).unwrap();
+
+    let mut cache: HashMap<&str, OwnedUnwindInfo<Arc<[u8]>>> = HashMap::new();
+    cache.insert(
+        "libmozglue",
+        OwnedUnwindInfo::parse(Arc::from(section)).unwrap(),
+    );
+    let owned = cache["libmozglue"].clone();
+    assert_send_sync(&owned);
+    assert_eq!(owned.address_range(), info.address_range());
+
+    let thread = std::thread::spawn(move || {
+        let mut functions = Vec::new();
+        let mut iter = owned.functions();
+        while let Some(function) = iter.next().unwrap() {
+            assert_eq!(
+                owned.lookup(function.start_address),
+                Ok(Some(function.clone()))
+            );
+            functions.push(function);
+        }
+        functions
+    });
+    let mut iter = info.functions();
+    for function in thread.join().unwrap() {
+        assert_eq!(iter.next().unwrap(), Some(function));
+    }
+    assert_eq!(iter.next().unwrap(), None);
+
+    let owned = OwnedUnwindInfo::parse(section.to_vec()).unwrap();
+    assert_eq!(owned.lookup(0x1234).unwrap(), info.lookup(0x1234).unwrap());
+    assert!(OwnedUnwindInfo::parse(vec![0u8; 4]).is_err());
+}
+
+#[test]
+fn test_page_views() {
+    use macho_unwind_info::{OpcodeSource, PageView};
+
+    let data = read_fixture("fixtures/x86_64/fp/libmozglue.dylib");
+    let info = UnwindInfo::parse(unwind_info_section(&data)).unwrap();
+    let mut iter = info.functions();
+    let (mut global_count, mut local_count) = (0, 0);
+    for page_index in 0..info.page_count() {
+        let page = info.page(page_index).unwrap().unwrap();
+        assert!(matches!(page, PageView::Compressed(_)));
+        let range = page.address_range();
+        assert_eq!(info.page_index_of(range.start).unwrap(), Some(page_index));
+        assert_eq!(info.page_index_of(range.end - 1).unwrap(), Some(page_index));
+        for index in 0..page.len() {
+            let page_function = page.function_at(index).unwrap().unwrap();
+            let function = page_function.function;
+            assert_eq!(iter.next().unwrap().as_ref(), Some(&function));
+            assert_eq!(page.index_of(function.start_address).unwrap(), Some(index));
+            assert_eq!(
+                page.index_of(function.end_address - 1).unwrap(),
+                Some(index)
+            );
+            match page_function.opcode_source {
+                OpcodeSource::GlobalPalette { .. } => global_count += 1,
+                OpcodeSource::LocalPalette { .. } => local_count += 1,
+                OpcodeSource::Inline => panic!("inline opcode in a compressed page"),
+            }
+        }
+        assert_eq!(page.function_at(page.len()).unwrap(), None);
+        assert_eq!(page.index_of(range.end).unwrap(), None);
+    }
+    assert_eq!(iter.next().unwrap(), None);
+    assert!(info.page(info.page_count()).unwrap().is_none());
+    assert_eq!(info.page_index_of(info.address_range().end).unwrap(), None);
+    let stats = info.stats().unwrap();
+    assert_eq!(global_count, stats.global_palette_entry_count);
+    assert_eq!(local_count, stats.local_palette_entry_count);
+
+    let section = common::unwind_info(&[(0x1000, 0x0101_0000), (0x1010, 0x0200_0000)], 0x1020);
+    let info = UnwindInfo::parse(&section).unwrap();
+    assert_eq!(info.page_count(), 1);
+    let Some(PageView::Regular(page)) = info.page(0).unwrap() else {
+        panic!("expected a regular page");
+    };
+    assert_eq!(page.len(), 2);
+    assert_eq!(page.index_of(0x101f).unwrap(), Some(1));
+    let page_function = page.function_at(1).unwrap().unwrap();
+    assert_eq!(page_function.opcode_source, OpcodeSource::Inline);
+    assert_eq!(page_function.function.start_address, 0x1010);
+    assert_eq!(page_function.function.end_address, 0x1020);
+    assert_eq!(page_function.function.opcode, 0x0200_0000);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_objdump_listing() {
+    // Lines from `llvm-objdump --unwind-info fixtures/arm64/fp/query-api`.
+    let data = read_fixture("fixtures/arm64/fp/query-api.__unwind_info");
+    let info = UnwindInfo::parse(&data).unwrap();
+    let text = info.objdump_listing().unwrap().to_string();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3697);
+    assert_eq!(lines[0], "Contents of __unwind_info section:");
+    assert_eq!(
+        lines[4],
+        "  Personality function array section offset: 0x7c"
+    );
+    assert_eq!(lines[34], "    personality[1]: 0x00238000");
+    assert_eq!(
+        lines[35..37],
+        [
+            "  Top level indices: (count = 4)",
+            "    [0]: function offset=0x00000b64, 2nd level page offset=0x00002270, LSDA offset=0x000000b0",
+        ]
+    );
+    assert_eq!(
+        lines[40..42],
+        [
+            "  LSDA descriptors:",
+            "    [0]: function offset=0x000015a4, LSDA offset=0x001d3444",
+        ]
+    );
+    let page = lines
+        .iter()
+        .position(|line| line.starts_with("    Second level index[1]:"))
+        .unwrap();
+    assert_eq!(
+        lines[page..page + 4],
+        [
+            "    Second level index[1]: offset in section=0x00002b34, base function offset=0x0005e160",
+            "      Page encodings: (count = 1)",
+            "        encoding[24]: 0x02004000",
+            "      [0]: function offset=0x0005e160, encoding[7]=0x54000007",
+        ]
+    );
+
+    let data = common::unwind_info(&[(0x1000, 0x0400_0000), (0x1200, 0)], 0x1300);
+    let info = UnwindInfo::parse(&data).unwrap();
+    let text = info.objdump_listing().unwrap().to_string();
+    assert!(text.ends_with(
+        "  Second level indices:\n    \
+        Second level index[0]: offset in section=0x00000034, base function offset=0x00001000\n      \
+        [0]: function offset=0x00001000, encoding=0x04000000\n      \
+        [1]: function offset=0x00001200, encoding=0x00000000\n"
+    ));
+}
+
+#[test]
+fn test_error_context() {
+    use macho_unwind_info::{ErrorContext, ErrorKind, ReadError};
+
+    // Point entry 5 of the first page at an opcode index past both palettes.
+    let mut data = read_fixture("fixtures/arm64/fp/query-api.__unwind_info");
+    let page_offset = 0x2270;
+    let functions_offset = u16::from_le_bytes([data[page_offset + 4], data[page_offset + 5]]);
+    let entry_offset = page_offset + usize::from(functions_offset) + 5 * 4;
+    data[entry_offset + 3] = 0xff;
+    let info = UnwindInfo::parse(&data).unwrap();
+    let mut functions = info.functions();
+    for _ in 0..5 {
+        functions.next().unwrap();
+    }
+    let err = functions.next().unwrap_err();
+    assert_eq!(err, ErrorKind::ReadError(ReadError::LocalOpcodes));
+    assert_eq!(
+        *err.context(),
+        ErrorContext {
+            page_index: Some(0),
+            entry_index: Some(5),
+            ..Default::default()
+        }
+    );
+    let address =
+        0xb64 + (u32::from_le_bytes(data[entry_offset..][..4].try_into().unwrap()) & 0xff_ffff);
+    let err = info.lookup(address).unwrap_err();
+    assert_eq!(
+        *err.context(),
+        ErrorContext {
+            page_index: Some(0),
+            entry_index: Some(5),
+            address: Some(address),
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Read error: Could not read local opcodes (page 0, entry 5, looking up 0x{:x})",
+            address
+        )
+    );
+
+    // Cut off the last function entry of the regular page at offset 52.
+    let data = common::unwind_info(&[(0x1000, 0), (0x1100, 0), (0x1200, 0)], 0x1300);
+    let info = UnwindInfo::parse(&data[..data.len() - 8]).unwrap();
+    let err = info.lookup(0x1100).unwrap_err();
+    assert_eq!(err, ErrorKind::ReadError(ReadError::RegularPageFunctions));
+    assert_eq!(
+        *err.context(),
+        ErrorContext {
+            section_offset: Some(52 + 8 + 2 * 8),
+            page_index: Some(0),
+            entry_index: None,
+            address: Some(0x1100),
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "Read error: Could not read RegularPage functions (at section offset 0x4c, page 0, looking up 0x1100)"
+    );
+
+    // The first page entry claims to start before its first function.
+    let mut data = common::unwind_info(&[(0x1000, 0)], 0x1300);
+    data[28..32].copy_from_slice(&0xf00u32.to_le_bytes());
+    let info = UnwindInfo::parse(&data).unwrap();
+    let err = info.lookup(0xf80).unwrap_err();
+    assert_eq!(err, ErrorKind::InvalidPageEntryFirstAddress);
+    assert_eq!(err.context().page_index, Some(0));
+    assert_eq!(err.context().address, Some(0xf80));
+}
+
+#[test]
+fn test_lookup_details() {
+    use macho_unwind_info::raw::CompressedFunctionEntry;
+    use macho_unwind_info::{EndAddressSource, LookupDetails, OpcodeSource, PageKind};
+
+    // Values from `llvm-objdump --unwind-info fixtures/arm64/fp/query-api`.
+    let data = read_fixture("fixtures/arm64/fp/query-api.__unwind_info");
+    let info = UnwindInfo::parse(&data).unwrap();
+    let details = info.lookup_details(0x15b0).unwrap().unwrap();
+    assert_eq!(
+        details,
+        LookupDetails {
+            function: info.lookup(0x15b0).unwrap().unwrap(),
+            page_index: 0,
+            page_kind: PageKind::Compressed,
+            entry_index: 2,
+            compressed_entry: Some(CompressedFunctionEntry::new(0x07000a40)),
+            opcode_source: OpcodeSource::GlobalPalette { index: 7 },
+            end_address_source: EndAddressSource::NextEntry,
+            personality_index: Some(1),
+            personality: Some(0x00238000),
+            lsda: Some(0x001d3444),
+        }
+    );
+    assert_eq!(
+        details.to_string(),
+        "page 0 (compressed), entry 2\n\
+         raw entry 0x07000a40: opcode index 7, relative address 0xa40\n\
+         opcode 0x54000007 from the global palette at index 7\n\
+         function 0x000015a4..0x00001784, end address from the next entry\n\
+         personality slot 1: 0x00238000\n\
+         LSDA at 0x001d3444"
+    );
+
+    let details = info.lookup_details(0xfaf0).unwrap().unwrap();
+    assert_eq!(details.entry_index, 106);
+    assert_eq!(details.function.opcode, 0x03000014);
+    assert_eq!(
+        details.opcode_source,
+        OpcodeSource::LocalPalette { index: 3 }
+    );
+    assert_eq!(details.personality_index, None);
+    assert_eq!(details.lsda, None);
+
+    // The last entry of the first page ends where the second page starts.
+    let details = info.lookup_details(0x5c898).unwrap().unwrap();
+    assert_eq!(details.entry_index, 553);
+    assert_eq!(details.function.end_address, 0x5e160);
+    assert_eq!(details.end_address_source, EndAddressSource::NextPage);
+
+    let data = common::unwind_info(&[(0x1000, 0x04000000), (0x1200, 0)], 0x1300);
+    let info = UnwindInfo::parse(&data).unwrap();
+    let details = info.lookup_details(0x1100).unwrap().unwrap();
+    assert_eq!(details.page_kind, PageKind::Regular);
+    assert_eq!(details.compressed_entry, None);
+    assert_eq!(details.opcode_source, OpcodeSource::Inline);
+    assert_eq!(details.end_address_source, EndAddressSource::NextEntry);
+    assert_eq!(
+        info.lookup_details(0x1250)
+            .unwrap()
+            .unwrap()
+            .end_address_source,
+        EndAddressSource::NextPage
+    );
+    assert_eq!(info.lookup_details(0x1300).unwrap(), None);
+}
+
+#[test]
+fn test_functions_in_range() {
+    use macho_unwind_info::RangeExtent;
+
+    let data = read_fixture("fixtures/arm64/fp/query-api.__unwind_info");
+    let info = UnwindInfo::parse(&data).unwrap();
+    let mut all = Vec::new();
+    let mut iter = info.functions();
+    while let Some(function) = iter.next().unwrap() {
+        all.push(function);
+    }
+
+    let collect = |range: std::ops::Range<u32>, extent| {
+        let mut functions = Vec::new();
+        let mut iter = info.functions_in_range(range, extent).unwrap();
+        while let Some(function) = iter.next().unwrap() {
+            functions.push(function);
+        }
+        functions
+    };
+    // Within a page, across the boundary between the first two pages at
+    // 0x5e160, and past both ends of the covered range.
+    for range in [
+        0x15b0..0x4000,
+        0x5c000..0x60000,
+        0x5e160..0x5e161,
+        0x0..0x1000,
+        0x0..u32::MAX,
+    ] {
+        let full: Vec<_> = all
+            .iter()
+            .filter(|f| f.start_address < range.end && f.end_address > range.start)
+            .cloned()
+            .collect();
+        assert_eq!(collect(range.clone(), RangeExtent::Full), full);
+        let clipped: Vec<_> = full
+            .into_iter()
+            .map(|mut f| {
+                f.start_address = f.start_address.max(range.start);
+                f.end_address = f.end_address.min(range.end);
+                f
+            })
+            .collect();
+        assert_eq!(collect(range, RangeExtent::Clipped), clipped);
+    }
+
+    let function = info.lookup(0x5c8a0).unwrap().unwrap();
+    assert_eq!(collect(0x5c8a0..0x5c8a1, RangeExtent::Full), vec![function]);
+    assert!(collect(0x4000..0x4000, RangeExtent::Full).is_empty());
+    assert!(collect(0x10000000..0x10000010, RangeExtent::Full).is_empty());
+}
+
+#[test]
+fn test_function_iter_double_ended() {
+    let data = read_fixture("fixtures/arm64/fp/query-api.__unwind_info");
+    let info = UnwindInfo::parse(&data).unwrap();
+    let mut all = Vec::new();
+    let mut iter = info.functions();
+    while let Some(function) = iter.next().unwrap() {
+        all.push(function);
+    }
+    assert!(info.page_count() > 1);
+
+    let mut reversed = Vec::new();
+    let mut iter = info.functions();
+    while let Some(function) = iter.next_back().unwrap() {
+        reversed.push(function);
+    }
+    reversed.reverse();
+    assert_eq!(reversed, all);
+
+    // Alternate between the ends until they meet, within a page and across
+    // page boundaries.
+    for front_count in [0, 1, 553, 554, 555, all.len() - 1, all.len()] {
+        let mut iter = info.functions();
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        for _ in 0..front_count {
+            front.push(iter.next().unwrap().unwrap());
+        }
+        while let Some(function) = iter.next_back().unwrap() {
+            back.push(function);
+            if let Some(function) = iter.next().unwrap() {
+                front.push(function);
+            }
+        }
+        assert_eq!(iter.next().unwrap(), None);
+        back.reverse();
+        front.extend(back);
+        assert_eq!(front, all);
+    }
+
+    for n in [
+        0,
+        1,
+        553,
+        554,
+        1000,
+        all.len() - 1,
+        all.len(),
+        all.len() + 1,
+    ] {
+        assert_eq!(info.functions().nth(n).unwrap().as_ref(), all.get(n));
+        let mut iter = info.functions().skip(n).unwrap();
+        assert_eq!(iter.next().unwrap().as_ref(), all.get(n));
+    }
+
+    // Skipping into a page which was started from the back.
+    let mut iter = info.functions();
+    let last = iter.next_back().unwrap();
+    assert_eq!(last.as_ref(), all.last());
+    assert_eq!(
+        iter.nth(all.len() - 2).unwrap().as_ref(),
+        all.get(all.len() - 2)
+    );
+    assert_eq!(iter.next().unwrap(), None);
+    let mut iter = info.functions();
+    iter.next_back().unwrap();
+    assert_eq!(iter.nth(all.len() - 1).unwrap(), None);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_functions() {
+    use rayon::iter::ParallelIterator;
+
+    for (path, is_section) in [
+        ("fixtures/arm64/fp/query-api.__unwind_info", true),
+        ("fixtures/x86_64/fp/libmozglue.dylib", false),
+    ] {
+        let data = read_fixture(path);
+        let section = if is_section {
+            &data[..]
+        } else {
+            unwind_info_section(&data)
+        };
+        let info = UnwindInfo::parse(section).unwrap();
+        let mut expected = Vec::new();
+        let mut iter = info.functions();
+        while let Some(function) = iter.next().unwrap() {
+            expected.push(function);
+        }
+        let functions: Result<Vec<_>, _> = info.par_functions().collect();
+        assert_eq!(functions.unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_lookup_decoded() {
+    use macho_unwind_info::opcodes::{
+        Arm64, CompactArch, CompactOpcode, ReturnAddressLocation, SavedRegister, X86_64,
+    };
+
+    let data = read_fixture("fixtures/arm64/fp/query-api.__unwind_info");
+    let info = UnwindInfo::parse(&data).unwrap();
+    let (function, opcode) = info.lookup_decoded::<Arm64>(0x4000).unwrap().unwrap();
+    assert_eq!(Some(function.clone()), info.lookup(0x4000).unwrap());
+    assert_eq!(Arm64::ARCH, Arch::Arm64);
+    assert!(opcode.is_frame_based());
+    assert!(!opcode.is_dwarf());
+    assert_eq!(opcode.eh_frame_fde(), None);
+    assert_eq!(
+        opcode.return_address_location(),
+        Some(ReturnAddressLocation::AtCfaOffset(-8))
+    );
+    let saved: Vec<(u16, i64)> = opcode
+        .saved_registers()
+        .iter()
+        .map(|saved| (saved.dwarf_number, saved.cfa_offset))
+        .collect();
+    assert_eq!(
+        saved,
+        [
+            (19, -24),
+            (20, -32),
+            (21, -40),
+            (22, -48),
+            (23, -56),
+            (24, -64),
+            (29, -16)
+        ]
+    );
+    assert_eq!(info.lookup_decoded::<Arm64>(0).unwrap(), None);
+
+    // Dwarf, frameless immediate with rbx, and null entries.
+    let data = common::unwind_info(
+        &[(0x1000, 0x0400_0123), (0x1010, 0x0203_0400), (0x1020, 0)],
+        0x1030,
+    );
+    let info = UnwindInfo::parse(&data).unwrap();
+    let (_, opcode) = info.lookup_decoded::<X86_64>(0x1000).unwrap().unwrap();
+    assert!(opcode.is_dwarf());
+    assert_eq!(opcode.eh_frame_fde(), Some(0x123));
+    assert_eq!(opcode.return_address_location(), None);
+    assert!(opcode.saved_registers().is_empty());
+    let (_, opcode) = info.lookup_decoded::<X86_64>(0x1010).unwrap().unwrap();
+    assert!(!opcode.is_frame_based());
+    assert_eq!(
+        opcode.return_address_location(),
+        Some(ReturnAddressLocation::AtCfaOffset(-8))
+    );
+    assert_eq!(
+        &opcode.saved_registers()[..],
+        [SavedRegister {
+            dwarf_number: 3,
+            cfa_offset: -16
+        }]
+    );
+    let (_, opcode) = info.lookup_decoded::<X86_64>(0x1020).unwrap().unwrap();
+    assert!(opcode.is_null());
+    assert_eq!(opcode.return_address_location(), None);
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use macho_unwind_info::stack_walker::FunctionLookup;
+use macho_unwind_info::{Function, Module, ModuleMap, OwnedUnwindInfo};
+
+mod common;
+use common::unwind_info;
+
+/// A module whose unwind info covers 0x1000..0x1300 relative to its mach-O
+/// header, with a `__TEXT` segment of 0x4000 bytes.
+fn module(text_vmaddr: u64, slide: u64) -> Module {
+    let data: Arc<[u8]> = unwind_info(&[(0x1000, 0x04000000), (0x1200, 0)], 0x1300).into();
+    Module::new(
+        OwnedUnwindInfo::parse(data).unwrap(),
+        text_vmaddr,
+        0x4000,
+        slide,
+    )
+}
+
+fn function(start_address: u32, end_address: u32, opcode: u32) -> Function {
+    Function {
+        start_address,
+        end_address,
+        opcode,
+    }
+}
+
+#[test]
+fn test_lookup() {
+    let map = ModuleMap::new();
+    let a = map.add(module(0x1_0000_0000, 0x5000));
+    let b = map.add(module(0x7ff8_0000_0000, 0));
+    assert_eq!(map.len(), 2);
+
+    let lookup = map.lookup(0x1_0000_6100).unwrap().unwrap();
+    assert_eq!(lookup.id, a);
+    assert_eq!(lookup.module.base_address(), 0x1_0000_5000);
+    assert_eq!(lookup.function, Some(function(0x1000, 0x1200, 0x04000000)));
+
+    let lookup = map.lookup(0x7ff8_0000_1250).unwrap().unwrap();
+    assert_eq!(lookup.id, b);
+    assert_eq!(lookup.function, Some(function(0x1200, 0x1300, 0)));
+
+    // In the __TEXT segment, but outside of the unwind info's range.
+    let lookup = map.lookup(0x1_0000_8000).unwrap().unwrap();
+    assert_eq!(lookup.id, a);
+    assert_eq!(lookup.function, None);
+
+    // Before, between and after the modules.
+    assert!(map.lookup(0x1000).unwrap().is_none());
+    assert!(map.lookup(0x1_0000_9000).unwrap().is_none());
+    assert!(map.lookup(0x7ff8_0000_4000).unwrap().is_none());
+
+    assert_eq!(
+        map.lookup_function(0x7ff8_0000_1000).unwrap(),
+        Some((0x7ff8_0000_0000, function(0x1000, 0x1200, 0x04000000)))
+    );
+    assert_eq!(map.lookup_function(0x1_0000_8000).unwrap(), None);
+}
+
+#[test]
+fn test_unloaded_and_overlapping_modules() {
+    let map = ModuleMap::new();
+    let a = map.add(module(0x1_0000_0000, 0));
+    let b = map.add(module(0x1_0000_4000, 0));
+    assert_eq!(map.lookup(0x1_0000_1000).unwrap().unwrap().id, a);
+    assert_eq!(map.lookup(0x1_0000_5000).unwrap().unwrap().id, b);
+
+    let removed = map.remove(a).unwrap();
+    assert_eq!(removed.base_address(), 0x1_0000_0000);
+    assert!(map.remove(a).is_none());
+    assert!(map.get(a).is_none());
+    assert!(map.lookup(0x1_0000_1000).unwrap().is_none());
+    assert_eq!(map.lookup(0x1_0000_5000).unwrap().unwrap().id, b);
+
+    // A module loaded over b's range, whose unload was missed, replaces b.
+    let c = map.add(module(0x1_0000_0000, 0x2000));
+    assert!(map.get(b).is_none());
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.lookup(0x1_0000_5000).unwrap().unwrap().id, c);
+    assert!(map.lookup(0x1_0000_6000).unwrap().is_none());
+
+    // Reloading at the same address gives a new id.
+    map.remove(c);
+    assert!(map.is_empty());
+    let d = map.add(module(0x1_0000_0000, 0x2000));
+    assert_ne!(c, d);
+    assert_eq!(map.lookup(0x1_0000_3000).unwrap().unwrap().id, d);
+}
+
+#[test]
+fn test_concurrent_lookups() {
+    let map = ModuleMap::new();
+    let stable = map.add(module(0x2_0000_0000, 0));
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..1000 {
+                    let lookup = map.lookup(0x2_0000_1000).unwrap().unwrap();
+                    assert_eq!(lookup.id, stable);
+                    if let Some(lookup) = map.lookup(0x1_0000_1000).unwrap() {
+                        assert_eq!(lookup.function.unwrap().start_address, 0x1000);
+                    }
+                }
+            });
+        }
+        scope.spawn(|| {
+            for _ in 0..1000 {
+                let id = map.add(module(0x1_0000_0000, 0));
+                assert!(map.remove(id).is_some());
+            }
+        });
+    });
+    assert_eq!(map.len(), 1);
+}
@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+
+use macho_unwind_info::stack_walker::{
+    FallbackPolicy, FallbackRule, ImageUnwindInfo, MissingUnwindInfo, RegsArm64, RegsX86,
+    RegsX86_64, StackWalkError, StackWalker, UnwindMethod, UnwindRegs,
+};
+use macho_unwind_info::{Arch, UnwindInfo};
+
+mod common;
+use common::unwind_info;
+
+const BASE: u64 = 0x1_0000_0000;
+
+/// A sparse memory image, read in 8-byte little-endian words.
+#[derive(Default)]
+struct Memory(HashMap<u64, u8>);
+
+impl Memory {
+    fn write(&mut self, address: u64, value: u64) {
+        for (i, byte) in value.to_le_bytes().iter().enumerate() {
+            self.0.insert(address + i as u64, *byte);
+        }
+    }
+
+    fn reader(&self) -> impl FnMut(u64) -> Option<u64> + '_ {
+        move |address| {
+            let mut bytes = [0; 8];
+            for (i, byte) in bytes.iter_mut().enumerate() {
+                *byte = *self.0.get(&(address + i as u64))?;
+            }
+            Some(u64::from_le_bytes(bytes))
+        }
+    }
+}
+
+fn walk<Regs: UnwindRegs>(
+    info: &UnwindInfo,
+    memory: &Memory,
+    regs: Regs,
+) -> (Vec<(u64, UnwindMethod)>, Option<StackWalkError>) {
+    let lookup = ImageUnwindInfo {
+        base_address: BASE,
+        unwind_info: info,
+    };
+    let mut walker = StackWalker::new(lookup, memory.reader(), regs);
+    let mut frames = Vec::new();
+    loop {
+        match walker.next() {
+            Ok(Some(frame)) => frames.push((frame.regs.pc(), frame.unwound_by)),
+            Ok(None) => return (frames, None),
+            Err(err) => return (frames, Some(err)),
+        }
+    }
+}
+
+#[test]
+fn test_x86_64() {
+    // 0x1000: frame-based, rbx saved at rbp-8.
+    // 0x1100: frameless, 32 bytes of stack including the return address, r12 saved.
+    // 0x1200: frameless indirect, `sub rsp, imm32` with the immediate at +0x10,
+    //         plus 8 bytes for one pushed register (r13).
+    // 0x1300: dwarf.
+    let info = unwind_info(
+        &[
+            (0x1000, 0x0101_0001),
+            (0x1100, 0x0204_0401),
+            (0x1200, 0x0310_2402),
+            (0x1300, 0x0400_0040),
+        ],
+        0x1400,
+    );
+    let info = UnwindInfo::parse(&info).unwrap();
+    let mut memory = Memory::default();
+    memory.write(BASE + 0x1210, 0x18);
+
+    // Frame 0 in 0x1200, rsp = 0x8000. Stack size 0x18 + 8 = 0x20.
+    memory.write(0x8010, 0xd13); // r13
+    memory.write(0x8018, BASE + 0x1150); // return address into 0x1100
+                                         // Frame 1 in 0x1100, rsp = 0x8020. Stack size 0x20.
+    memory.write(0x8030, 0xd12); // r12
+    memory.write(0x8038, BASE + 0x1020); // return address into 0x1000
+                                         // Frame 2 in 0x1000, rsp = 0x8040, rbp = 0x8050.
+    memory.write(0x8048, 0xdb); // rbx
+    memory.write(0x8050, 0x8070); // caller rbp
+    memory.write(0x8058, BASE + 0x1310); // return address into 0x1300
+
+    let regs = RegsX86_64 {
+        rip: BASE + 0x1220,
+        rsp: 0x8000,
+        rbp: 0x8050,
+        ..Default::default()
+    };
+    let (frames, err) = walk(&info, &memory, regs);
+    assert_eq!(
+        frames,
+        [
+            (BASE + 0x1220, UnwindMethod::InitialRegisters),
+            (BASE + 0x1150, UnwindMethod::FramelessIndirect),
+            (BASE + 0x1020, UnwindMethod::FramelessImmediate),
+            (BASE + 0x1310, UnwindMethod::FramePointer),
+        ]
+    );
+    assert_eq!(
+        err,
+        Some(StackWalkError::NeedsDwarf {
+            address: BASE + 0x130f,
+            eh_frame_fde: 0x40
+        })
+    );
+}
+
+#[test]
+fn test_x86_64_saved_registers() {
+    let info = unwind_info(&[(0x1000, 0x0101_0001), (0x1100, 0x0204_0401)], 0x1200);
+    let info = UnwindInfo::parse(&info).unwrap();
+    let mut memory = Memory::default();
+    memory.write(0x8010, 0xd12);
+    memory.write(0x8018, BASE + 0x1020);
+    memory.write(0x8028, 0xdb);
+    memory.write(0x8030, 0);
+    memory.write(0x8038, 0);
+
+    let regs = RegsX86_64 {
+        rip: BASE + 0x1100,
+        rsp: 0x8000,
+        rbp: 0x8030,
+        ..Default::default()
+    };
+    let lookup = ImageUnwindInfo {
+        base_address: BASE,
+        unwind_info: &info,
+    };
+    let mut walker = StackWalker::new(lookup, memory.reader(), regs);
+    walker.next().unwrap().unwrap();
+    let frame = walker.next().unwrap().unwrap();
+    assert_eq!(frame.regs.r12, Some(0xd12));
+    assert_eq!(frame.regs.rbx, None);
+    assert_eq!(frame.lookup_address, BASE + 0x101f);
+    assert_eq!(walker.next().unwrap(), None);
+}
+
+#[test]
+fn test_x86_frame_based() {
+    // 0x1000: frame-based, ebx saved at ebp-4.
+    let info = unwind_info(&[(0x1000, 0x0101_0001)], 0x1100);
+    let info = UnwindInfo::parse(&info).unwrap();
+    let mut memory = Memory::default();
+    memory.write(0x8000, 0xdb);
+    memory.write(0x8004, 0x8100);
+    memory.write(0x8008, 0x1080);
+
+    let regs = RegsX86 {
+        eip: (BASE + 0x1010) as u32,
+        esp: 0x7ff0,
+        ebp: 0x8004,
+        ..Default::default()
+    };
+    let lookup = ImageUnwindInfo {
+        base_address: 0,
+        unwind_info: &info,
+    };
+    let mut walker = StackWalker::new(lookup, memory.reader(), regs);
+    walker.next().unwrap().unwrap();
+    let frame = walker.next().unwrap().unwrap();
+    assert_eq!(frame.regs.eip, 0x1080);
+    assert_eq!(frame.regs.esp, 0x800c);
+    assert_eq!(frame.regs.ebp, 0x8100);
+    assert_eq!(frame.regs.ebx, Some(0xdb));
+}
+
+#[test]
+fn test_arm64() {
+    // 0x1000: frame-based, x19/x20 saved.
+    // 0x1100: frameless, no stack.
+    let info = unwind_info(&[(0x1000, 0x0400_0001), (0x1100, 0x0200_0000)], 0x1200);
+    let info = UnwindInfo::parse(&info).unwrap();
+    let mut memory = Memory::default();
+    memory.write(0x8010, 0x20); // x20
+    memory.write(0x8018, 0x19); // x19
+    memory.write(0x8020, 0x8040); // caller fp
+    memory.write(0x8028, BASE + 0x1010); // return address into 0x1000
+    memory.write(0x8030, 0);
+    memory.write(0x8038, 0);
+    memory.write(0x8040, 0);
+    memory.write(0x8048, 0);
+
+    let regs = RegsArm64 {
+        pc: BASE + 0x1104,
+        sp: 0x8010,
+        fp: 0x8020,
+        lr: Some(BASE + 0x1040),
+        ..Default::default()
+    };
+    let lookup = ImageUnwindInfo {
+        base_address: BASE,
+        unwind_info: &info,
+    };
+    let mut walker = StackWalker::new(lookup, memory.reader(), regs);
+    walker.next().unwrap().unwrap();
+    let frame = walker.next().unwrap().unwrap();
+    assert_eq!(frame.unwound_by, UnwindMethod::FramelessLr);
+    assert_eq!((frame.regs.pc, frame.regs.sp), (BASE + 0x1040, 0x8010));
+    let frame = walker.next().unwrap().unwrap();
+    assert_eq!(frame.unwound_by, UnwindMethod::FramePointer);
+    assert_eq!((frame.regs.pc, frame.regs.sp), (BASE + 0x1010, 0x8030));
+    assert_eq!(frame.regs.x19_to_x28[..2], [Some(0x19), Some(0x20)]);
+    assert_eq!(frame.regs.lr, None);
+
+    // The outermost frame record has a zero return address.
+    assert_eq!(walker.next().unwrap(), None);
+}
+
+#[test]
+fn test_arm64_frameless_needs_lr() {
+    let info = unwind_info(&[(0x1000, 0x0400_0000), (0x1100, 0x0200_0000)], 0x1200);
+    let info = UnwindInfo::parse(&info).unwrap();
+    let mut memory = Memory::default();
+    memory.write(0x8000, 0x8100);
+    memory.write(0x8008, BASE + 0x1110); // return address into the frameless function
+
+    let regs = RegsArm64 {
+        pc: BASE + 0x1000,
+        sp: 0x7ff0,
+        fp: 0x8000,
+        ..Default::default()
+    };
+    let (frames, err) = walk(&info, &memory, regs);
+    assert_eq!(frames.len(), 2);
+    assert_eq!(err, Some(StackWalkError::LrUnknown));
+}
+
+#[test]
+fn test_corrupted_stacks() {
+    let info = unwind_info(&[(0x1000, 0x0400_0000), (0x1100, 0x0200_0000)], 0x1200);
+    let info = UnwindInfo::parse(&info).unwrap();
+
+    // A frame pointer which points to itself.
+    let mut memory = Memory::default();
+    memory.write(0x8000, 0x7ff0);
+    memory.write(0x8008, BASE + 0x1010);
+    memory.write(0x7ff0, 0x8000);
+    memory.write(0x7ff8, BASE + 0x1010);
+    let regs = RegsArm64 {
+        pc: BASE + 0x1000,
+        sp: 0x7fe0,
+        fp: 0x8000,
+        ..Default::default()
+    };
+    let (frames, err) = walk(&info, &memory, regs.clone());
+    assert_eq!(frames.len(), 2);
+    assert_eq!(
+        err,
+        Some(StackWalkError::NonIncreasingSp {
+            callee_sp: 0x8010,
+            caller_sp: 0x8000
+        })
+    );
+
+    // A frame pointer outside of the readable memory.
+    let regs = RegsArm64 { fp: 0x9000, ..regs };
+    let (frames, err) = walk(&info, &memory, regs.clone());
+    assert_eq!(frames.len(), 1);
+    assert_eq!(err, Some(StackWalkError::MemoryReadFailed(0x9000)));
+
+    // A frameless leaf function whose lr points back into itself.
+    let regs = RegsArm64 {
+        pc: BASE + 0x1104,
+        lr: Some(BASE + 0x1104),
+        ..regs
+    };
+    let (frames, err) = walk(&info, &memory, regs.clone());
+    assert_eq!(frames.len(), 1);
+    assert_eq!(err, Some(StackWalkError::Loop(BASE + 0x1104)));
+
+    // No unwind info for the address.
+    let regs = RegsArm64 {
+        pc: BASE + 0x2000,
+        ..regs
+    };
+    let (frames, err) = walk(&info, &memory, regs);
+    assert_eq!(frames.len(), 1);
+    assert_eq!(err, Some(StackWalkError::NoUnwindInfo(BASE + 0x2000)));
+}
+
+#[test]
+fn test_hostile_base_address() {
+    use macho_unwind_info::stack_walker::FunctionLookup;
+    use macho_unwind_info::{Error, Function};
+
+    /// Reports a frameless indirect function at a base address which makes
+    /// the function's absolute address overflow.
+    struct HostileLookup;
+
+    impl FunctionLookup for HostileLookup {
+        fn lookup_function(&self, _address: u64) -> Result<Option<(u64, Function)>, Error> {
+            let function = Function {
+                start_address: 0x1200,
+                end_address: 0x1300,
+                opcode: 0x0310_2402,
+            };
+            Ok(Some((u64::MAX - 0xff, function)))
+        }
+    }
+
+    let memory = Memory::default();
+    let regs = RegsX86_64 {
+        rip: 0x1220,
+        rsp: 0x8000,
+        ..Default::default()
+    };
+    let mut walker = StackWalker::new(HostileLookup, memory.reader(), regs);
+    assert!(walker.next().unwrap().is_some());
+    // The `sub` immediate is read from the wrapped-around address.
+    assert_eq!(
+        walker.next().err(),
+        Some(StackWalkError::MemoryReadFailed(0x1110))
+    );
+}
+
+#[test]
+fn test_max_frames() {
+    let info = unwind_info(&[(0x1000, 0x0400_0000)], 0x1100);
+    let info = UnwindInfo::parse(&info).unwrap();
+    let mut memory = Memory::default();
+    for fp in (0x8000..0x8100).step_by(16) {
+        memory.write(fp, fp + 16);
+        memory.write(fp + 8, BASE + 0x1010);
+    }
+    let regs = RegsArm64 {
+        pc: BASE + 0x1000,
+        sp: 0x7ff0,
+        fp: 0x8000,
+        ..Default::default()
+    };
+    let lookup = ImageUnwindInfo {
+        base_address: BASE,
+        unwind_info: &info,
+    };
+    let mut walker = StackWalker::new(lookup, memory.reader(), regs);
+    walker.set_max_frames(4);
+    for _ in 0..4 {
+        assert!(walker.next().unwrap().is_some());
+    }
+    assert_eq!(walker.next(), Err(StackWalkError::TooManyFrames(4)));
+    assert_eq!(walker.next(), Ok(None));
+}
+
+#[test]
+fn test_fallback_policy_rules() {
+    use FallbackRule::*;
+    use MissingUnwindInfo::*;
+
+    let policy = FallbackPolicy::default();
+    assert_eq!(policy.rule(Arch::Arm64, NotCovered, true), Stop);
+
+    let policy = FallbackPolicy::FRAME_POINTER;
+    assert_eq!(policy.rule(Arch::X86_64, NotCovered, true), FramePointer);
+    assert_eq!(
+        policy.rule(Arch::Arm64, NullOpcode, true),
+        ReturnAddressInLr
+    );
+    assert_eq!(policy.rule(Arch::Arm64, NullOpcode, false), FramePointer);
+
+    // The lr rule only applies where lr is known.
+    let policy = FallbackPolicy {
+        unrecognized_kind: ReturnAddressInLr,
+        ..FallbackPolicy::STOP
+    };
+    assert_eq!(
+        policy.rule(Arch::Arm64, UnrecognizedKind(1), true),
+        ReturnAddressInLr
+    );
+    assert_eq!(policy.rule(Arch::Arm64, UnrecognizedKind(1), false), Stop);
+    assert_eq!(policy.rule(Arch::X86_64, UnrecognizedKind(5), true), Stop);
+    assert_eq!(policy.rule(Arch::X86_64, NotCovered, true), Stop);
+
+    assert_eq!(
+        Arch::Arm64.missing_unwind_info(0x0100_0000),
+        Some(UnrecognizedKind(1))
+    );
+    assert_eq!(Arch::X86.missing_unwind_info(0x4000_0000), Some(NullOpcode));
+    assert_eq!(Arch::X86_64.missing_unwind_info(0x0400_0040), None);
+}
+
+#[test]
+fn test_x86_64_fallback() {
+    // 0x1000: frame-based. 0x1100: null opcode.
+    let info = unwind_info(&[(0x1000, 0x0101_0000), (0x1100, 0)], 0x1200);
+    let info = UnwindInfo::parse(&info).unwrap();
+    let mut memory = Memory::default();
+    // Frame 0 at 0x2000, outside of the unwind info, with a frame record.
+    memory.write(0x8000, 0x8020);
+    memory.write(0x8008, BASE + 0x1110); // return address into 0x1100
+    memory.write(0x8020, 0x8040);
+    memory.write(0x8028, BASE + 0x1010); // return address into 0x1000
+    memory.write(0x8040, 0);
+    memory.write(0x8048, 0);
+
+    let regs = RegsX86_64 {
+        rip: BASE + 0x2000,
+        rsp: 0x7ff0,
+        rbp: 0x8000,
+        rbx: Some(0xdb),
+        ..Default::default()
+    };
+    let (frames, err) = walk(&info, &memory, regs.clone());
+    assert_eq!(frames.len(), 1);
+    assert_eq!(err, Some(StackWalkError::NoUnwindInfo(BASE + 0x2000)));
+
+    let lookup = ImageUnwindInfo {
+        base_address: BASE,
+        unwind_info: &info,
+    };
+    let mut walker = StackWalker::new(lookup, memory.reader(), regs);
+    walker.set_fallback_policy(FallbackPolicy::FRAME_POINTER);
+    assert!(!walker.next().unwrap().unwrap().unwound_by.is_heuristic());
+    let frame = walker.next().unwrap().unwrap();
+    assert_eq!(
+        frame.unwound_by,
+        UnwindMethod::Fallback {
+            rule: FallbackRule::FramePointer,
+            reason: MissingUnwindInfo::NotCovered
+        }
+    );
+    assert!(frame.unwound_by.is_heuristic());
+    assert_eq!((frame.regs.rip, frame.regs.rsp), (BASE + 0x1110, 0x8010));
+    assert_eq!(frame.regs.rbx, None);
+    let frame = walker.next().unwrap().unwrap();
+    assert_eq!(
+        frame.unwound_by,
+        UnwindMethod::Fallback {
+            rule: FallbackRule::FramePointer,
+            reason: MissingUnwindInfo::NullOpcode
+        }
+    );
+    assert_eq!(frame.regs.rip, BASE + 0x1010);
+    // The frame-based function's frame record ends the stack.
+    assert_eq!(walker.next().unwrap(), None);
+}
+
+#[test]
+fn test_arm64_fallback() {
+    // 0x1000: frame-based. 0x1100: an opcode with the undefined kind 1.
+    let info = unwind_info(&[(0x1000, 0x0400_0000), (0x1100, 0x0100_0000)], 0x1200);
+    let info = UnwindInfo::parse(&info).unwrap();
+    let mut memory = Memory::default();
+    memory.write(0x8000, 0x8020);
+    memory.write(0x8008, BASE + 0x1010); // return address into 0x1000
+    memory.write(0x8020, 0);
+    memory.write(0x8028, 0);
+
+    // The innermost frame is in 0x1100, a leaf function called from 0x1100.
+    let regs = RegsArm64 {
+        pc: BASE + 0x1100,
+        sp: 0x7ff0,
+        fp: 0x8000,
+        lr: Some(BASE + 0x1180),
+        ..Default::default()
+    };
+    let (frames, err) = walk(&info, &memory, regs.clone());
+    assert_eq!(frames.len(), 1);
+    assert_eq!(
+        err,
+        Some(StackWalkError::InvalidOpcode {
+            address: BASE + 0x1100,
+            opcode: 0x0100_0000
+        })
+    );
+
+    let lookup = ImageUnwindInfo {
+        base_address: BASE,
+        unwind_info: &info,
+    };
+    let mut walker = StackWalker::new(lookup, memory.reader(), regs);
+    walker.set_fallback_policy(FallbackPolicy::FRAME_POINTER);
+    walker.next().unwrap().unwrap();
+    let frame = walker.next().unwrap().unwrap();
+    assert_eq!(
+        frame.unwound_by,
+        UnwindMethod::Fallback {
+            rule: FallbackRule::ReturnAddressInLr,
+            reason: MissingUnwindInfo::UnrecognizedKind(1)
+        }
+    );
+    assert_eq!((frame.regs.pc, frame.regs.sp), (BASE + 0x1180, 0x7ff0));
+    assert_eq!(frame.regs.lr, None);
+    // Not the innermost frame anymore, so the frame record is used.
+    let frame = walker.next().unwrap().unwrap();
+    assert_eq!(
+        frame.unwound_by,
+        UnwindMethod::Fallback {
+            rule: FallbackRule::FramePointer,
+            reason: MissingUnwindInfo::UnrecognizedKind(1)
+        }
+    );
+    assert_eq!((frame.regs.pc, frame.regs.sp), (BASE + 0x1010, 0x8010));
+    assert_eq!(walker.next().unwrap(), None);
+}